@@ -1,19 +1,106 @@
 
+use rust_decimal::Decimal;
+
 pub static PROJECT_NAME: &str = "garbage_collector";
+pub static NATIVE_TOKEN_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
 
 #[derive(Debug, Clone)]
 pub struct Env {
     pub debug: bool,
+    // Price sources in priority order, e.g. "defillama,coingecko,dex"
+    pub price_sources: Vec<String>,
+    pub coingecko_api_key: String,
+    // Seconds a cached price stays fresh, 0 disables the cache
+    pub price_cache_max_age: u64,
+    // Value balances at this date (YYYY-MM-DD) instead of current prices
+    pub price_date: String,
+    // Currency reports are shown in, a fiat code like EUR or a crypto unit like ETH
+    pub report_currency: String,
+    pub fx_api_url: String,
+    // USD value below which a balance counts as dust
+    pub dust_threshold: Decimal,
+    // SQLite results database, only used with the sqlite feature
+    pub results_db: String,
+    // USD value range of the balances a sweep swaps to native
+    pub sweep_min_value: Decimal,
+    pub sweep_max_value: Decimal,
+    // Percent a balance or quote may drift between planning and executing a sweep
+    pub sweep_drift_tolerance: Decimal,
+    // Simulate approvals and swaps instead of sending them
+    pub dry_run: bool,
+    // Days of Approval logs an allowance audit reads, turned into blocks with each chain's block time
+    pub allowance_log_days: u64,
+    // Aggregators a swap is quoted on, the best output after gas wins
+    pub swap_providers: Vec<String>,
+    pub oneinch_api_key: String,
+    pub zerox_api_key: String,
+    // Address native balances are consolidated to
+    pub collector_address: String,
+    // Per-chain destinations of forwarded tokens as "Base=0x...,Arbitrum=0x...", the collector elsewhere
+    pub forward_destinations: String,
+    // USD value of native a consolidation leaves on each wallet and chain
+    pub consolidation_reserve_value: Decimal,
+    // USD value below which a consolidation transfer isn't sent
+    pub consolidation_min_value: Decimal,
+    // Bridges native balances are quoted on, the best amount arriving after fees wins
+    pub bridge_providers: Vec<String>,
+    // Chain swept native balances are bridged to
+    pub bridge_target_chain: String,
+    // USD value below which a native balance isn't bridged
+    pub bridge_min_value: Decimal,
+    // Bridges costing more than this percent of the amount are skipped
+    pub bridge_max_fee_percent: Decimal,
 }
 
 pub fn get_env(key: &str) -> String {
     std::env::var(key).unwrap_or(String::from(""))
 }
 
+// Parse a comma separated env value, falling back to defaults when it is empty
+pub fn parse_list(value: &str, defaults: &[&str]) -> Vec<String> {
+    let list: Vec<String> = value
+        .split(',')
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if list.is_empty() {
+        defaults.iter().map(|v| v.to_string()).collect()
+    } else {
+        list
+    }
+}
+
 impl Env {
     pub fn new() -> Self {
         Env {
             debug: get_env("DEBUG").parse::<bool>().unwrap(),
+            price_sources: parse_list(&get_env("PRICE_SOURCES"), &["defillama", "coingecko", "dex"]),
+            coingecko_api_key: get_env("COINGECKO_API_KEY"),
+            price_cache_max_age: get_env("PRICE_CACHE_MAX_AGE").parse::<u64>().unwrap_or(900),
+            price_date: get_env("PRICE_DATE"),
+            report_currency: get_env("REPORT_CURRENCY"),
+            fx_api_url: match get_env("FX_API_URL") {
+                url if url.is_empty() => "https://api.frankfurter.app/latest?from=USD".to_owned(),
+                url => url,
+            },
+            dust_threshold: get_env("DUST_THRESHOLD").parse::<Decimal>().unwrap_or(Decimal::ONE),
+            results_db: get_env("RESULTS_DB"),
+            sweep_min_value: get_env("SWEEP_MIN_VALUE").parse::<Decimal>().unwrap_or(Decimal::new(5, 1)),
+            sweep_max_value: get_env("SWEEP_MAX_VALUE").parse::<Decimal>().unwrap_or(Decimal::new(50, 0)),
+            sweep_drift_tolerance: get_env("SWEEP_DRIFT_TOLERANCE").parse::<Decimal>().unwrap_or(Decimal::ONE),
+            dry_run: get_env("DRY_RUN").parse::<bool>().unwrap_or(false),
+            allowance_log_days: get_env("ALLOWANCE_LOG_DAYS").parse::<u64>().unwrap_or(14),
+            swap_providers: parse_list(&get_env("SWAP_PROVIDERS"), &["odos", "kyberswap", "paraswap", "1inch", "0x", "dex"]),
+            oneinch_api_key: get_env("ONEINCH_API_KEY"),
+            zerox_api_key: get_env("ZEROX_API_KEY"),
+            collector_address: get_env("COLLECTOR_ADDRESS"),
+            forward_destinations: get_env("FORWARD_DESTINATIONS"),
+            consolidation_reserve_value: get_env("CONSOLIDATION_RESERVE_VALUE").parse::<Decimal>().unwrap_or(Decimal::ZERO),
+            consolidation_min_value: get_env("CONSOLIDATION_MIN_VALUE").parse::<Decimal>().unwrap_or(Decimal::ONE),
+            bridge_providers: parse_list(&get_env("BRIDGE_PROVIDERS"), &["stargate", "lifi"]),
+            bridge_target_chain: get_env("BRIDGE_TARGET_CHAIN"),
+            bridge_min_value: get_env("BRIDGE_MIN_VALUE").parse::<Decimal>().unwrap_or(Decimal::new(5, 0)),
+            bridge_max_fee_percent: get_env("BRIDGE_MAX_FEE_PERCENT").parse::<Decimal>().unwrap_or(Decimal::new(5, 0)),
         }
     }
 }
@@ -93,4 +180,28 @@ pub fn convert_network_name_to_coingecko_query_string(chain_name: ChainName) ->
         ChainName::Taiko => "".to_owned(),
         ChainName::Nova => "arbitrum-nova".to_owned(),
     }
+}
+
+pub fn convert_network_name_to_coingecko_native_id(chain_name: ChainName) -> String {
+    match chain_name {
+        ChainName::Ethereum
+        | ChainName::Arbitrum
+        | ChainName::Optimism
+        | ChainName::Base
+        | ChainName::Linea
+        | ChainName::Zksync
+        | ChainName::Scroll
+        | ChainName::Blast
+        | ChainName::Manta
+        | ChainName::Taiko
+        | ChainName::Nova => "ethereum".to_owned(),
+        ChainName::Bsc | ChainName::Opbnb => "binancecoin".to_owned(),
+        ChainName::Polygon => "matic-network".to_owned(),
+        ChainName::Avalanche => "avalanche-2".to_owned(),
+        ChainName::Mantle => "mantle".to_owned(),
+        ChainName::Gnosis => "xdai".to_owned(),
+        ChainName::Fantom => "fantom".to_owned(),
+        ChainName::Celo => "celo".to_owned(),
+        ChainName::Core => "coredaoorg".to_owned(),
+    }
 }
\ No newline at end of file
@@ -0,0 +1,211 @@
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::helpers::web3_client::PERMIT2_ADDRESS;
+
+// A spender a wallet has approved, named when it is a known one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceEntry {
+    pub chain: String,
+    pub token: Address,
+    // Empty when the token isn't in the wallet's latest scan
+    pub symbol: String,
+    pub spender: Address,
+    pub spender_name: Option<String>,
+    pub allowance: U256,
+    // Given inside Permit2, which holds the token allowance itself
+    #[serde(default)]
+    pub via_permit2: bool,
+}
+
+impl AllowanceEntry {
+    // Some tokens lower a maximum allowance on every transfer, so anything above half of it counts
+    pub fn is_unlimited(&self) -> bool {
+        match self.via_permit2 {
+            // Permit2 amounts are uint160
+            true => self.allowance > U256::MAX >> 97,
+            false => self.allowance > U256::MAX >> 1,
+        }
+    }
+
+    fn spender_label(&self) -> String {
+        let spender = match &self.spender_name {
+            Some(name) => format!("{} ({})", name, self.spender),
+            None => self.spender.to_string(),
+        };
+        match self.via_permit2 {
+            true => format!("{} via Permit2", spender),
+            false => spender,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AllowanceReport {
+    pub wallet: String,
+    pub timestamp: u64,
+    pub entries: Vec<AllowanceEntry>,
+}
+
+impl AllowanceReport {
+    pub fn render(&self) -> String {
+        let mut out = format!("Allowances of {}\n", self.wallet);
+        let mut chain = "";
+        for entry in self.entries.iter() {
+            if entry.chain != chain {
+                chain = &entry.chain;
+                out.push_str(&format!("{}:\n", chain));
+            }
+            let token = match entry.symbol.is_empty() {
+                true => entry.token.to_string(),
+                false => entry.symbol.clone(),
+            };
+            let spender = entry.spender_label();
+            let amount = match entry.is_unlimited() {
+                true => "unlimited".to_owned(),
+                false => entry.allowance.to_string(),
+            };
+            out.push_str(&format!("  {:<42} {:<80} {}\n", token, spender, amount));
+        }
+        let unlimited = self.entries.iter().filter(|e| e.is_unlimited()).count();
+        out.push_str(&format!("{} allowances, {} unlimited\n", self.entries.len(), unlimited));
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum RevokeStatus {
+    Revoked { tx_hash: String },
+    // Dry run: the revoke was built and simulated but not sent
+    Simulated,
+    Failed { error: String },
+}
+
+pub fn render_revokes(results: &[(AllowanceEntry, RevokeStatus)]) -> String {
+    let mut out = String::new();
+    for (entry, status) in results {
+        let status = match status {
+            RevokeStatus::Revoked { tx_hash } => format!("revoked, tx {}", tx_hash),
+            RevokeStatus::Simulated => "would revoke".to_owned(),
+            RevokeStatus::Failed { error } => format!("failed: {}", error),
+        };
+        out.push_str(&format!("  {:<10} {} -> {}  {}\n", entry.chain, entry.token, entry.spender_label(), status));
+    }
+    let revoked = results.iter().filter(|(_, s)| matches!(s, RevokeStatus::Revoked { .. })).count();
+    out.push_str(&format!("Revoked {} of {} allowances\n", revoked, results.len()));
+    out
+}
+
+// Spenders to check on a chain even without Approval logs: Permit2, the DEX routers
+// and the "spenders" of chains.json
+pub fn known_spenders(chain: &Value) -> Vec<(String, Address)> {
+    let mut spenders = vec![("Permit2".to_owned(), PERMIT2_ADDRESS.parse().unwrap())];
+    if let Some(router) = chain["dex"]["uniswapV2Router"].as_str().and_then(|r| r.parse().ok()) {
+        spenders.push(("Uniswap V2 Router".to_owned(), router));
+    }
+    if let Some(router) = chain["dex"]["uniswapV3Router"].as_str().and_then(|r| r.parse().ok()) {
+        spenders.push(("Uniswap V3 Router".to_owned(), router));
+    }
+    if let Some(extra) = chain["spenders"].as_object() {
+        for (name, address) in extra {
+            if let Some(address) = address.as_str().and_then(|a| a.parse().ok()) {
+                spenders.push((name.clone(), address));
+            }
+        }
+    }
+    spenders
+}
+
+// Blocks of Approval logs to read on a chain to cover the given days, from its "blockTime" in
+// chains.json. A chain without one is read as if it made a block every second.
+pub fn log_window_blocks(chain: &Value, days: u64) -> u64 {
+    let block_time = chain["blockTime"].as_f64().filter(|t| *t > 0.0).unwrap_or(1.0);
+    (days as f64 * 86_400.0 / block_time).ceil() as u64
+}
+
+// Allowances to revoke, an empty filter matches nothing
+#[derive(Clone, Debug, Default)]
+pub struct RevokeFilter {
+    pub chain: Option<String>,
+    pub token: Option<Address>,
+    pub spender: Option<Address>,
+    pub all: bool,
+}
+
+impl RevokeFilter {
+    pub fn matches(&self, entry: &AllowanceEntry) -> bool {
+        if !self.all && self.chain.is_none() && self.token.is_none() && self.spender.is_none() {
+            return false;
+        }
+        self.chain.as_ref().is_none_or(|c| c.eq_ignore_ascii_case(&entry.chain))
+            && self.token.is_none_or(|t| t == entry.token)
+            && self.spender.is_none_or(|s| s == entry.spender)
+    }
+}
+
+#[test]
+fn test_known_spenders() {
+    let chain = serde_json::json!({
+        "dex": { "uniswapV2Router": "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D" },
+        "spenders": { "Old Router": "0x1111111254EEB25477B68fb85Ed929f73A960582", "Broken": "0x12" },
+    });
+    let spenders = known_spenders(&chain);
+    let names: Vec<&str> = spenders.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["Permit2", "Uniswap V2 Router", "Old Router"]);
+    assert_eq!(known_spenders(&serde_json::json!({})).len(), 1);
+}
+
+#[test]
+fn test_log_window_blocks() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    assert_eq!(log_window_blocks(&chain_data["Ethereum"], 14), 100_800);
+    assert_eq!(log_window_blocks(&chain_data["Arbitrum"], 14), 4_838_400);
+    assert_eq!(log_window_blocks(&serde_json::json!({}), 1), 86_400);
+    assert!(chain_data.as_object().unwrap().values().all(|chain| chain["blockTime"].as_f64().is_some()));
+}
+
+#[test]
+fn test_revoke_filter() {
+    let entry = |chain: &str, allowance: U256| AllowanceEntry {
+        chain: chain.to_owned(),
+        token: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(),
+        symbol: "USDC".to_owned(),
+        spender: PERMIT2_ADDRESS.parse().unwrap(),
+        spender_name: Some("Permit2".to_owned()),
+        allowance,
+        via_permit2: false,
+    };
+    let base = entry("Base", U256::MAX);
+    let optimism = entry("Optimism", U256::from(5));
+    assert!(base.is_unlimited());
+    assert!(!optimism.is_unlimited());
+
+    assert!(!RevokeFilter::default().matches(&base));
+    assert!(RevokeFilter { all: true, ..Default::default() }.matches(&optimism));
+    let by_chain = RevokeFilter { chain: Some("base".to_owned()), ..Default::default() };
+    assert!(by_chain.matches(&base));
+    assert!(!by_chain.matches(&optimism));
+    let by_spender = RevokeFilter { spender: Some(Address::ZERO), ..Default::default() };
+    assert!(!by_spender.matches(&base));
+
+    let report = AllowanceReport { wallet: "0x01".to_owned(), timestamp: 0, entries: vec![base.clone(), optimism.clone()] };
+    assert!(report.render().ends_with("2 allowances, 1 unlimited\n"));
+    let revokes = vec![
+        (base, RevokeStatus::Revoked { tx_hash: "0x02".to_owned() }),
+        (optimism, RevokeStatus::Failed { error: "reverted".to_owned() }),
+    ];
+    assert!(render_revokes(&revokes).ends_with("Revoked 1 of 2 allowances\n"));
+
+    // A router's allowance inside Permit2 tops out at uint160
+    let router = AllowanceEntry {
+        spender: "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45".parse().unwrap(),
+        spender_name: Some("Uniswap V3 Router".to_owned()),
+        allowance: U256::from(alloy::primitives::U160::MAX),
+        via_permit2: true,
+        ..entry("Base", U256::ZERO)
+    };
+    assert!(router.is_unlimited());
+    assert!(render_revokes(&[(router, RevokeStatus::Simulated)]).contains("Uniswap V3 Router (0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45) via Permit2"));
+}
@@ -0,0 +1,422 @@
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, U256},
+    rpc::types::TransactionRequest,
+};
+use async_trait::async_trait;
+use eyre::Result;
+use log::warn;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::const_types::Env;
+use crate::helpers::consolidation::{self, TransferStatus};
+use crate::helpers::currency::CurrencyConverter;
+use crate::helpers::lifi_bridge::LifiBridge;
+use crate::helpers::stargate_bridge::StargateBridge;
+use crate::helpers::swap_provider::SwapTx;
+use crate::helpers::web3_client::Network;
+
+// LayerZero V2 endpoint ids are the V1 chain ids of chains.json plus this
+static LZ_V2_EID_OFFSET: u32 = 30_000;
+// Percent added to a quoted messaging fee, it can move between quoting and sending
+static NATIVE_FEE_MARGIN: u64 = 10;
+
+pub static DEFAULT_BRIDGE_PROVIDERS: [&str; 2] = ["stargate", "lifi"];
+
+// A chain as bridges see it
+#[derive(Clone)]
+pub struct BridgeChain {
+    pub network: Network,
+    // LayerZero V1 chain id from chains.json
+    pub lz_id: Option<u32>,
+    pub currency: String,
+}
+
+impl BridgeChain {
+    pub fn from_chain_data(chain_name: &str, chain: &Value) -> Result<Self> {
+        Ok(BridgeChain {
+            network: Network::from_chain_data(chain_name, chain)?,
+            lz_id: chain["lzId"].as_str().and_then(|id| id.parse().ok()),
+            currency: chain["currency"].as_str().unwrap_or_default().to_owned(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.network.chain_name
+    }
+
+    // LayerZero V2 endpoint id
+    pub fn lz_eid(&self) -> Option<u32> {
+        self.lz_id.map(|id| id + LZ_V2_EID_OFFSET)
+    }
+}
+
+// Native amount moved from one chain to another
+#[derive(Clone, Debug)]
+pub struct BridgeQuote {
+    pub provider: &'static str,
+    pub from_chain: String,
+    pub to_chain: String,
+    pub amount_in: U256,
+    // Expected to arrive on the target chain
+    pub amount_out: U256,
+    // Native paid on top of amount_in, e.g. the LayerZero messaging fee
+    pub native_fee: U256,
+    // Gas units of the bridge transaction on the source chain
+    pub gas: u64,
+    // Provider specific data the transaction is built from
+    pub route: Value,
+}
+
+impl BridgeQuote {
+    pub fn gas_cost(&self, gas_price: u128) -> U256 {
+        U256::from(self.gas) * U256::from(gas_price)
+    }
+
+    // What arrives less what is paid on top of the amount
+    pub fn net_out(&self, gas_price: u128) -> U256 {
+        self.amount_out.saturating_sub(self.native_fee + self.gas_cost(gas_price))
+    }
+
+    // Everything the bridge costs, as a percent of the amount
+    pub fn fee_percent(&self, gas_price: u128) -> Decimal {
+        let amount_in = Decimal::from_str_exact(&self.amount_in.to_string()).unwrap_or_default();
+        if amount_in.is_zero() {
+            return Decimal::ONE_HUNDRED;
+        }
+        let cost = self.amount_in.saturating_sub(self.net_out(gas_price));
+        Decimal::from_str_exact(&cost.to_string()).unwrap_or_default() / amount_in * Decimal::ONE_HUNDRED
+    }
+}
+
+#[async_trait]
+pub trait BridgeProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether the provider moves native funds from one chain to the other
+    fn supports_route(&self, from: &BridgeChain, to: &BridgeChain) -> bool;
+
+    async fn quote(&self, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256) -> Result<BridgeQuote>;
+
+    // Transaction that executes a quote of this provider on the source chain
+    async fn build_bridge(&self, sender: Address, quote: &BridgeQuote) -> Result<SwapTx>;
+}
+
+// Which providers to ask, where to bridge to and when it is worth it
+#[derive(Clone, Debug)]
+pub struct BridgeConfig {
+    pub providers: Vec<String>,
+    pub target_chain: Option<String>,
+    // Balances worth less than this many USD stay where they are
+    pub min_value: Decimal,
+    // Bridges costing more than this percent of the amount are skipped
+    pub max_fee_percent: Decimal,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        BridgeConfig {
+            providers: DEFAULT_BRIDGE_PROVIDERS.iter().map(|p| p.to_string()).collect(),
+            target_chain: None,
+            min_value: Decimal::new(5, 0),
+            max_fee_percent: Decimal::new(5, 0),
+        }
+    }
+}
+
+impl BridgeConfig {
+    pub fn from_env(env: &Env) -> Self {
+        BridgeConfig {
+            providers: env.bridge_providers.clone(),
+            target_chain: Some(env.bridge_target_chain.clone()).filter(|c| !c.is_empty()),
+            min_value: env.bridge_min_value,
+            max_fee_percent: env.bridge_max_fee_percent,
+        }
+    }
+}
+
+// Every configured provider. Quotes are compared on what arrives less the fees and gas.
+pub struct BridgeRouter {
+    providers: Vec<Box<dyn BridgeProvider>>,
+}
+
+impl BridgeRouter {
+    pub fn new(providers: Vec<Box<dyn BridgeProvider>>) -> Self {
+        BridgeRouter { providers }
+    }
+
+    pub fn from_config(config: &BridgeConfig, chain_data: &Value) -> Result<Self> {
+        let mut providers: Vec<Box<dyn BridgeProvider>> = vec![];
+        for provider in config.providers.iter() {
+            match provider.as_str() {
+                // Stargate native pools from the "bridge" sections of chains.json
+                "stargate" => providers.push(Box::new(StargateBridge::new(chain_data))),
+                "lifi" => providers.push(Box::new(LifiBridge::new()?)),
+                p => warn!("Unknown bridge provider {}, skipping", p),
+            }
+        }
+        Ok(Self::new(providers))
+    }
+
+    pub fn supports_route(&self, from: &BridgeChain, to: &BridgeChain) -> bool {
+        self.providers.iter().any(|p| p.supports_route(from, to))
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&dyn BridgeProvider> {
+        self.providers.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    // Quotes of every provider covering the route, best first. Failed providers are left out.
+    pub async fn quotes(&self, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256, gas_price: u128) -> Vec<BridgeQuote> {
+        let requests = self.providers.iter()
+            .filter(|p| p.supports_route(from, to))
+            .map(|p| async move { (p.name(), p.quote(sender, from, to, amount).await) });
+        let mut quotes = vec![];
+        for (name, result) in futures::future::join_all(requests).await {
+            match result {
+                Ok(q) => quotes.push(q),
+                Err(e) => warn!("No {} bridge quote from {} to {}: {:?}", name, from.name(), to.name(), e),
+            }
+        }
+        quotes.sort_by_key(|q| std::cmp::Reverse(q.net_out(gas_price)));
+        quotes
+    }
+
+    pub async fn best_quote(&self, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256, gas_price: u128) -> Result<BridgeQuote> {
+        match self.quotes(sender, from, to, amount, gas_price).await.into_iter().next() {
+            Some(q) => Ok(q),
+            None => Err(eyre::eyre!("BridgeRouter:best_quote No provider could quote {} to {}", from.name(), to.name())),
+        }
+    }
+
+    // Quote on the provider of an earlier quote, e.g. again for the final amount
+    pub async fn quote_with(&self, provider: &str, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256) -> Result<BridgeQuote> {
+        match self.provider(provider) {
+            Some(p) => p.quote(sender, from, to, amount).await,
+            None => Err(eyre::eyre!("BridgeRouter:quote_with Provider {} not available", provider)),
+        }
+    }
+
+    // Unsigned transaction of a quote, without gas fields
+    pub async fn transaction(&self, sender: Address, quote: &BridgeQuote) -> Result<TransactionRequest> {
+        let provider = match self.provider(quote.provider) {
+            Some(p) => p,
+            None => return Err(eyre::eyre!("BridgeRouter:transaction Provider {} not available", quote.provider)),
+        };
+        let bridge_tx = provider.build_bridge(sender, quote).await?;
+        Ok(TransactionRequest::default()
+            .with_from(sender)
+            .with_to(bridge_tx.to)
+            .with_input(bridge_tx.data)
+            .with_value(bridge_tx.value))
+    }
+}
+
+// Amount to bridge once the quote's fee and gas, the L1 data fee and the reserve are covered
+pub fn bridge_amount(balance: U256, reserve: U256, quote: &BridgeQuote, gas_limit: u128, gas_price: u128, l1_fee: U256) -> Option<U256> {
+    let native_fee = quote.native_fee * U256::from(100 + NATIVE_FEE_MARGIN) / U256::from(100);
+    consolidation::sendable_amount(balance, consolidation::transfer_fee(gas_limit, gas_price, l1_fee) + native_fee, reserve)
+}
+
+// Native balance of one chain moved to the target chain, amounts in native units
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BridgeEntry {
+    pub from_chain: String,
+    pub to_chain: String,
+    pub token: String,
+    // Provider the amount was quoted on, empty when it never got a quote
+    pub provider: String,
+    pub balance: Decimal,
+    pub amount: Decimal,
+    pub amount_out: Decimal,
+    // Messaging fee, gas and L1 data fee the bridge was budgeted with
+    pub fee: Decimal,
+    // USD value of the amount, zero when the native price is unknown
+    pub value: Decimal,
+    #[serde(flatten)]
+    pub status: TransferStatus,
+}
+
+impl BridgeEntry {
+    pub fn new(from_chain: &str, to_chain: &str, token: &str, balance: Decimal, status: TransferStatus) -> Self {
+        BridgeEntry {
+            from_chain: from_chain.to_owned(),
+            to_chain: to_chain.to_owned(),
+            token: token.to_owned(),
+            provider: String::new(),
+            balance,
+            amount: Decimal::ZERO,
+            amount_out: Decimal::ZERO,
+            fee: Decimal::ZERO,
+            value: Decimal::ZERO,
+            status,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BridgeReport {
+    pub wallet: String,
+    pub target_chain: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub entries: Vec<BridgeEntry>,
+}
+
+impl BridgeReport {
+    pub fn sent(&self) -> impl Iterator<Item = &BridgeEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, TransferStatus::Sent { .. }))
+    }
+
+    pub fn render(&self, converter: &CurrencyConverter) -> String {
+        let mut out = match self.dry_run {
+            true => format!("Bridge plan for {} to {} (dry run, nothing sent)\n", self.wallet, self.target_chain),
+            false => format!("Bridging of {} to {}\n", self.wallet, self.target_chain),
+        };
+        out.push_str(&format!(
+            "  {:<10} {:<6} {:<9} {:>24} {:>24} {:>24} {:>14}  {}\n",
+            "Chain", "Token", "Provider", "Amount", "Received", "Fee", "Value", "Status",
+        ));
+        for entry in self.entries.iter() {
+            let status = match &entry.status {
+                TransferStatus::Sent { tx_hash } => format!("sent, tx {}", tx_hash),
+                TransferStatus::Simulated => "would bridge".to_owned(),
+                TransferStatus::Skipped { reason } => format!("skipped: {}", reason),
+                TransferStatus::Failed { error } => format!("failed: {}", error),
+            };
+            out.push_str(&format!(
+                "  {:<10} {:<6} {:<9} {:>24} {:>24} {:>24} {:>14}  {}\n",
+                entry.from_chain, entry.token, entry.provider, entry.amount, entry.amount_out, entry.fee, converter.format(entry.value), status,
+            ));
+        }
+        let done: Vec<&BridgeEntry> = match self.dry_run {
+            true => self.entries.iter().filter(|e| e.status == TransferStatus::Simulated).collect(),
+            false => self.sent().collect(),
+        };
+        let value: Decimal = done.iter().map(|e| e.value).sum();
+        let fee: Decimal = done.iter().map(|e| e.fee).sum();
+        out.push_str(&format!(
+            "{} {} of {} chains, {}, fees {} in native\n",
+            if self.dry_run { "Would bridge" } else { "Bridged" }, done.len(), self.entries.len(), converter.format(value), fee,
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+pub fn test_bridge_chain(chain_name: &str) -> BridgeChain {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    BridgeChain::from_chain_data(chain_name, &chain_data[chain_name]).unwrap()
+}
+
+// Bridges between the chains it was given with a flat fee
+#[cfg(test)]
+struct MockBridgeProvider {
+    name: &'static str,
+    chains: Vec<&'static str>,
+    fee: u64,
+    native_fee: u64,
+    gas: u64,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl BridgeProvider for MockBridgeProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn supports_route(&self, from: &BridgeChain, to: &BridgeChain) -> bool {
+        self.chains.contains(&from.name()) && self.chains.contains(&to.name()) && from.name() != to.name()
+    }
+
+    async fn quote(&self, _sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256) -> Result<BridgeQuote> {
+        if amount <= U256::from(self.fee) {
+            return Err(eyre::eyre!("amount below the fee"));
+        }
+        Ok(BridgeQuote {
+            provider: self.name,
+            from_chain: from.name().to_owned(),
+            to_chain: to.name().to_owned(),
+            amount_in: amount,
+            amount_out: amount - U256::from(self.fee),
+            native_fee: U256::from(self.native_fee),
+            gas: self.gas,
+            route: serde_json::json!({ "to": to.lz_eid() }),
+        })
+    }
+
+    async fn build_bridge(&self, _sender: Address, quote: &BridgeQuote) -> Result<SwapTx> {
+        Ok(SwapTx {
+            to: Address::ZERO,
+            data: Default::default(),
+            value: quote.amount_in + quote.native_fee,
+            gas: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_best_bridge_quote() {
+    let (base, arbitrum, bsc) = (test_bridge_chain("Base"), test_bridge_chain("Arbitrum"), test_bridge_chain("Bsc"));
+    assert_eq!(base.lz_eid(), Some(30184));
+    assert_eq!(arbitrum.lz_eid(), Some(30110));
+    assert_eq!(base.currency, "ETH");
+
+    let provider = |name, chains, fee, native_fee, gas| -> Box<dyn BridgeProvider> {
+        Box::new(MockBridgeProvider { name, chains, fee, native_fee, gas })
+    };
+    let router = BridgeRouter::new(vec![
+        // Cheapest on the amount but pays a big messaging fee
+        provider("messaging", vec!["Base", "Arbitrum"], 100, 5_000, 100),
+        provider("pool", vec!["Base", "Arbitrum"], 1_000, 0, 200),
+        provider("elsewhere", vec!["Bsc", "Arbitrum"], 0, 0, 0),
+    ]);
+    assert!(router.supports_route(&base, &arbitrum));
+    assert!(!router.supports_route(&base, &bsc));
+
+    let amount = U256::from(1_000_000);
+    let quotes = router.quotes(Address::ZERO, &base, &arbitrum, amount, 10).await;
+    let names: Vec<&str> = quotes.iter().map(|q| q.provider).collect();
+    assert_eq!(names, vec!["pool", "messaging"]);
+    assert_eq!(quotes[0].net_out(10), U256::from(997_000));
+    assert_eq!(quotes[0].fee_percent(10), Decimal::new(3, 1));
+    assert!(router.best_quote(Address::ZERO, &base, &bsc, amount, 10).await.is_err());
+
+    let quote = router.quote_with("messaging", Address::ZERO, &base, &arbitrum, amount).await.unwrap();
+    let tx = router.transaction(Address::ZERO, &quote).await.unwrap();
+    assert_eq!(tx.value, Some(amount + U256::from(5_000)));
+
+    // The messaging fee gets its margin, gas is budgeted at the limit
+    let bridged = bridge_amount(U256::from(2_000_000), U256::from(10_000), &quote, 150, 10, U256::ZERO);
+    assert_eq!(bridged, Some(U256::from(2_000_000 - 10_000 - 5_500 - 1_500)));
+    assert_eq!(bridge_amount(U256::from(6_000), U256::ZERO, &quote, 150, 10, U256::ZERO), None);
+}
+
+#[test]
+fn test_bridge_report() {
+    let planned = BridgeEntry {
+        provider: "stargate".to_owned(),
+        amount: Decimal::new(5, 2),
+        amount_out: Decimal::new(499, 4),
+        fee: Decimal::new(2, 4),
+        value: Decimal::new(200, 0),
+        ..BridgeEntry::new("Base", "Arbitrum", "ETH", Decimal::new(6, 2), TransferStatus::Simulated)
+    };
+    let skipped = BridgeEntry::new("Scroll", "Arbitrum", "ETH", Decimal::new(1, 4), TransferStatus::Skipped { reason: "worth less than 5 USD".to_owned() });
+    let report = BridgeReport {
+        wallet: "0x01".to_owned(),
+        target_chain: "Arbitrum".to_owned(),
+        timestamp: 0,
+        dry_run: true,
+        entries: vec![planned, skipped],
+    };
+    let rendered = report.render(&CurrencyConverter::usd());
+    assert!(rendered.starts_with("Bridge plan for 0x01 to Arbitrum (dry run, nothing sent)\n"));
+    assert!(rendered.ends_with("Would bridge 1 of 2 chains, 200.00 USD, fees 0.0002 in native\n"));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["entries"][0]["status"], "simulated");
+}
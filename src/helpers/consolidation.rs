@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{utils::parse_units, Address, U256};
+use eyre::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::currency::CurrencyConverter;
+use crate::helpers::utils::u256_to_decimal;
+use crate::helpers::web3_client::{is_native_token, Balance};
+
+// Percent added to the quoted L1 data fee, it follows the L1 gas price until the transaction lands
+static L1_FEE_MARGIN: u64 = 20;
+
+// Where native balances are consolidated to and what stays behind
+#[derive(Clone, Debug)]
+pub struct ConsolidationConfig {
+    pub collector: Option<Address>,
+    // Per-chain destinations of forwarded tokens, the collector on other chains
+    pub destinations: HashMap<String, Address>,
+    // USD value of native left on each wallet and chain for later transactions
+    pub reserve_value: Decimal,
+    // Smaller transfers aren't worth a transaction
+    pub min_value: Decimal,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        ConsolidationConfig {
+            collector: None,
+            destinations: HashMap::new(),
+            reserve_value: Decimal::ZERO,
+            min_value: Decimal::ONE,
+        }
+    }
+}
+
+impl ConsolidationConfig {
+    // Where tokens of a chain are forwarded to
+    pub fn destination(&self, chain: &str) -> Option<Address> {
+        self.destinations.iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(chain))
+            .map(|(_, address)| *address)
+            .or(self.collector)
+    }
+}
+
+// Parse "Base=0x...,Arbitrum=0x..." into per-chain addresses
+pub fn parse_destinations(value: &str) -> Result<HashMap<String, Address>> {
+    let mut destinations = HashMap::new();
+    for pair in value.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        match pair.split_once('=') {
+            Some((chain, address)) => { destinations.insert(chain.trim().to_owned(), address.trim().parse()?); }
+            None => return Err(eyre::eyre!("Destination {} is not chain=address", pair)),
+        }
+    }
+    Ok(destinations)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum TransferStatus {
+    Sent { tx_hash: String },
+    // Dry run: the transfer was built and simulated but not sent
+    Simulated,
+    Skipped { reason: String },
+    Failed { error: String },
+}
+
+// Native transfer of one chain, amounts in native units
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransferEntry {
+    pub chain: String,
+    pub token: String,
+    pub balance: Decimal,
+    pub amount: Decimal,
+    // Gas and L1 data fee the transfer was budgeted with
+    pub fee: Decimal,
+    pub reserve: Decimal,
+    // USD value of the amount, zero when the native price is unknown
+    pub value: Decimal,
+    #[serde(flatten)]
+    pub status: TransferStatus,
+}
+
+impl TransferEntry {
+    pub fn new(chain: &str, token: &str, balance: U256, status: TransferStatus) -> Self {
+        TransferEntry {
+            chain: chain.to_owned(),
+            token: token.to_owned(),
+            balance: u256_to_decimal(balance, 18).unwrap_or_default(),
+            amount: Decimal::ZERO,
+            fee: Decimal::ZERO,
+            reserve: Decimal::ZERO,
+            value: Decimal::ZERO,
+            status,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConsolidationReport {
+    pub wallet: String,
+    pub collector: Address,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub entries: Vec<TransferEntry>,
+}
+
+impl ConsolidationReport {
+    pub fn sent(&self) -> impl Iterator<Item = &TransferEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, TransferStatus::Sent { .. }))
+    }
+
+    pub fn render(&self, converter: &CurrencyConverter) -> String {
+        let mut out = match self.dry_run {
+            true => format!("Consolidation of {} to {} (dry run, nothing sent)\n", self.wallet, self.collector),
+            false => format!("Consolidation of {} to {}\n", self.wallet, self.collector),
+        };
+        out.push_str(&format!(
+            "  {:<10} {:<6} {:>24} {:>24} {:>24} {:>14}  {}\n",
+            "Chain", "Token", "Balance", "Amount", "Fee", "Value", "Status",
+        ));
+        for entry in self.entries.iter() {
+            let status = match &entry.status {
+                TransferStatus::Sent { tx_hash } => format!("sent, tx {}", tx_hash),
+                TransferStatus::Simulated => "would send".to_owned(),
+                TransferStatus::Skipped { reason } => format!("skipped: {}", reason),
+                TransferStatus::Failed { error } => format!("failed: {}", error),
+            };
+            out.push_str(&format!(
+                "  {:<10} {:<6} {:>24} {:>24} {:>24} {:>14}  {}\n",
+                entry.chain, entry.token, entry.balance, entry.amount, entry.fee, converter.format(entry.value), status,
+            ));
+        }
+        let done: Vec<&TransferEntry> = match self.dry_run {
+            true => self.entries.iter().filter(|e| e.status == TransferStatus::Simulated).collect(),
+            false => self.sent().collect(),
+        };
+        let value: Decimal = done.iter().map(|e| e.value).sum();
+        out.push_str(&format!(
+            "{} {} of {} transfers, {}\n",
+            if self.dry_run { "Would send" } else { "Sent" }, done.len(), self.entries.len(), converter.format(value),
+        ));
+        out
+    }
+}
+
+// Tokens to forward, by symbol or address and optionally on one chain
+#[derive(Clone, Debug, Default)]
+pub struct TokenFilter {
+    pub tokens: Vec<String>,
+    pub chain: Option<String>,
+}
+
+impl TokenFilter {
+    pub fn matches(&self, chain: &str, balance: &Balance) -> bool {
+        if is_native_token(&balance.token_address) || balance.balance.is_zero() {
+            return false;
+        }
+        self.chain.as_ref().is_none_or(|c| c.eq_ignore_ascii_case(chain))
+            && self.tokens.iter().any(|t| match t.parse::<Address>() {
+                Ok(address) => address == balance.token_address,
+                Err(_) => t.eq_ignore_ascii_case(&balance.token_symbol),
+            })
+    }
+}
+
+// ERC-20 balance forwarded as is, amount as read on-chain right before sending
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ForwardEntry {
+    pub chain: String,
+    pub token: String,
+    pub address: Address,
+    pub to: Option<Address>,
+    pub amount: Decimal,
+    // USD value at the scan's price
+    pub value: Decimal,
+    #[serde(flatten)]
+    pub status: TransferStatus,
+}
+
+impl ForwardEntry {
+    pub fn new(chain: &str, balance: &Balance, to: Option<Address>, status: TransferStatus) -> Self {
+        ForwardEntry {
+            chain: chain.to_owned(),
+            token: balance.token_symbol.clone(),
+            address: balance.token_address,
+            to,
+            amount: balance.amount().unwrap_or_default(),
+            value: balance.value().unwrap_or_default(),
+            status,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ForwardReport {
+    pub wallet: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub entries: Vec<ForwardEntry>,
+}
+
+impl ForwardReport {
+    pub fn sent(&self) -> impl Iterator<Item = &ForwardEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, TransferStatus::Sent { .. }))
+    }
+
+    pub fn render(&self, converter: &CurrencyConverter) -> String {
+        let mut out = match self.dry_run {
+            true => format!("Token forwarding of {} (dry run, nothing sent)\n", self.wallet),
+            false => format!("Token forwarding of {}\n", self.wallet),
+        };
+        for entry in self.entries.iter() {
+            let status = match &entry.status {
+                TransferStatus::Sent { tx_hash } => format!("sent, tx {}", tx_hash),
+                TransferStatus::Simulated => "would send".to_owned(),
+                TransferStatus::Skipped { reason } => format!("skipped: {}", reason),
+                TransferStatus::Failed { error } => format!("failed: {}", error),
+            };
+            let to = entry.to.map(|t| t.to_string()).unwrap_or_default();
+            out.push_str(&format!(
+                "  {:<10} {:<8} {:>24} {:>14} -> {:<42}  {}\n",
+                entry.chain, entry.token, entry.amount, converter.format(entry.value), to, status,
+            ));
+        }
+        let done: Vec<&ForwardEntry> = match self.dry_run {
+            true => self.entries.iter().filter(|e| e.status == TransferStatus::Simulated).collect(),
+            false => self.sent().collect(),
+        };
+        let value: Decimal = done.iter().map(|e| e.value).sum();
+        out.push_str(&format!(
+            "{} {} of {} tokens, {}\n",
+            if self.dry_run { "Would forward" } else { "Forwarded" }, done.len(), self.entries.len(), converter.format(value),
+        ));
+        out
+    }
+}
+
+// Native amount worth the reserve value, None when it can't be priced
+pub fn reserve_amount(reserve_value: Decimal, native_price: Option<Decimal>) -> Result<Option<U256>> {
+    if reserve_value.is_zero() {
+        return Ok(Some(U256::ZERO));
+    }
+    match native_price.filter(|p| !p.is_zero()) {
+        Some(price) => Ok(Some(parse_units(&(reserve_value / price).round_dp(18).to_string(), 18)?.into())),
+        None => Ok(None),
+    }
+}
+
+// Most a transfer can cost: its whole gas limit at the fee cap plus the L1 data fee with a margin
+pub fn transfer_fee(gas_limit: u128, max_fee_per_gas: u128, l1_fee: U256) -> U256 {
+    U256::from(gas_limit) * U256::from(max_fee_per_gas) + l1_fee * U256::from(100 + L1_FEE_MARGIN) / U256::from(100)
+}
+
+// What is left to send once the fee and the reserve are covered
+pub fn sendable_amount(balance: U256, fee: U256, reserve: U256) -> Option<U256> {
+    balance.checked_sub(fee)?.checked_sub(reserve).filter(|a| !a.is_zero())
+}
+
+#[test]
+fn test_sendable_amount() {
+    let eth = U256::from(10).pow(U256::from(18));
+    let fee = transfer_fee(21_000, 1_000_000_000, U256::from(1_000_000));
+    assert_eq!(fee, U256::from(21_000_001_200_000u64));
+    assert_eq!(sendable_amount(eth, fee, U256::ZERO), Some(eth - fee));
+    assert_eq!(sendable_amount(fee, fee, U256::ZERO), None);
+    assert_eq!(sendable_amount(eth, fee, eth), None);
+
+    // 2 USD at 4000 USD per ETH
+    assert_eq!(reserve_amount(Decimal::TWO, Some(Decimal::new(4000, 0))).unwrap(), Some(U256::from(500_000_000_000_000u64)));
+    assert_eq!(reserve_amount(Decimal::ZERO, None).unwrap(), Some(U256::ZERO));
+    assert_eq!(reserve_amount(Decimal::TWO, None).unwrap(), None);
+}
+
+#[test]
+fn test_consolidation_report() {
+    let sent = TransferEntry {
+        amount: Decimal::new(5, 1),
+        value: Decimal::new(2000, 0),
+        ..TransferEntry::new("Base", "ETH", U256::from(10).pow(U256::from(18)), TransferStatus::Sent { tx_hash: "0x01".to_owned() })
+    };
+    assert_eq!(sent.balance, Decimal::ONE);
+    let skipped = TransferEntry::new("Scroll", "ETH", U256::from(1000), TransferStatus::Skipped { reason: "balance doesn't cover the fee".to_owned() });
+    let report = ConsolidationReport {
+        wallet: "0x02".to_owned(),
+        collector: Address::ZERO,
+        timestamp: 0,
+        dry_run: false,
+        entries: vec![sent, skipped],
+    };
+    let rendered = report.render(&CurrencyConverter::usd());
+    assert!(rendered.contains("skipped: balance doesn't cover the fee"));
+    assert!(rendered.ends_with("Sent 1 of 2 transfers, 2000.00 USD\n"));
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["entries"][0]["status"], "sent");
+    let parsed: ConsolidationReport = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.entries[1].status, report.entries[1].status);
+}
+
+#[test]
+fn test_forward_selection() {
+    let usdc: Address = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".parse().unwrap();
+    let mut balance = Balance::new(usdc, "USD Coin".to_owned(), "USDC".to_owned(), 6, U256::from(2_500_000));
+    balance.set_token_price(Decimal::ONE);
+    let filter = TokenFilter { tokens: vec!["usdc".to_owned()], chain: None };
+    assert!(filter.matches("Base", &balance));
+    assert!(TokenFilter { tokens: vec![usdc.to_string()], chain: Some("base".to_owned()) }.matches("Base", &balance));
+    assert!(!TokenFilter { tokens: vec!["usdc".to_owned()], chain: Some("Arbitrum".to_owned()) }.matches("Base", &balance));
+    assert!(!TokenFilter::default().matches("Base", &balance));
+    let native = Balance::new(crate::constants::const_types::NATIVE_TOKEN_ADDRESS.parse().unwrap(), "Ether".to_owned(), "ETH".to_owned(), 18, U256::from(1));
+    assert!(!TokenFilter { tokens: vec!["eth".to_owned()], chain: None }.matches("Base", &native));
+
+    let collector: Address = "0x0000000000000000000000000000000000000001".parse().unwrap();
+    let config = ConsolidationConfig {
+        collector: Some(collector),
+        destinations: parse_destinations("Base=0x0000000000000000000000000000000000000002, ").unwrap(),
+        ..ConsolidationConfig::default()
+    };
+    assert_eq!(config.destination("base"), Some("0x0000000000000000000000000000000000000002".parse().unwrap()));
+    assert_eq!(config.destination("Arbitrum"), Some(collector));
+    assert!(parse_destinations("Base").is_err());
+
+    let report = ForwardReport {
+        wallet: "0x03".to_owned(),
+        timestamp: 0,
+        dry_run: true,
+        entries: vec![ForwardEntry::new("Base", &balance, config.destination("Base"), TransferStatus::Simulated)],
+    };
+    assert!(report.render(&CurrencyConverter::usd()).ends_with("Would forward 1 of 1 tokens, 2.50 USD\n"));
+}
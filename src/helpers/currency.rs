@@ -0,0 +1,189 @@
+use alloy::primitives::{Address, U256};
+use eyre::Result;
+use reqwest::Url;
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use crate::constants::const_types::NATIVE_TOKEN_ADDRESS;
+use crate::helpers::price_oracle::PriceOracle;
+use crate::helpers::utils::f64_to_decimal;
+use crate::helpers::web3_client::{Balance, Network};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReportCurrency {
+    Usd,
+    // ISO code converted through the FX source, e.g. EUR
+    Fiat(String),
+    // Crypto unit priced through the price oracle, e.g. ETH
+    Crypto(String),
+}
+
+impl ReportCurrency {
+    pub fn parse(code: &str) -> Self {
+        let code = code.trim().to_uppercase();
+        if code.is_empty() || code == "USD" {
+            ReportCurrency::Usd
+        } else if Self::crypto_reference(&code).is_some() {
+            ReportCurrency::Crypto(code)
+        } else {
+            ReportCurrency::Fiat(code)
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            ReportCurrency::Usd => "USD",
+            ReportCurrency::Fiat(code) | ReportCurrency::Crypto(code) => code,
+        }
+    }
+
+    // Chain and token whose USD price defines a crypto unit
+    fn crypto_reference(code: &str) -> Option<(&'static str, &'static str, u8)> {
+        match code {
+            "ETH" => Some(("Ethereum", NATIVE_TOKEN_ADDRESS, 18)),
+            "BTC" => Some(("Ethereum", "0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599", 8)),
+            "BNB" => Some(("Bsc", NATIVE_TOKEN_ADDRESS, 18)),
+            "AVAX" => Some(("Avalanche", NATIVE_TOKEN_ADDRESS, 18)),
+            "MATIC" | "POL" => Some(("Polygon", NATIVE_TOKEN_ADDRESS, 18)),
+            _ => None,
+        }
+    }
+}
+
+// Converts USD values into the report currency
+#[derive(Clone, Debug)]
+pub struct CurrencyConverter {
+    currency: ReportCurrency,
+    // Units of the report currency per 1 USD
+    rate: Decimal,
+}
+
+impl CurrencyConverter {
+    pub fn usd() -> Self {
+        CurrencyConverter {
+            currency: ReportCurrency::Usd,
+            rate: Decimal::ONE,
+        }
+    }
+
+    pub fn with_rate(currency: ReportCurrency, rate: Decimal) -> Self {
+        CurrencyConverter { currency, rate }
+    }
+
+    // Rate at the price timestamp when balances are valued at a past date, the latest otherwise
+    pub async fn new(
+        currency: ReportCurrency,
+        fx_url: &str,
+        price_oracle: &PriceOracle,
+        chain_data: &Value,
+        price_timestamp: Option<u64>,
+    ) -> Result<Self> {
+        let rate = match &currency {
+            ReportCurrency::Usd => Decimal::ONE,
+            ReportCurrency::Fiat(code) => Self::fetch_fx_rate(fx_url, code, price_timestamp).await?,
+            ReportCurrency::Crypto(code) => Self::fetch_crypto_rate(code, price_oracle, chain_data, price_timestamp).await?,
+        };
+        Ok(Self::with_rate(currency, rate))
+    }
+
+    // The FX source is expected to answer with {"rates": {"EUR": 0.92, ...}} for a USD base
+    async fn fetch_fx_rate(fx_url: &str, code: &str, price_timestamp: Option<u64>) -> Result<Decimal> {
+        let url = match price_timestamp {
+            Some(timestamp) => Self::historical_fx_url(fx_url, timestamp)?,
+            None => Url::parse(fx_url)?,
+        };
+        let res = reqwest::get(url).await?;
+        if res.status() != 200 {
+            return Err(eyre::eyre!("CurrencyConverter:fetch_fx_rate Request failed with status {}", res.status()));
+        }
+        let json: Value = res.json().await?;
+        Self::parse_fx_rate(&json, code)
+    }
+
+    // Frankfurter style source, where /latest becomes the date, e.g. /2024-07-15
+    fn historical_fx_url(fx_url: &str, timestamp: u64) -> Result<Url> {
+        let mut url = Url::parse(fx_url)?;
+        let date = match chrono::DateTime::from_timestamp(timestamp as i64, 0) {
+            Some(d) => d.format("%Y-%m-%d").to_string(),
+            None => return Err(eyre::eyre!("CurrencyConverter:historical_fx_url Invalid timestamp {}", timestamp)),
+        };
+        let path = match url.path().strip_suffix("latest") {
+            Some(base) => format!("{}{}", base, date),
+            None => return Err(eyre::eyre!("CurrencyConverter:historical_fx_url No /latest path in {} to ask for a past rate", fx_url)),
+        };
+        url.set_path(&path);
+        Ok(url)
+    }
+
+    fn parse_fx_rate(json: &Value, code: &str) -> Result<Decimal> {
+        match json["rates"][code].as_f64().and_then(f64_to_decimal) {
+            Some(rate) if rate > Decimal::ZERO => Ok(rate),
+            _ => Err(eyre::eyre!("CurrencyConverter:parse_fx_rate No rate for {}", code)),
+        }
+    }
+
+    async fn fetch_crypto_rate(code: &str, price_oracle: &PriceOracle, chain_data: &Value, price_timestamp: Option<u64>) -> Result<Decimal> {
+        let (chain_name, token, decimals) = match ReportCurrency::crypto_reference(code) {
+            Some(r) => r,
+            None => return Err(eyre::eyre!("CurrencyConverter:fetch_crypto_rate Unknown crypto unit {}", code)),
+        };
+        let network = Network::from_chain_data(chain_name, &chain_data[chain_name])?;
+        let mut balances = vec![Balance::new(token.parse::<Address>()?, code.to_owned(), code.to_owned(), decimals, U256::from(1))];
+        match price_timestamp {
+            Some(timestamp) => price_oracle.set_historical_token_prices(&network, &mut balances, timestamp).await?,
+            None => price_oracle.set_token_prices(&network, &mut balances).await?,
+        }
+        match Decimal::ONE.checked_div(balances[0].token_price) {
+            Some(rate) => Ok(rate),
+            None => Err(eyre::eyre!("CurrencyConverter:fetch_crypto_rate No price for {}", code)),
+        }
+    }
+
+    pub fn currency(&self) -> &ReportCurrency {
+        &self.currency
+    }
+
+    pub fn convert(&self, usd_value: Decimal) -> Decimal {
+        usd_value.checked_mul(self.rate).unwrap_or(Decimal::MAX)
+    }
+
+    // Converted value rounded for display, crypto units keep more digits
+    pub fn format(&self, usd_value: Decimal) -> String {
+        let dp = match self.currency {
+            ReportCurrency::Crypto(_) => 6,
+            _ => 2,
+        };
+        format!("{:.*} {}", dp, self.convert(usd_value).round_dp(dp as u32), self.currency.code())
+    }
+}
+
+#[test]
+fn test_parse_report_currency() {
+    assert_eq!(ReportCurrency::parse(""), ReportCurrency::Usd);
+    assert_eq!(ReportCurrency::parse("usd"), ReportCurrency::Usd);
+    assert_eq!(ReportCurrency::parse("eur"), ReportCurrency::Fiat("EUR".to_owned()));
+    assert_eq!(ReportCurrency::parse("ETH"), ReportCurrency::Crypto("ETH".to_owned()));
+}
+
+#[test]
+fn test_currency_conversion() {
+    let json: Value = serde_json::from_str(r#"{"amount": 1.0, "base": "USD", "date": "2024-07-15", "rates": {"EUR": 0.9178, "GBP": 0.7712}}"#).unwrap();
+    let rate = CurrencyConverter::parse_fx_rate(&json, "EUR").unwrap();
+    assert!(CurrencyConverter::parse_fx_rate(&json, "JPY").is_err());
+
+    let converter = CurrencyConverter::with_rate(ReportCurrency::Fiat("EUR".to_owned()), rate);
+    assert_eq!(converter.convert(Decimal::new(100, 0)), Decimal::new(9178, 2));
+    assert_eq!(converter.format(Decimal::new(100, 0)), "91.78 EUR");
+
+    // 1 ETH = 2500 USD
+    let converter = CurrencyConverter::with_rate(ReportCurrency::Crypto("ETH".to_owned()), Decimal::ONE / Decimal::new(2500, 0));
+    assert_eq!(converter.format(Decimal::new(1250, 0)), "0.500000 ETH");
+}
+
+#[test]
+fn test_historical_fx_url() {
+    let timestamp = crate::helpers::price_oracle::timestamp_from_date("2024-07-15").unwrap();
+    let url = CurrencyConverter::historical_fx_url("https://api.frankfurter.app/latest?from=USD", timestamp).unwrap();
+    assert_eq!(url.as_str(), "https://api.frankfurter.app/2024-07-15?from=USD");
+    assert!(CurrencyConverter::historical_fx_url("https://fx.example.com/rates?base=USD", timestamp).is_err());
+}
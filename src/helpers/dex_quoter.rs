@@ -0,0 +1,227 @@
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolCall,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::const_types::NATIVE_TOKEN_ADDRESS;
+use crate::helpers::web3_client::{Multicall, Network, UniswapV2Router, UniswapV3Quoter, Web3Client};
+
+static UNISWAP_V3_FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+
+// Per chain DEX contracts and quote tokens, read from the "dex" and "tokens" sections of chains.json
+#[derive(Clone, Debug)]
+pub struct DexConfig {
+    // Quoted and swapped through, so it has to be a Router02 with the Uniswap function names.
+    // Forks that renamed them, like TraderJoe's swapExactTokensForAVAX, can't be used.
+    pub v2_router: Option<Address>,
+    pub v3_quoter: Option<Address>,
+    // SwapRouter02, only needed to swap and not to quote
+    pub v3_router: Option<Address>,
+    pub wrapped_native: Address,
+    pub wrapped_native_decimals: u8,
+    // Wrapped native is the native balance itself, like CELO, so swaps to native don't unwrap
+    pub native_erc20: bool,
+    pub stablecoin: Option<Address>,
+    pub stablecoin_decimals: u8,
+}
+
+impl DexConfig {
+    pub fn from_chain_data(chain: &Value) -> Option<Self> {
+        let v2_router = chain["dex"]["uniswapV2Router"].as_str().and_then(|a| a.parse().ok());
+        let v3_quoter = chain["dex"]["uniswapV3Quoter"].as_str().and_then(|a| a.parse().ok());
+        if v2_router.is_none() && v3_quoter.is_none() {
+            return None;
+        }
+        Some(DexConfig {
+            v2_router,
+            v3_quoter,
+            v3_router: chain["dex"]["uniswapV3Router"].as_str().and_then(|a| a.parse().ok()),
+            wrapped_native: chain["tokens"]["WNATIVE"]["address"].as_str()?.parse().ok()?,
+            wrapped_native_decimals: chain["tokens"]["WNATIVE"]["decimals"].as_u64().unwrap_or(18) as u8,
+            native_erc20: chain["tokens"]["WNATIVE"]["nativeErc20"].as_bool().unwrap_or_default(),
+            stablecoin: chain["tokens"]["STABLECOIN"]["address"].as_str().and_then(|a| a.parse().ok()),
+            stablecoin_decimals: chain["tokens"]["STABLECOIN"]["decimals"].as_u64().unwrap_or(18) as u8,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QuoteRoute {
+    V2 { router: Address, path: Vec<Address> },
+    V3 { quoter: Address, token_in: Address, token_out: Address, fee: u32 },
+}
+
+impl QuoteRoute {
+    fn encode(&self, amount_in: U256) -> Multicall::Call {
+        match self {
+            QuoteRoute::V2 { router, path } => Multicall::Call {
+                target: *router,
+                callData: Bytes::from(UniswapV2Router::getAmountsOutCall {
+                    amountIn: amount_in,
+                    path: path.clone(),
+                }.abi_encode()),
+            },
+            QuoteRoute::V3 { quoter, token_in, token_out, fee } => Multicall::Call {
+                target: *quoter,
+                callData: Bytes::from(UniswapV3Quoter::quoteExactInputSingleCall {
+                    params: UniswapV3Quoter::QuoteExactInputSingleParams {
+                        tokenIn: *token_in,
+                        tokenOut: *token_out,
+                        amountIn: amount_in,
+                        fee: *fee,
+                        sqrtPriceLimitX96: U256::ZERO,
+                    },
+                }.abi_encode()),
+            },
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<U256> {
+        match self {
+            QuoteRoute::V2 { .. } => {
+                let UniswapV2Router::getAmountsOutReturn { amounts } = UniswapV2Router::getAmountsOutCall::abi_decode_returns(data, true).ok()?;
+                amounts.last().copied()
+            }
+            QuoteRoute::V3 { .. } => {
+                let result = UniswapV3Quoter::quoteExactInputSingleCall::abi_decode_returns(data, true).ok()?;
+                Some(result.amountOut)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QuoteRequest {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+}
+
+#[derive(Clone, Debug)]
+pub struct DexQuote {
+    pub route: QuoteRoute,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+pub struct DexQuoter {
+    config: DexConfig,
+    web3_client: Web3Client,
+}
+
+impl DexQuoter {
+    pub fn new(network: Network, config: DexConfig) -> Result<Self> {
+        Ok(DexQuoter {
+            config,
+            web3_client: Web3Client::new(network, PrivateKeySigner::random())?,
+        })
+    }
+
+    pub fn config(&self) -> &DexConfig {
+        &self.config
+    }
+
+    // DEX contracts only know the wrapped native token
+    fn routing_address(&self, token: Address) -> Address {
+        if token == NATIVE_TOKEN_ADDRESS.parse::<Address>().unwrap() {
+            self.config.wrapped_native
+        } else {
+            token
+        }
+    }
+
+    // Every V2 and V3 route we can try between two tokens
+    pub fn routes(&self, token_in: Address, token_out: Address) -> Vec<QuoteRoute> {
+        let token_in = self.routing_address(token_in);
+        let token_out = self.routing_address(token_out);
+        let mut routes = vec![];
+        if token_in == token_out {
+            return routes;
+        }
+
+        if let Some(router) = self.config.v2_router {
+            routes.push(QuoteRoute::V2 { router, path: vec![token_in, token_out] });
+            if token_in != self.config.wrapped_native && token_out != self.config.wrapped_native {
+                routes.push(QuoteRoute::V2 { router, path: vec![token_in, self.config.wrapped_native, token_out] });
+            }
+        }
+        if let Some(quoter) = self.config.v3_quoter {
+            for fee in UNISWAP_V3_FEE_TIERS {
+                routes.push(QuoteRoute::V3 { quoter, token_in, token_out, fee });
+            }
+        }
+        routes
+    }
+
+    // Quote every request over all routes in one multicall and keep the best output for each
+    pub async fn best_quotes(&self, requests: &[QuoteRequest]) -> Result<Vec<Option<DexQuote>>> {
+        let mut calls = vec![];
+        let mut call_index: Vec<(usize, QuoteRoute)> = vec![];
+        for (i, request) in requests.iter().enumerate() {
+            for route in self.routes(request.token_in, request.token_out) {
+                calls.push(route.encode(request.amount_in));
+                call_index.push((i, route));
+            }
+        }
+        if calls.is_empty() {
+            return Ok(vec![None; requests.len()]);
+        }
+
+        let results = self.web3_client.try_aggregate(calls).await?;
+
+        let mut best: Vec<Option<DexQuote>> = vec![None; requests.len()];
+        for ((i, route), data) in call_index.into_iter().zip(results) {
+            let amount_out = match data.as_ref().and_then(|d| route.decode(d)) {
+                Some(a) if !a.is_zero() => a,
+                _ => continue,
+            };
+            if best[i].as_ref().is_none_or(|q| amount_out > q.amount_out) {
+                best[i] = Some(DexQuote {
+                    route,
+                    amount_in: requests[i].amount_in,
+                    amount_out,
+                });
+            }
+        }
+        Ok(best)
+    }
+}
+
+#[test]
+fn test_quote_routes() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    let config = DexConfig::from_chain_data(&chain_data["Ethereum"]).unwrap();
+    let network = Network::new(
+        1,
+        "Ethereum".to_owned(),
+        vec!["https://ethereum.publicnode.com".parse().unwrap()],
+        "https://etherscan.io/tx/".to_owned(),
+        "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    ).unwrap();
+    let quoter = DexQuoter::new(network, config.clone()).unwrap();
+    let token: Address = "0x1f9840a85d5aF5bf1D1762F925BDADdC4201F984".parse().unwrap();
+
+    // Direct and via WNATIVE on V2, plus one V3 route per fee tier
+    let routes = quoter.routes(token, config.stablecoin.unwrap());
+    assert_eq!(routes.len(), 2 + UNISWAP_V3_FEE_TIERS.len());
+
+    // Native is routed as WNATIVE, so no hop through itself
+    let routes = quoter.routes(NATIVE_TOKEN_ADDRESS.parse().unwrap(), config.stablecoin.unwrap());
+    assert_eq!(routes[0], QuoteRoute::V2 { router: config.v2_router.unwrap(), path: vec![config.wrapped_native, config.stablecoin.unwrap()] });
+    assert_eq!(routes.len(), 1 + UNISWAP_V3_FEE_TIERS.len());
+
+    assert!(quoter.routes(config.wrapped_native, NATIVE_TOKEN_ADDRESS.parse().unwrap()).is_empty());
+}
+
+#[test]
+fn test_decode_v2_quote() {
+    let route = QuoteRoute::V2 { router: Address::ZERO, path: vec![Address::ZERO, Address::ZERO] };
+    let data = UniswapV2Router::getAmountsOutCall::abi_encode_returns(&(vec![U256::from(10), U256::from(25)],));
+    assert_eq!(route.decode(&data), Some(U256::from(25)));
+    assert_eq!(route.decode(&[]), None);
+}
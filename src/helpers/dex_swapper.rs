@@ -0,0 +1,229 @@
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    sol_types::SolCall,
+};
+use async_trait::async_trait;
+use eyre::Result;
+use serde_json::Value;
+
+use crate::helpers::dex_quoter::{DexConfig, DexQuoter, QuoteRequest, QuoteRoute};
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{self, QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
+use crate::helpers::web3_client::{self, Network, UniswapV2Router, UniswapV3SwapRouter};
+
+// Rough gas of a router swap, only used to compare quotes. The swap itself is estimated.
+static V2_SWAP_GAS: u64 = 120_000;
+static V2_HOP_GAS: u64 = 60_000;
+static V3_SWAP_GAS: u64 = 150_000;
+static UNWRAP_GAS: u64 = 30_000;
+// Seconds a built swap stays valid
+static SWAP_DEADLINE: u64 = 1200;
+
+// Swaps straight through the chain's Uniswap V2 and V3 routers, for when no aggregator covers a
+// chain or their APIs are down. Quotes come from DexQuoter, min-out is the quote less slippage.
+pub struct DexSwapper {
+    quoter: Option<DexQuoter>,
+}
+
+impl DexSwapper {
+    pub fn new(network: Network, chain_data: &Value) -> Result<Self> {
+        let quoter = match DexConfig::from_chain_data(chain_data) {
+            Some(config) => Some(DexQuoter::new(network, config)?),
+            None => None,
+        };
+        Ok(DexSwapper { quoter })
+    }
+
+    fn config(&self) -> Result<&DexConfig> {
+        match &self.quoter {
+            Some(quoter) => Ok(quoter.config()),
+            None => Err(eyre::eyre!("DexSwapper No DEX configured for this chain")),
+        }
+    }
+
+    pub fn min_out(amount_out: U256, options: &SwapOptions) -> U256 {
+        let bps = U256::from(options.slippage_bps().min(10_000));
+        amount_out * (U256::from(10_000) - bps) / U256::from(10_000)
+    }
+
+    // Whether a swap to this token has to unwrap. Where wrapped native is the native balance
+    // itself, the swap already pays out native.
+    fn unwraps_to(&self, token_out: &Address) -> Result<bool> {
+        Ok(web3_client::is_native_token(token_out) && !self.config()?.native_erc20)
+    }
+
+    fn route_gas(route: &QuoteRoute, native_out: bool) -> u64 {
+        match route {
+            QuoteRoute::V2 { path, .. } => V2_SWAP_GAS + V2_HOP_GAS * (path.len().saturating_sub(2) as u64),
+            QuoteRoute::V3 { .. } if native_out => V3_SWAP_GAS + UNWRAP_GAS,
+            QuoteRoute::V3 { .. } => V3_SWAP_GAS,
+        }
+    }
+
+    // Router a route swaps through, which is also the spender of the input
+    fn route_router(&self, route: &QuoteRoute) -> Result<Address> {
+        match route {
+            QuoteRoute::V2 { router, .. } => Ok(*router),
+            QuoteRoute::V3 { .. } => match self.config()?.v3_router {
+                Some(router) => Ok(router),
+                None => Err(eyre::eyre!("DexSwapper No Uniswap V3 router configured")),
+            },
+        }
+    }
+
+    // Router call for a route. A V3 swap to native goes to the router as wrapped native and is
+    // unwrapped to the sender in the same multicall.
+    pub fn swap_call(route: &QuoteRoute, router: Address, sender: Address, amount_in: U256, min_out: U256, native_out: bool, deadline: U256) -> Bytes {
+        match route {
+            QuoteRoute::V2 { path, .. } if native_out => UniswapV2Router::swapExactTokensForETHCall {
+                amountIn: amount_in,
+                amountOutMin: min_out,
+                path: path.clone(),
+                to: sender,
+                deadline,
+            }.abi_encode().into(),
+            QuoteRoute::V2 { path, .. } => UniswapV2Router::swapExactTokensForTokensCall {
+                amountIn: amount_in,
+                amountOutMin: min_out,
+                path: path.clone(),
+                to: sender,
+                deadline,
+            }.abi_encode().into(),
+            QuoteRoute::V3 { token_in, token_out, fee, .. } => {
+                let swap = UniswapV3SwapRouter::exactInputSingleCall {
+                    params: UniswapV3SwapRouter::ExactInputSingleParams {
+                        tokenIn: *token_in,
+                        tokenOut: *token_out,
+                        fee: *fee,
+                        recipient: if native_out { router } else { sender },
+                        amountIn: amount_in,
+                        amountOutMinimum: min_out,
+                        sqrtPriceLimitX96: U256::ZERO,
+                    },
+                }.abi_encode();
+                let mut calls: Vec<Bytes> = vec![swap.into()];
+                if native_out {
+                    calls.push(UniswapV3SwapRouter::unwrapWETH9Call { amountMinimum: min_out, recipient: sender }.abi_encode().into());
+                }
+                UniswapV3SwapRouter::multicallCall { deadline, data: calls }.abi_encode().into()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SwapProvider for DexSwapper {
+    fn name(&self) -> &'static str {
+        "dex"
+    }
+
+    fn supports_network(&self) -> bool {
+        self.quoter.is_some()
+    }
+
+    async fn quote(&self, _sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, _options: &SwapOptions) -> Result<SwapQuote> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), tokens_in)?;
+        if web3_client::is_native_token(&token_in.address) {
+            return Err(eyre::eyre!("DexSwapper:quote Only ERC-20 inputs can be swapped through the routers"));
+        }
+        let quoter = match &self.quoter {
+            Some(q) => q,
+            None => return Err(eyre::eyre!("DexSwapper:quote No DEX configured for this chain")),
+        };
+        let request = QuoteRequest { token_in: token_in.address, token_out: swap_provider::api_address(&token_out.address), amount_in: *amount_in };
+        let quote = match quoter.best_quotes(&[request]).await?.pop().flatten() {
+            Some(q) => q,
+            None => return Err(eyre::eyre!("DexSwapper:quote No pool for {}", token_in.symbol)),
+        };
+        let native_out = self.unwraps_to(&token_out.address)?;
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: quote.amount_out,
+            gas: Self::route_gas(&quote.route, native_out),
+            spender: self.route_router(&quote.route)?,
+            in_values: vec![],
+            metrics: QuoteMetrics::default(),
+            route: serde_json::to_value(&quote.route)?,
+        })
+    }
+
+    async fn build_swap(&self, sender: Address, quote: &SwapQuote, options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        let (_, amount_in) = swap_provider::single_input(self.name(), &quote.tokens_in)?;
+        let route: QuoteRoute = serde_json::from_value(quote.route.clone())?;
+        let router = self.route_router(&route)?;
+        let deadline = U256::from(chrono::Utc::now().timestamp() as u64 + SWAP_DEADLINE);
+        let native_out = self.unwraps_to(&quote.token_out.address)?;
+        Ok(SwapTx {
+            to: router,
+            data: Self::swap_call(&route, router, sender, *amount_in, Self::min_out(quote.out_amount, options), native_out, deadline),
+            value: U256::ZERO,
+            gas: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_dex_swap_calls() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    let swapper = DexSwapper::new(swap_provider::test_network(8453, "Base"), &chain_data["Base"]).unwrap();
+    assert!(swapper.supports_network());
+    assert!(!DexSwapper::new(swap_provider::test_network(81457, "Blast"), &chain_data["Blast"]).unwrap().supports_network());
+    let config = swapper.config().unwrap().clone();
+
+    let usdc = swap_provider::test_token("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "USDC", 6);
+    let eth = swap_provider::test_token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "ETH", 18);
+    let sender: Address = "0x0000000000000000000000000000000000000001".parse().unwrap();
+    let options = SwapOptions { slippage_percent: 1.0, ..SwapOptions::default() };
+    assert_eq!(DexSwapper::min_out(U256::from(1_000_000), &options), U256::from(990_000));
+
+    let v2 = QuoteRoute::V2 { router: config.v2_router.unwrap(), path: vec![usdc.address, config.wrapped_native] };
+    let quote = SwapQuote {
+        provider: "dex",
+        tokens_in: vec![(usdc.clone(), U256::from(5_000_000))],
+        token_out: eth.clone(),
+        out_amount: U256::from(2_000_000_000_000_000u64),
+        gas: DexSwapper::route_gas(&v2, true),
+        spender: swapper.route_router(&v2).unwrap(),
+        in_values: vec![],
+        metrics: QuoteMetrics::default(),
+        route: serde_json::to_value(&v2).unwrap(),
+    };
+    assert_eq!(quote.route["kind"], "v2");
+    let tx = swapper.build_swap(sender, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, config.v2_router.unwrap());
+    let call = UniswapV2Router::swapExactTokensForETHCall::abi_decode(&tx.data, true).unwrap();
+    assert_eq!(call.amountIn, U256::from(5_000_000));
+    assert_eq!(call.amountOutMin, U256::from(1_980_000_000_000_000u64));
+    assert_eq!(call.to, sender);
+
+    let v3 = QuoteRoute::V3 { quoter: config.v3_quoter.unwrap(), token_in: usdc.address, token_out: config.wrapped_native, fee: 500 };
+    let quote = SwapQuote { route: serde_json::to_value(&v3).unwrap(), ..quote };
+    let tx = swapper.build_swap(sender, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, config.v3_router.unwrap());
+    let multicall = UniswapV3SwapRouter::multicallCall::abi_decode(&tx.data, true).unwrap();
+    assert_eq!(multicall.data.len(), 2);
+    let swap = UniswapV3SwapRouter::exactInputSingleCall::abi_decode(&multicall.data[0], true).unwrap();
+    assert_eq!(swap.params.recipient, tx.to);
+    assert_eq!(swap.params.fee, 500);
+    let unwrap = UniswapV3SwapRouter::unwrapWETH9Call::abi_decode(&multicall.data[1], true).unwrap();
+    assert_eq!(unwrap.recipient, sender);
+    assert_eq!(unwrap.amountMinimum, swap.params.amountOutMinimum);
+
+    // CELO is its own wrapped token, so the V3 swap pays the sender without unwrapping
+    let celo = DexSwapper::new(swap_provider::test_network(42220, "Celo"), &chain_data["Celo"]).unwrap();
+    assert!(celo.supports_network());
+    let celo_config = celo.config().unwrap().clone();
+    let v3 = QuoteRoute::V3 { quoter: celo_config.v3_quoter.unwrap(), token_in: usdc.address, token_out: celo_config.wrapped_native, fee: 500 };
+    let quote = SwapQuote { route: serde_json::to_value(&v3).unwrap(), ..quote };
+    let tx = celo.build_swap(sender, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, celo_config.v3_router.unwrap());
+    let multicall = UniswapV3SwapRouter::multicallCall::abi_decode(&tx.data, true).unwrap();
+    assert_eq!(multicall.data.len(), 1);
+    let swap = UniswapV3SwapRouter::exactInputSingleCall::abi_decode(&multicall.data[0], true).unwrap();
+    assert_eq!(swap.params.recipient, sender);
+
+    // Native inputs have to go through an aggregator
+    assert!(swapper.quote(sender, &[(eth.clone(), U256::from(1))], &usdc, &options).await.is_err());
+}
@@ -1,16 +1,39 @@
-use alloy::{primitives::{utils::format_units, Address, U256}, signers::local::PrivateKeySigner};
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{utils::format_units, Address, U256},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
 use const_types::ChainName;
 use serde_json::{to_string_pretty, Value};
 use eyre::Result;
 use reqwest::Url;
-use std::{io::Write, sync::Arc, fs, collections::HashMap};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::{io::{IsTerminal, Write}, sync::Arc, fs, collections::HashMap};
 use tokio::{task, sync::Mutex};
 use log::{error, info, warn};
 
 use crate::{constants::const_types::Env, helpers::web3_client::*};
 use crate::constants::const_types;
+use crate::helpers::allowances::{self, AllowanceEntry, AllowanceReport, RevokeStatus};
+use crate::helpers::bridge::{self, BridgeChain, BridgeConfig, BridgeEntry, BridgeReport, BridgeRouter};
+use crate::helpers::consolidation::{
+    self, ConsolidationConfig, ConsolidationReport, ForwardEntry, ForwardReport, TokenFilter, TransferEntry, TransferStatus,
+};
+use crate::helpers::currency::{CurrencyConverter, ReportCurrency};
 use crate::helpers::odos_aggregator::OdosAggregator;
+use crate::helpers::price_oracle::{self, PriceOracle};
+use crate::helpers::report::{self, PortfolioSummary, ReportFormat, ReportRow};
+use crate::helpers::results_store::{normalize_address, ResultsStore, ScanResult};
+use crate::helpers::swap_provider::{self, SwapNotSent, SwapOptions, SwapProviderConfig, SwapQuote, SwapRejection, SwapRouter};
+use crate::helpers::sweep::{self, PlanEntry, SweepConfig, SweepEntry, SweepPlan, SweepReport, SweepStatus};
+use crate::helpers::utils::{f64_to_decimal, u256_to_decimal};
+#[cfg(feature = "sqlite")]
+use crate::helpers::results_db::{ResultsDb, TransactionRecord};
 
+static TOP_HOLDINGS: usize = 10;
+
+#[derive(Clone, Debug)]
 pub struct TokenData {
     pub address: Address,
     pub name: String,
@@ -22,6 +45,27 @@ pub struct GarbageCollector {
     signer: PrivateKeySigner,
     // Chain JSON data
     chain_data: Value,
+    price_oracle: Arc<PriceOracle>,
+    // Unix timestamp to value balances at, current prices when None
+    price_timestamp: Option<u64>,
+    report_currency: ReportCurrency,
+    fx_api_url: String,
+    // USD value below which a balance counts as dust
+    dust_threshold: Decimal,
+    results_store: ResultsStore,
+    sweep_config: SweepConfig,
+    swap_providers: SwapProviderConfig,
+    // Days of Approval logs an allowance audit reads, as blocks from each chain's block time
+    allowance_log_days: u64,
+    consolidation_config: ConsolidationConfig,
+    bridge_config: BridgeConfig,
+    #[cfg(feature = "sqlite")]
+    results_db: Option<std::sync::Mutex<ResultsDb>>,
+    report_format: ReportFormat,
+    // Directory reports are written to as <dir>/<name>.<ext>, results when None
+    report_output: Option<String>,
+    // Simulate approvals and swaps instead of sending them
+    dry_run: bool,
     debug: bool,
 }
 
@@ -30,6 +74,22 @@ impl Default for GarbageCollector {
         GarbageCollector {
             signer: PrivateKeySigner::random(),
             chain_data: Value::Null,
+            price_oracle: Arc::new(PriceOracle::default()),
+            price_timestamp: None,
+            report_currency: ReportCurrency::Usd,
+            fx_api_url: String::new(),
+            dust_threshold: Decimal::ONE,
+            results_store: ResultsStore::default(),
+            sweep_config: SweepConfig::default(),
+            swap_providers: SwapProviderConfig::default(),
+            allowance_log_days: 14,
+            consolidation_config: ConsolidationConfig::default(),
+            bridge_config: BridgeConfig::default(),
+            #[cfg(feature = "sqlite")]
+            results_db: None,
+            report_format: ReportFormat::Text,
+            report_output: None,
+            dry_run: false,
             debug: false,
         }
     }
@@ -39,8 +99,46 @@ impl GarbageCollector {
     pub fn new() -> Self {
         let chain_data = GarbageCollector::parse_json_data("data/chains.json".to_owned()).unwrap();
         let env = Env::new();
+        let price_oracle = PriceOracle::from_env(&env, &chain_data).unwrap();
+        let price_timestamp = if env.price_date.is_empty() {
+            None
+        } else {
+            Some(price_oracle::timestamp_from_date(&env.price_date).unwrap())
+        };
+        let swap_providers = SwapProviderConfig::from_env(&env);
+        let bridge_config = BridgeConfig::from_env(&env);
         GarbageCollector {
             chain_data,
+            price_oracle: Arc::new(price_oracle),
+            price_timestamp,
+            report_currency: ReportCurrency::parse(&env.report_currency),
+            fx_api_url: env.fx_api_url,
+            dust_threshold: env.dust_threshold,
+            sweep_config: SweepConfig {
+                min_value: env.sweep_min_value,
+                max_value: env.sweep_max_value,
+                drift_tolerance: env.sweep_drift_tolerance,
+            },
+            swap_providers,
+            allowance_log_days: env.allowance_log_days,
+            consolidation_config: ConsolidationConfig {
+                collector: if env.collector_address.is_empty() {
+                    None
+                } else {
+                    Some(env.collector_address.parse().unwrap())
+                },
+                destinations: consolidation::parse_destinations(&env.forward_destinations).unwrap(),
+                reserve_value: env.consolidation_reserve_value,
+                min_value: env.consolidation_min_value,
+            },
+            bridge_config,
+            #[cfg(feature = "sqlite")]
+            results_db: if env.results_db.is_empty() {
+                None
+            } else {
+                Some(std::sync::Mutex::new(ResultsDb::open(&env.results_db).unwrap()))
+            },
+            dry_run: env.dry_run,
             debug: env.debug,
             ..Default::default()
         }
@@ -51,54 +149,176 @@ impl GarbageCollector {
         self.signer = signer_;
     }
 
-    pub fn read_all_non_zero_balances() -> Result<()> {
-        let dir = fs::read_dir("results")?;
-        for entry in dir {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let file_name = path.file_name().unwrap().to_str().unwrap();
-                if file_name.starts_with("tokens_") {
-                    let address = file_name.replace("tokens_", "").replace(".json", "");
-                    info!("Reading non zero balances for address: {}", address);
-                    GarbageCollector::read_non_zero_balances(address)?;
-                }
+    // Value scans at a past unix timestamp instead of current prices
+    pub fn set_price_timestamp(&mut self, timestamp: Option<u64>) {
+        self.price_timestamp = timestamp;
+    }
+
+    // Balances worth less than this many USD are reported as dust
+    pub fn set_dust_threshold(&mut self, threshold: Decimal) {
+        self.dust_threshold = threshold;
+    }
+
+    // Write reports as files in the given format besides the terminal output, into the output directory if given
+    pub fn set_report_format(&mut self, format: ReportFormat, output_dir: Option<String>) {
+        self.report_format = format;
+        self.report_output = output_dir;
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    // Consolidate and forward to this address on every chain instead of the configured ones
+    pub fn set_collector(&mut self, collector: Address) {
+        self.consolidation_config.collector = Some(collector);
+        self.consolidation_config.destinations.clear();
+    }
+
+    // Bridge native balances to this chain instead of the configured one
+    pub fn set_bridge_target(&mut self, chain: String) {
+        self.bridge_config.target_chain = Some(chain);
+    }
+
+    // Falls back to USD when the conversion rate can't be fetched
+    pub async fn currency_converter(&self) -> CurrencyConverter {
+        if self.report_currency == ReportCurrency::Usd {
+            return CurrencyConverter::usd();
+        }
+        match CurrencyConverter::new(self.report_currency.clone(), &self.fx_api_url, &self.price_oracle, &self.chain_data, self.price_timestamp).await {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Could not get {} rate, reporting in USD: {:?}", self.report_currency.code(), e);
+                CurrencyConverter::usd()
             }
         }
-        Ok(())
     }
 
-    pub fn read_non_zero_balances(target_address: String) -> Result<()> {
-        let file_path = format!("results/tokens_{}.json", target_address.to_lowercase());
-        let contents = match fs::read_to_string(file_path) {
-            Ok(c) => c,
-            Err(_) => return Err(eyre::eyre!("Failed to read file")),
-        };
-        let v: HashMap<String, Vec<Balance>> = serde_json::from_str(&contents)?;
-        Self::output_report(&v);
+    pub async fn read_all_non_zero_balances(&self) -> Result<()> {
+        let converter = self.currency_converter().await;
+        let mut rows = vec![];
+        for address in self.results_store.wallets()? {
+            info!("Reading non zero balances for address: {}", address);
+            rows.extend(self.read_non_zero_balances_with(address, &converter)?);
+        }
+        self.write_report(&rows, "report", &converter)?;
+        self.write_portfolio_summary(&rows, &converter)
+    }
+
+    // Merge every wallet into one portfolio view and print it
+    fn write_portfolio_summary(&self, rows: &[ReportRow], converter: &CurrencyConverter) -> Result<()> {
+        let summary = PortfolioSummary::from_rows(
+            rows,
+            converter.convert(self.dust_threshold),
+            TOP_HOLDINGS,
+            converter.currency().code(),
+        );
+        println!("=================================");
+        print!("{}", report::render_summary_text(&summary));
+        if self.report_format == ReportFormat::Text {
+            return Ok(());
+        }
+        let path = self.report_path("portfolio");
+        report::write_summary(self.report_format, &summary, &path)?;
+        info!("Portfolio summary written to {}", path);
         Ok(())
     }
 
-    fn output_report(balances: &HashMap<String, Vec<Balance>>) {
-        let mut total_balance: f64 = 0.0;
-        for (k, v) in balances.iter() {
-            println!("Chain: {}", k);
-            let mut total_balance_for_chain: f64 = 0.0;
-            for balance in v.iter() {
-                if let Ok(converted_balance) = format_units(balance.balance, balance.decimals) {
-                    let value = converted_balance.parse::<f64>().unwrap() * balance.token_price;
-                    total_balance_for_chain += value;
-                    println!("Token: {}, Balance: {}, Value: {}", balance.token_symbol, converted_balance, value);
-                } else {
-                    println!("Token: {}, Balance: Failed to format balance", balance.token_symbol);
-                }
+    pub async fn read_non_zero_balances(&self, target_address: String) -> Result<()> {
+        let converter = self.currency_converter().await;
+        let rows = self.read_non_zero_balances_with(target_address.clone(), &converter)?;
+        self.write_report(&rows, &format!("report_{}", normalize_address(&target_address)?), &converter)
+    }
+
+    fn read_non_zero_balances_with(&self, target_address: String, converter: &CurrencyConverter) -> Result<Vec<ReportRow>> {
+        let scan = self.results_store.latest(&target_address)?;
+        self.output_report(&scan.balances, converter);
+        Ok(report::build_rows(&scan.wallet, &scan.balances, &self.explorers(), converter))
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn results_db(&self) -> Result<std::sync::MutexGuard<'_, ResultsDb>> {
+        match &self.results_db {
+            Some(db) => Ok(db.lock().unwrap()),
+            None => Err(eyre::eyre!("RESULTS_DB is not set")),
+        }
+    }
+
+    // Copy JSON results into the database, returns the number of new scans
+    #[cfg(feature = "sqlite")]
+    pub fn import_results_to_db(&self) -> Result<usize> {
+        self.results_db()?.import_json(&self.results_store)
+    }
+
+    // Transactions are recorded once they were sent, so a database error is logged and the run goes on
+    #[cfg(feature = "sqlite")]
+    pub fn record_transaction(&self, record: &TransactionRecord) {
+        if let Some(db) = &self.results_db {
+            if let Err(e) = db.lock().unwrap().record_transaction(record) {
+                error!("Error recording {} transaction {} in the results database: {:?}", record.kind, record.hash, e);
             }
-            total_balance += total_balance_for_chain;
-            println!("Total balance for chain: {}", total_balance_for_chain);
-            println!("---------------------------------\n");
         }
-        println!("Total balance: {}", total_balance);
-    } 
+    }
+
+    // Print every wallet's latest balance of a token, by symbol or address
+    #[cfg(feature = "sqlite")]
+    pub async fn print_token_balances(&self, token: &str) -> Result<()> {
+        let holdings = self.results_db()?.token_balances(token)?;
+        let converter = self.currency_converter().await;
+        let mut total = Decimal::ZERO;
+        for holding in holdings.iter() {
+            total += holding.value;
+            println!(
+                "{}  {:<10} {:>24} {:<8} {:>16}",
+                holding.wallet, holding.chain, holding.amount, holding.symbol, converter.format(holding.value),
+            );
+        }
+        println!("Total: {} in {} balances", converter.format(total), holdings.len());
+        Ok(())
+    }
+
+    // Print the total value of every stored scan of a wallet
+    #[cfg(feature = "sqlite")]
+    pub async fn print_value_history(&self, wallet: &str) -> Result<()> {
+        let history = self.results_db()?.value_history(wallet)?;
+        let converter = self.currency_converter().await;
+        for (timestamp, value) in history {
+            let date = chrono::DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+            println!("{}  {:>16}", date.format("%Y-%m-%d %H:%M:%S"), converter.format(value));
+        }
+        Ok(())
+    }
+
+    // Explorer URL of every chain, keyed like the scan results
+    fn explorers(&self) -> HashMap<String, String> {
+        match self.chain_data.as_object() {
+            Some(chains) => chains.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v["explorer"].as_str()?.to_owned())))
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+
+    fn write_report(&self, rows: &[ReportRow], name: &str, converter: &CurrencyConverter) -> Result<()> {
+        if self.report_format == ReportFormat::Text {
+            return Ok(());
+        }
+        let path = self.report_path(name);
+        report::write_report(self.report_format, rows, converter.currency().code(), &path)?;
+        info!("Report written to {}", path);
+        Ok(())
+    }
+
+    // Report files go to the --output directory, results/ by default, one file per wallet
+    fn report_path(&self, name: &str) -> String {
+        let dir = self.report_output.as_deref().unwrap_or("results").trim_end_matches('/');
+        format!("{}/{}.{}", dir, name, self.report_format.extension())
+    }
+
+    fn output_report(&self, balances: &HashMap<String, Vec<Balance>>, converter: &CurrencyConverter) {
+        let color = std::io::stdout().is_terminal();
+        print!("{}", report::render_terminal(balances, converter, self.dust_threshold, color));
+    }
 
     // Parse JSON file
     fn parse_json_data(file_path: String) -> Result<Value> {
@@ -130,47 +350,6 @@ impl GarbageCollector {
         Ok(token_data.clone())
     }
 
-    async fn get_token_prices(chain_name: &str, token_balances: &mut [Balance]) -> Result<()> {
-        let chain = match chain_name {
-            "Zksync" => "era".to_owned(),
-            "Nova" => "arbitrum_nova".to_owned(),
-            v => v.to_owned(),
-        };
-
-        let mut url = "https://coins.llama.fi/prices/current/".to_owned();
-        token_balances.iter().enumerate().for_each(|(i, token_balance)| {
-            let token_address = token_balance.token_address;
-            url.push_str(
-                format!(
-                    "{}:{}{}",
-                    chain,
-                    token_address,
-                    if i + 1 == token_balances.len() { "" } else { "," },
-                ).as_str()
-            );
-        });
-
-        let url = Url::parse(&url)?;
-        let res = reqwest::get(url).await?;
-        let json: Value = res.json().await?;
-        let coins = &json["coins"];
-        if coins.is_null() {
-            return Err(eyre::eyre!("Coins data is null"));
-        }
-
-        for (k, v) in coins.as_object().unwrap() {
-            let token_address: Address = {
-                let temp: Vec<&str> = k.split(':').collect();
-                temp[1].parse::<Address>().unwrap()
-            };
-            let token_balance = token_balances.iter_mut().find(|t_b| t_b.token_address == token_address);
-            if let Some(t_b) = token_balance {
-                t_b.set_token_price(v["price"].as_f64().unwrap());
-            }
-        }
-        Ok(())
-    }
-
     async fn get_token_data(chain_name: &String) -> Result<Value> {
         match Self::parse_json_data(format!("data/token_lists/{}.json", chain_name)) {
             Ok(v) => Ok(v),
@@ -183,7 +362,7 @@ impl GarbageCollector {
 
     pub async fn get_non_zero_tokens(&self, target_address: Address) -> Result<()> {
 
-        let results = Arc::new(Mutex::new(HashMap::<String, Vec<Balance>>::new()));
+        let results = Arc::new(Mutex::new(ScanResult::new(&target_address.to_string(), 0, HashMap::new())?));
         let mut handles = vec![];
         let chain_data = {
             let cloned_chain_data = self.chain_data.clone();
@@ -192,13 +371,7 @@ impl GarbageCollector {
 
         for (k, v) in chain_data {
             let results_clone = Arc::clone(&results);
-            let network = match Network::new( 
-                v["id"].as_u64().unwrap() as u32,
-                k.clone(),
-                v["rpc"].as_array().unwrap().iter().map(|rpc| Url::parse(rpc.as_str().unwrap()).unwrap()).collect(),
-                v["explorer"].to_string(),
-                v["multicall"].as_str().unwrap().parse::<Address>().unwrap_or(Address::ZERO),
-            ) {
+            let network = match Network::from_chain_data(&k, &v) {
                 Ok(n) => n,
                 Err(e) => {
                     error!("Error creating network {} : {:?}", k, e);
@@ -206,6 +379,8 @@ impl GarbageCollector {
                 }
             };
             let current_signer = self.signer.clone();
+            let price_oracle = Arc::clone(&self.price_oracle);
+            let price_timestamp = self.price_timestamp;
             let handle = task::spawn(async move {
                 let token_list_result = Self::get_token_data(&k).await;
                 let token_list = match token_list_result {
@@ -222,6 +397,19 @@ impl GarbageCollector {
                     }
                 }).collect();
 
+                // Add the wrapped native token when the token list lacks it, it is unwrapped by sweeps
+                if let Some(wrapped) = sweep::wrapped_native(&v) {
+                    if !token_datas.iter().any(|t| t.address == wrapped) {
+                        let name = v["tokens"]["WNATIVE"]["name"].as_str().unwrap_or_default().to_owned();
+                        token_datas.push(TokenData {
+                            address: wrapped,
+                            name: name.clone(),
+                            symbol: name,
+                            decimals: v["tokens"]["WNATIVE"]["decimals"].as_u64().unwrap_or(18) as u8,
+                        });
+                    }
+                }
+
                 // Add native token
                 token_datas.push(TokenData {
                     address: "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE".parse::<Address>().unwrap(),
@@ -231,23 +419,32 @@ impl GarbageCollector {
                 });
     
                 let res = GarbageCollector::get_non_zero_tokens_for_chain(
-                    network,
+                    network.clone(),
                     target_address,
                     token_datas,
                     current_signer,
                 ).await;
                 
-                let mut balance_list = match res {
-                    Ok(b_l) => b_l,
+                let (mut balance_list, block_number) = match res {
+                    Ok(r) => r,
                     Err(e) => {
                         error!("Error getting balance list: {:?}", e);
                         return;
                     }
                 };
                 if !balance_list.is_empty() {
-                    GarbageCollector::get_token_prices(&k, &mut balance_list).await.unwrap();
+                    let prices_result = match price_timestamp {
+                        Some(t) => price_oracle.set_historical_token_prices(&network, &mut balance_list, t).await,
+                        None => price_oracle.set_token_prices(&network, &mut balance_list).await,
+                    };
+                    if let Err(e) = prices_result {
+                        error!("Error getting token prices for {}: {:?}", k, e);
+                    }
                     let mut results = results_clone.lock().await;
-                    results.insert(k.to_string(), balance_list);
+                    results.block_numbers.insert(k.to_string(), block_number);
+                    let priced_at = price_timestamp.unwrap_or(chrono::Utc::now().timestamp() as u64);
+                    results.price_timestamps.insert(k.to_string(), priced_at);
+                    results.balances.insert(k.to_string(), balance_list);
                 }
             });
             handles.push(handle);
@@ -257,10 +454,21 @@ impl GarbageCollector {
             handle.await.unwrap();
         }
 
-        let final_result = results.lock().await;
+        let mut final_result = results.lock().await;
+        final_result.timestamp = chrono::Utc::now().timestamp() as u64;
         // Output report
-        Self::output_report(&final_result);
-        Self::write_to_json_file(format!("results/tokens_{}.json", target_address), "results", &*final_result)?;
+        let converter = self.currency_converter().await;
+        self.output_report(&final_result.balances, &converter);
+        let path = self.results_store.save(&final_result)?;
+        info!("Scan saved to {}", path.display());
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = &self.results_db {
+            if let Err(e) = db.lock().unwrap().insert_scan(&final_result) {
+                error!("Error saving scan to the results database: {:?}", e);
+            }
+        }
+        let rows = report::build_rows(&final_result.wallet, &final_result.balances, &self.explorers(), &converter);
+        self.write_report(&rows, &format!("report_{}", final_result.wallet), &converter)?;
         Ok(())
     }
 
@@ -269,30 +477,1120 @@ impl GarbageCollector {
         target_wallet: Address,
         token_datas: Vec<TokenData>,
         signer: PrivateKeySigner,
-    ) -> Result<Vec<Balance>> {
+    ) -> Result<(Vec<Balance>, u64)> {
         let mut web3_client = Web3Client::new(network, signer).unwrap();
         
-        let balance_list =  match web3_client.call_balance(target_wallet, token_datas).await {
+        let (balance_list, block_number) = match web3_client.call_balance(target_wallet, token_datas).await {
             Ok(b_l) => b_l,
             Err(e) => {
                 return Err(eyre::eyre!("Error getting balance list: {:?}", e));
             }
         };
-        Ok(balance_list)
+        Ok((balance_list, block_number))
+    }
+
+    // Outstanding allowances of a wallet on every chain, from its Approval logs over the last
+    // allowance_log_days days and from the known spenders of its scanned tokens, also inside Permit2
+    pub async fn audit_allowances(&self, wallet: Address) -> Result<AllowanceReport> {
+        let wallet_key = normalize_address(&wallet.to_string())?;
+        let scan = self.results_store.latest(&wallet_key).ok();
+        let mut chains: Vec<(&String, &Value)> = self.chain_data.as_object().map(|c| c.iter().collect()).unwrap_or_default();
+        chains.sort_by_key(|(chain, _)| chain.as_str());
+
+        let audits = chains.into_iter().map(|(chain, chain_data)| {
+            let tokens: Vec<(Address, String)> = scan.as_ref()
+                .and_then(|s| s.balances.get(chain))
+                .map(|balances| balances.iter()
+                    .filter(|b| !is_native_token(&b.token_address))
+                    .map(|b| (b.token_address, b.token_symbol.clone()))
+                    .collect())
+                .unwrap_or_default();
+            async move {
+                match self.audit_chain_allowances(chain, chain_data, wallet, tokens).await {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        error!("Error auditing allowances on {}: {:?}", chain, e);
+                        vec![]
+                    }
+                }
+            }
+        });
+        let entries = futures::future::join_all(audits).await.into_iter().flatten().collect();
+
+        let report = AllowanceReport {
+            wallet: wallet_key,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            entries,
+        };
+        print!("{}", report.render());
+        let path = self.results_store.save_report(&report.wallet, "allowances", report.timestamp, &report)?;
+        info!("Allowance report saved to {}", path.display());
+        Ok(report)
+    }
+
+    async fn audit_chain_allowances(
+        &self,
+        chain: &str,
+        chain_data: &Value,
+        wallet: Address,
+        tokens: Vec<(Address, String)>,
+    ) -> Result<Vec<AllowanceEntry>> {
+        let network = Network::from_chain_data(chain, chain_data)?;
+        let web3_client = Web3Client::new(network.clone(), self.signer.clone())?;
+
+        let mut spenders = allowances::known_spenders(chain_data);
+        if OdosAggregator::is_supported(chain) {
+            match OdosAggregator::new(self.signer.clone(), network, vec![])?.router_address().await {
+                Ok(router) => spenders.push(("Odos Router".to_owned(), router)),
+                Err(e) => warn!("Could not get the Odos router on {}: {:?}", chain, e),
+            }
+        }
+
+        let mut pairs: Vec<(Address, Address)> = vec![];
+        let to_block = web3_client.get_block_number().await?;
+        let from_block = to_block.saturating_sub(allowances::log_window_blocks(chain_data, self.allowance_log_days));
+        match web3_client.approval_logs(wallet, from_block, to_block).await {
+            Ok(logged) => pairs.extend(logged),
+            Err(e) => warn!("Could not read Approval logs on {}, checking known spenders only: {:?}", chain, e),
+        }
+        for (token, _) in tokens.iter() {
+            pairs.extend(spenders.iter().map(|(_, spender)| (*token, *spender)));
+        }
+        let mut seen = std::collections::HashSet::new();
+        pairs.retain(|pair| seen.insert(*pair));
+
+        let entry = |token: Address, spender: Address, allowance: U256, via_permit2: bool| AllowanceEntry {
+            chain: chain.to_owned(),
+            token,
+            symbol: tokens.iter().find(|(t, _)| *t == token).map(|(_, s)| s.clone()).unwrap_or_default(),
+            spender,
+            spender_name: spenders.iter().find(|(_, s)| *s == spender).map(|(name, _)| name.clone()),
+            allowance,
+            via_permit2,
+        };
+        let amounts = web3_client.allowances_of(wallet, &pairs).await?;
+        let mut entries: Vec<AllowanceEntry> = pairs.into_iter().zip(amounts).filter_map(|((token, spender), amount)| {
+            Some(entry(token, spender, amount.filter(|a| !a.is_zero())?, false))
+        }).collect();
+
+        // Tokens Permit2 may spend can be passed on to the known spenders inside Permit2
+        let permit2: Address = PERMIT2_ADDRESS.parse()?;
+        let mut permit2_pairs = vec![];
+        for e in entries.iter().filter(|e| e.spender == permit2) {
+            permit2_pairs.extend(spenders.iter().filter(|(_, s)| *s != permit2).map(|(_, spender)| (e.token, *spender)));
+        }
+        if !permit2_pairs.is_empty() {
+            let amounts = web3_client.permit2_allowances_of(wallet, &permit2_pairs).await?;
+            entries.extend(permit2_pairs.into_iter().zip(amounts).filter_map(|((token, spender), amount)| {
+                Some(entry(token, spender, amount.filter(|a| !a.is_zero())?, true))
+            }));
+        }
+        Ok(entries)
+    }
+
+    // Set the selected allowances of the signer to zero and check they are gone
+    pub async fn revoke_allowances(&self, entries: &[AllowanceEntry]) -> Result<Vec<(AllowanceEntry, RevokeStatus)>> {
+        let owner = self.signer.address();
+        let mut results = vec![];
+        let mut clients: HashMap<String, Web3Client> = HashMap::new();
+        for entry in entries {
+            if !clients.contains_key(&entry.chain) {
+                let network = Network::from_chain_data(&entry.chain, &self.chain_data[entry.chain.as_str()])?;
+                let mut web3_client = Web3Client::new(network, self.signer.clone())?;
+                web3_client.set_dry_run(self.dry_run);
+                clients.insert(entry.chain.clone(), web3_client);
+            }
+            let web3_client = &clients[&entry.chain];
+            info!("Revoking {} allowance of {} on {}", entry.token, entry.spender, entry.chain);
+            let outcome = match entry.via_permit2 {
+                true => web3_client.revoke_permit2(entry.token, entry.spender).await.map(Some),
+                false => web3_client.approve(entry.token, entry.spender, U256::ZERO, None).await,
+            };
+            let status = match outcome {
+                Ok(Some(TxOutcome::Sent(receipt))) if !receipt.status() => RevokeStatus::Failed {
+                    error: format!("transaction {} reverted", receipt.transaction_hash),
+                },
+                Ok(Some(TxOutcome::Sent(receipt))) => match Self::allowance_left(web3_client, owner, entry).await {
+                    Ok(left) if left.is_zero() => RevokeStatus::Revoked { tx_hash: receipt.transaction_hash.to_string() },
+                    Ok(left) => RevokeStatus::Failed { error: format!("allowance is still {}", left) },
+                    Err(e) => RevokeStatus::Failed { error: e.to_string() },
+                },
+                Ok(_) => RevokeStatus::Simulated,
+                Err(e) => RevokeStatus::Failed { error: e.to_string() },
+            };
+            #[cfg(feature = "sqlite")]
+            if let RevokeStatus::Revoked { tx_hash } = &status {
+                self.record_transaction(&TransactionRecord {
+                    hash: tx_hash.clone(),
+                    chain: entry.chain.clone(),
+                    wallet: owner.to_string(),
+                    kind: "revoke".to_owned(),
+                    timestamp: chrono::Utc::now().timestamp() as u64,
+                    success: true,
+                    details: Some(format!("{} for {}", entry.token, entry.spender)),
+                });
+            }
+            results.push((entry.clone(), status));
+        }
+        print!("{}", allowances::render_revokes(&results));
+        Ok(results)
+    }
+
+    async fn allowance_left(web3_client: &Web3Client, owner: Address, entry: &AllowanceEntry) -> Result<U256> {
+        match entry.via_permit2 {
+            true => web3_client.permit2_allowance_of(owner, entry.token, entry.spender).await,
+            false => web3_client.allowance_of(owner, entry.token, entry.spender).await,
+        }
+    }
+
+    // Swap small balances from the signer's latest scan to native on every chain. Candidates are
+    // read again on chain first, so a stale scan never sweeps more than is there.
+    pub async fn sweep_dust(&self) -> Result<SweepReport> {
+        let wallet = self.signer.address().to_string();
+        let scan = self.results_store.latest(&wallet)?;
+        let mut chains: Vec<&String> = scan.balances.keys().collect();
+        chains.sort();
+
+        let mut entries = vec![];
+        for chain in chains {
+            let (scanned, skipped) = sweep::select_candidates(chain, &scan.balances[chain], &self.sweep_config);
+            entries.extend(skipped);
+            if scanned.is_empty() {
+                continue;
+            }
+            let current = match self.current_balances(chain, &scanned).await {
+                Ok(current) => current,
+                Err(e) => {
+                    entries.extend(scanned.into_iter().map(|b| SweepEntry::new(chain, b, SweepStatus::Failed { error: e.to_string() })));
+                    continue;
+                }
+            };
+            for (balance, now) in scanned.iter().zip(current.iter()) {
+                if now.balance.is_zero() {
+                    entries.push(SweepEntry::new(chain, balance, SweepStatus::Skipped { reason: "no balance left".to_owned() }));
+                }
+            }
+            // The value range applies to what is left now
+            let current: Vec<Balance> = current.into_iter().filter(|b| !b.balance.is_zero()).collect();
+            let (selected, skipped) = sweep::select_candidates(chain, &current, &self.sweep_config);
+            entries.extend(skipped);
+            let (selected, unwraps) = sweep::split_wrapped_native(selected, sweep::wrapped_native(&self.chain_data[chain.as_str()]));
+            for balance in unwraps {
+                info!("Unwrapping {} {} on {}", balance.amount().unwrap_or_default(), balance.token_symbol, chain);
+                let status = self.unwrap_native(chain, balance.token_address, balance.balance, balance.value()?, &scan.balances[chain]).await
+                    .unwrap_or_else(|e| SweepStatus::Failed { error: e.to_string() });
+                entries.push(SweepEntry::new(chain, balance, status));
+            }
+            if selected.is_empty() {
+                continue;
+            }
+            let (router, native) = match self.sweep_target(chain) {
+                Ok(target) => target,
+                Err(status) => {
+                    entries.extend(selected.into_iter().map(|b| SweepEntry::new(chain, b, status.clone())));
+                    continue;
+                }
+            };
+            let native_price = Self::native_price(&scan.balances[chain], &native);
+            for batch in selected.chunks(router.max_inputs()) {
+                let tokens_in: Vec<(TokenData, U256)> = batch.iter().map(|balance| {
+                    info!("Sweeping {} {} on {}", balance.amount().unwrap_or_default(), balance.token_symbol, chain);
+                    (Self::balance_token(balance), balance.balance)
+                }).collect();
+                let statuses = self.swap_batch(chain, &router, tokens_in, &native, native_price, None).await;
+                for (balance, status) in batch.iter().zip(statuses) {
+                    entries.push(SweepEntry::new(chain, balance, status));
+                }
+            }
+        }
+        self.finish_sweep(entries).await
+    }
+
+    // Quote every sweep candidate of the signer's latest scan into a plan file to review
+    // before executing it with execute_sweep_plan
+    pub async fn plan_sweep(&self) -> Result<SweepPlan> {
+        let wallet = self.signer.address().to_string();
+        let scan = self.results_store.latest(&wallet)?;
+        let mut chains: Vec<&String> = scan.balances.keys().collect();
+        chains.sort();
+
+        let mut entries = vec![];
+        let mut skipped = vec![];
+        for chain in chains {
+            let (selected, s) = sweep::select_candidates(chain, &scan.balances[chain], &self.sweep_config);
+            skipped.extend(s);
+            let (selected, unwraps) = sweep::split_wrapped_native(selected, sweep::wrapped_native(&self.chain_data[chain.as_str()]));
+            for balance in unwraps {
+                // Unwrapping returns the amount as it is
+                let gas_value = match self.unwrap_gas_value(chain, &scan.balances[chain]).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        skipped.push(SweepEntry::new(chain, balance, SweepStatus::Failed { error: e.to_string() }));
+                        continue;
+                    }
+                };
+                if let Some(reason) = sweep::gas_skip_reason(balance.value()?, gas_value) {
+                    skipped.push(SweepEntry::new(chain, balance, SweepStatus::Skipped { reason }));
+                    continue;
+                }
+                entries.push(PlanEntry {
+                    chain: chain.clone(),
+                    token: balance.token_symbol.clone(),
+                    address: balance.token_address,
+                    decimals: balance.decimals,
+                    amount: balance.amount()?,
+                    route: sweep::UNWRAP_ROUTE.to_owned(),
+                    expected_out: balance.amount()?,
+                    min_out: balance.amount()?,
+                    value: balance.value()?,
+                    gas_value,
+                });
+            }
+            if selected.is_empty() {
+                continue;
+            }
+            let (router, native) = match self.sweep_target(chain) {
+                Ok(target) => target,
+                Err(status) => {
+                    skipped.extend(selected.into_iter().map(|b| SweepEntry::new(chain, b, status.clone())));
+                    continue;
+                }
+            };
+            let options = self.swap_options(chain)?;
+            let native_price = Self::native_price(&scan.balances[chain], &native);
+            let web3_client = Web3Client::new(router.network().clone(), self.signer.clone())?;
+            let gas_price = match Self::quote_gas_price(&web3_client, &options).await {
+                Ok(p) => p,
+                Err(e) => {
+                    skipped.extend(selected.into_iter().map(|b| SweepEntry::new(chain, b, SweepStatus::Failed { error: e.to_string() })));
+                    continue;
+                }
+            };
+            for balance in selected {
+                let tokens_in = [(Self::balance_token(balance), balance.balance)];
+                let mut quote = match router.best_quote(self.signer.address(), &tokens_in, &native, &options, gas_price).await {
+                    Ok(q) => q,
+                    Err(e) => {
+                        skipped.push(SweepEntry::new(chain, balance, SweepStatus::Skipped { reason: e.to_string() }));
+                        continue;
+                    }
+                };
+                let (out_value, gas_value) = Self::quote_values(&mut quote, native_price, gas_price)?;
+                if let Some(reason) = sweep::gas_skip_reason(out_value, gas_value) {
+                    skipped.push(SweepEntry::new(chain, balance, SweepStatus::Skipped { reason }));
+                    continue;
+                }
+                // Staleness is checked again on execution
+                if let Err(rejection) = options.guards.check(&quote.metrics, quote.metrics.block_number.unwrap_or_default()) {
+                    skipped.push(SweepEntry::new(chain, balance, SweepStatus::Rejected { rejection }));
+                    continue;
+                }
+                let expected_out = u256_to_decimal(quote.out_amount, native.decimals)?;
+                let tolerance = Decimal::ONE - self.sweep_config.drift_tolerance / Decimal::ONE_HUNDRED;
+                entries.push(PlanEntry {
+                    chain: chain.clone(),
+                    token: balance.token_symbol.clone(),
+                    address: balance.token_address,
+                    decimals: balance.decimals,
+                    amount: balance.amount()?,
+                    route: quote.provider.to_owned(),
+                    expected_out,
+                    min_out: (expected_out * tolerance).round_dp(native.decimals.into()),
+                    value: balance.value()?,
+                    gas_value,
+                });
+            }
+        }
+
+        let plan = SweepPlan {
+            wallet: normalize_address(&wallet)?,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            scan_timestamp: scan.timestamp,
+            entries,
+            skipped,
+        };
+        let converter = self.currency_converter().await;
+        print!("{}", plan.render(&converter));
+        let path = self.results_store.save_report(&plan.wallet, "plan", plan.timestamp, &plan)?;
+        info!("Sweep plan saved to {}, review it and execute it with sweep-execute --plan {}", path.display(), path.display());
+        Ok(plan)
     }
 
+    // Execute exactly the swaps of a reviewed plan. Swaps whose on-chain balance or new quote
+    // drifted beyond the tolerance are refused. Once the plan is validated, errors fail their
+    // entries only, so the report of what was sent is always saved.
+    pub async fn execute_sweep_plan(&self, plan: &SweepPlan) -> Result<SweepReport> {
+        let signer = normalize_address(&self.signer.address().to_string())?;
+        if normalize_address(&plan.wallet)? != signer {
+            return Err(eyre::eyre!("Sweep plan is for {} but the signer is {}", plan.wallet, signer));
+        }
+        plan.validate()?;
+        // Gas is valued at the native prices of the scan the plan was made from
+        let scan = match self.results_store.load(&plan.wallet, plan.scan_timestamp) {
+            Ok(scan) => Some(scan),
+            Err(e) => {
+                warn!("Could not load scan {} of the plan, gas is left unvalued: {:?}", plan.scan_timestamp, e);
+                None
+            }
+        };
+
+        let mut entries = vec![];
+        for (chain, plan_entries) in plan.chains() {
+            let scanned: &[Balance] = scan.as_ref().and_then(|s| s.balances.get(chain)).map(|b| b.as_slice()).unwrap_or_default();
+            let (unwraps, plan_entries): (Vec<&PlanEntry>, Vec<&PlanEntry>) = plan_entries.into_iter().partition(|e| e.route == sweep::UNWRAP_ROUTE);
+            for entry in unwraps {
+                let status = self.execute_planned_unwrap(chain, entry, scanned).await
+                    .unwrap_or_else(|e| SweepStatus::Failed { error: e.to_string() });
+                entries.push(entry.sweep_entry(status));
+            }
+            if plan_entries.is_empty() {
+                continue;
+            }
+            let (router, native) = match self.sweep_target(chain) {
+                Ok(target) => target,
+                Err(status) => {
+                    entries.extend(plan_entries.into_iter().map(|e| e.sweep_entry(status.clone())));
+                    continue;
+                }
+            };
+            let web3_client = match Web3Client::new(router.network().clone(), self.signer.clone()) {
+                Ok(client) => client,
+                Err(e) => {
+                    entries.extend(plan_entries.into_iter().map(|entry| entry.sweep_entry(SweepStatus::Failed { error: e.to_string() })));
+                    continue;
+                }
+            };
+            let mut ready = vec![];
+            for entry in plan_entries {
+                let refused = match router.provider(&entry.route) {
+                    Some(_) => match web3_client.get_user_balance(self.signer.address(), Some(entry.address.to_string())).await {
+                        Ok(raw) => match u256_to_decimal(raw, entry.decimals) {
+                            Ok(on_chain) => sweep::check_balance_drift(entry.amount, on_chain).map(|reason| SweepStatus::Skipped { reason }),
+                            Err(e) => Some(SweepStatus::Failed { error: e.to_string() }),
+                        },
+                        Err(e) => Some(SweepStatus::Failed { error: e.to_string() }),
+                    },
+                    None => Some(SweepStatus::Skipped { reason: format!("unknown route {}", entry.route) }),
+                };
+                match refused {
+                    Some(status) => entries.push(entry.sweep_entry(status)),
+                    None => ready.push(entry),
+                }
+            }
+            // Planned swaps stay on the aggregator they were quoted on
+            let mut routes: Vec<&str> = ready.iter().map(|e| e.route.as_str()).collect();
+            routes.sort();
+            routes.dedup();
+            for route in routes {
+                let route_entries: Vec<&PlanEntry> = ready.iter().filter(|e| e.route == route).copied().collect();
+                let max_inputs = router.provider(route).map(|p| p.max_inputs()).unwrap_or(1);
+                for batch in route_entries.chunks(max_inputs) {
+                    let mut tokens_in = vec![];
+                    for entry in batch {
+                        info!("Sweeping {} {} on {} through {} as planned", entry.amount, entry.token, chain, route);
+                        tokens_in.push((TokenData {
+                            address: entry.address,
+                            name: entry.token.clone(),
+                            symbol: entry.token.clone(),
+                            decimals: entry.decimals,
+                        }, entry.raw_amount()));
+                    }
+                    // Validated with the plan, so this only guards against a plan changed since
+                    let tokens_in: Vec<(TokenData, U256)> = match tokens_in.into_iter().map(|(token, amount)| amount.map(|a| (token, a))).collect() {
+                        Ok(tokens_in) => tokens_in,
+                        Err(e) => {
+                            entries.extend(batch.iter().map(|entry| entry.sweep_entry(SweepStatus::Failed { error: e.to_string() })));
+                            continue;
+                        }
+                    };
+                    let statuses = self.swap_batch(chain, &router, tokens_in, &native, Self::native_price(scanned, &native), Some(batch)).await;
+                    entries.extend(batch.iter().zip(statuses).map(|(entry, status)| entry.sweep_entry(status)));
+                }
+            }
+        }
+        self.finish_sweep(entries).await
+    }
+
+    // Swap providers and native output token of a chain, or the status of balances that can't be swept on it
+    fn sweep_target(&self, chain: &str) -> Result<(SwapRouter, TokenData), SweepStatus> {
+        let chain_data = &self.chain_data[chain];
+        let network = Network::from_chain_data(chain, chain_data).map_err(|e| SweepStatus::Failed { error: e.to_string() })?;
+        let router = SwapRouter::from_config(&self.swap_providers, &network, chain_data, &self.signer)
+            .map_err(|e| SweepStatus::Failed { error: e.to_string() })?;
+        // Opbnb, Core, Manta, Taiko and Nova have neither an aggregator nor a "dex" section, see test_uncovered_chains
+        if !router.is_supported() {
+            return Err(SweepStatus::Skipped { reason: format!("no swap aggregator covers {} and chains.json has no DEX for it", chain) });
+        }
+        let native = TokenData {
+            address: const_types::NATIVE_TOKEN_ADDRESS.parse().unwrap(),
+            name: chain_data["currency"].as_str().unwrap_or_default().to_owned(),
+            symbol: chain_data["currency"].as_str().unwrap_or_default().to_owned(),
+            decimals: 18,
+        };
+        Ok((router, native))
+    }
+
+    // Balances as they are on chain now, with the prices of the scan
+    async fn current_balances(&self, chain: &str, balances: &[&Balance]) -> Result<Vec<Balance>> {
+        let web3_client = Web3Client::new(Network::from_chain_data(chain, &self.chain_data[chain])?, self.signer.clone())?;
+        let mut current = vec![];
+        for balance in balances {
+            let amount = web3_client.get_user_balance(self.signer.address(), Some(balance.token_address.to_string())).await?;
+            current.push(Balance { balance: amount, ..(*balance).clone() });
+        }
+        Ok(current)
+    }
+
+    // Gas of an unwrap on a chain valued at the native price of the scanned balances, zero when unknown
+    async fn unwrap_gas_value(&self, chain: &str, balances: &[Balance]) -> Result<Decimal> {
+        let native: Address = const_types::NATIVE_TOKEN_ADDRESS.parse()?;
+        let native_price = match balances.iter().find(|b| b.token_address == native).map(|b| b.token_price) {
+            Some(price) if !price.is_zero() => price,
+            _ => return Ok(Decimal::ZERO),
+        };
+        let web3_client = Web3Client::new(Network::from_chain_data(chain, &self.chain_data[chain])?, self.signer.clone())?;
+        let gas_price = web3_client.get_gas_price(Some(1.0)).await?;
+        let cost = U256::from(sweep::UNWRAP_GAS) * U256::from(gas_price);
+        Ok((u256_to_decimal(cost, 18)? * native_price).round_sf(6).unwrap_or_default())
+    }
+
+    // Unwrap a wrapped native balance through the wrapper's withdraw, unless the gas costs more
+    // than the amount is worth. Out and gas values are at the native price of the scanned balances.
+    async fn unwrap_native(&self, chain: &str, wrapper: Address, amount: U256, out_value: Decimal, balances: &[Balance]) -> Result<SweepStatus> {
+        let gas_value = self.unwrap_gas_value(chain, balances).await?;
+        if let Some(reason) = sweep::gas_skip_reason(out_value, gas_value) {
+            return Ok(SweepStatus::Skipped { reason });
+        }
+        let mut web3_client = Web3Client::new(Network::from_chain_data(chain, &self.chain_data[chain])?, self.signer.clone())?;
+        web3_client.set_dry_run(self.dry_run);
+        let native: Address = const_types::NATIVE_TOKEN_ADDRESS.parse()?;
+        let native_price = balances.iter().find(|b| b.token_address == native).map(|b| b.token_price).filter(|p| !p.is_zero());
+        let cost_value = |cost: U256| -> Result<Decimal> {
+            match native_price {
+                Some(price) => Ok((u256_to_decimal(cost, 18)? * price).round_sf(6).unwrap_or_default()),
+                None => Ok(gas_value),
+            }
+        };
+        Ok(match web3_client.unwrap_native(wrapper, amount).await? {
+            TxOutcome::Sent(receipt) if !receipt.status() => SweepStatus::Failed {
+                error: format!("transaction {} reverted", receipt.transaction_hash),
+            },
+            TxOutcome::Sent(receipt) => SweepStatus::Unwrapped {
+                tx_hash: receipt.transaction_hash.to_string(),
+                out_value,
+                gas_value: cost_value(U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price))?,
+            },
+            TxOutcome::Simulated(tx) => SweepStatus::Simulated { out_value, gas_value: cost_value(tx.gas_cost())? },
+        })
+    }
+
+    // Unwrap a planned amount once the on-chain balance is checked against the plan. Only the
+    // chain's own wrapper is unwrapped, whatever token an edited plan routes to "unwrap".
+    async fn execute_planned_unwrap(&self, chain: &str, entry: &PlanEntry, scanned: &[Balance]) -> Result<SweepStatus> {
+        if sweep::wrapped_native(&self.chain_data[chain]) != Some(entry.address) {
+            return Ok(SweepStatus::Skipped { reason: format!("{} is not the wrapped native token of {}", entry.address, chain) });
+        }
+        let web3_client = Web3Client::new(Network::from_chain_data(chain, &self.chain_data[chain])?, self.signer.clone())?;
+        let raw = web3_client.get_user_balance(self.signer.address(), Some(entry.address.to_string())).await?;
+        let on_chain = u256_to_decimal(raw, entry.decimals)?;
+        if let Some(reason) = sweep::check_balance_drift(entry.amount, on_chain) {
+            return Ok(SweepStatus::Skipped { reason });
+        }
+        info!("Unwrapping {} {} on {} as planned", entry.amount, entry.token, chain);
+        let amount = entry.raw_amount()?;
+        self.unwrap_native(chain, entry.address, amount, entry.value, scanned).await
+    }
+
+    // Per-chain swap defaults from chains.json
+    fn swap_options(&self, chain: &str) -> Result<SwapOptions> {
+        SwapOptions::from_chain_data(&self.chain_data[chain])
+    }
+
+    fn balance_token(balance: &Balance) -> TokenData {
+        TokenData {
+            address: balance.token_address,
+            name: balance.token_name.clone(),
+            symbol: balance.token_symbol.clone(),
+            decimals: balance.decimals,
+        }
+    }
+
+    fn native_price(balances: &[Balance], native: &TokenData) -> Option<Decimal> {
+        balances.iter().find(|b| b.token_address == native.address).map(|b| b.token_price)
+    }
+
+    // Gas price quotes are compared at
+    async fn quote_gas_price(web3_client: &Web3Client, options: &SwapOptions) -> Result<u128> {
+        match options.gas_price_wei()? {
+            Some(gas_price) => Ok(gas_price),
+            None => web3_client.get_gas_price(Some(1.0)).await,
+        }
+    }
+
+    // USD value of a quote's output and of its gas. What the provider doesn't report is valued at
+    // the native price and filled into the quote's metrics for the guards.
+    fn quote_values(quote: &mut SwapQuote, native_price: Option<Decimal>, gas_price: u128) -> Result<(Decimal, Decimal)> {
+        if let Some(price) = native_price.filter(|p| !p.is_zero()) {
+            if quote.metrics.out_value.is_none() && is_native_token(&quote.token_out.address) {
+                quote.metrics.out_value = (u256_to_decimal(quote.out_amount, quote.token_out.decimals)? * price).to_f64();
+            }
+            if quote.metrics.gas_value.is_none() {
+                quote.metrics.gas_value = (u256_to_decimal(quote.gas_cost(gas_price), 18)? * price).to_f64();
+            }
+        }
+        let out_value = quote.metrics.out_value.and_then(f64_to_decimal).unwrap_or_default();
+        let gas_value = quote.metrics.gas_value.and_then(f64_to_decimal).unwrap_or_default();
+        Ok((out_value, gas_value))
+    }
+
+    // One multi-input swap, falling back to single swaps when the batch failed before anything was sent
+    async fn swap_batch(
+        &self,
+        chain: &str,
+        router: &SwapRouter,
+        tokens_in: Vec<(TokenData, U256)>,
+        native: &TokenData,
+        native_price: Option<Decimal>,
+        planned: Option<&[&PlanEntry]>,
+    ) -> Vec<SweepStatus> {
+        match self.swap_tokens_to_native_for_chain(router, tokens_in.clone(), native.clone(), native_price, planned).await {
+            Ok(s) => s,
+            Err(e) if tokens_in.len() > 1 && (self.dry_run || e.downcast_ref::<SwapNotSent>().is_some()) => {
+                warn!("Batch sweep on {} failed, swapping one by one: {:?}", chain, e);
+                let mut statuses = vec![];
+                for (i, token_in) in tokens_in.into_iter().enumerate() {
+                    statuses.push(match self.swap_tokens_to_native_for_chain(
+                        router,
+                        vec![token_in],
+                        native.clone(),
+                        native_price,
+                        planned.map(|p| &p[i..i + 1]),
+                    ).await {
+                        Ok(mut s) => s.remove(0),
+                        Err(e) => SweepStatus::Failed { error: e.to_string() },
+                    });
+                }
+                statuses
+            }
+            Err(e) => {
+                error!("Error sweeping {} on {}: {:?}", tokens_in[0].0.symbol, chain, e);
+                vec![SweepStatus::Failed { error: e.to_string() }; tokens_in.len()]
+            }
+        }
+    }
+
+    // Print, save and record the outcome of a sweep
+    async fn finish_sweep(&self, entries: Vec<SweepEntry>) -> Result<SweepReport> {
+        let report = SweepReport {
+            wallet: normalize_address(&self.signer.address().to_string())?,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            dry_run: self.dry_run,
+            entries,
+        };
+        let converter = self.currency_converter().await;
+        print!("{}", report.render(&converter));
+        let path = self.results_store.save_report(&report.wallet, "sweep", report.timestamp, &report)?;
+        info!("Sweep report saved to {}", path.display());
+        #[cfg(feature = "sqlite")]
+        for entry in report.swapped().chain(report.unwrapped()) {
+            let (tx_hash, kind) = match &entry.status {
+                SweepStatus::Swapped { tx_hash, .. } => (tx_hash, "sweep"),
+                SweepStatus::Unwrapped { tx_hash, .. } => (tx_hash, "unwrap"),
+                _ => continue,
+            };
+            self.record_transaction(&TransactionRecord {
+                hash: tx_hash.clone(),
+                chain: entry.chain.clone(),
+                wallet: report.wallet.clone(),
+                kind: kind.to_owned(),
+                timestamp: report.timestamp,
+                success: true,
+                details: Some(format!("{} {} to native", entry.amount, entry.token)),
+            });
+        }
+        Ok(report)
+    }
+
+    // Swap all inputs in one transaction on the provider with the best output after gas, or on the
+    // planned one, and return a status per input. Skips the swap when the quoted gas costs more
+    // than the output is worth, or for planned swaps when the quote drifted from the plan, and
+    // marks it rejected when a swap guard refuses the quote. In a dry run the gas of the simulated
+    // transactions is valued at the native price when it is known.
     async fn swap_tokens_to_native_for_chain(
-        network: Network,
-        token_in: TokenData,
+        &self,
+        router: &SwapRouter,
+        tokens_in: Vec<(TokenData, U256)>,
         token_out: TokenData,
-        amount_in: U256,
-        signer: PrivateKeySigner,
-    ) -> Result<()> {
-        let odos_aggregator = OdosAggregator::new(signer, network, vec![]).unwrap();
+        native_price: Option<Decimal>,
+        planned: Option<&[&PlanEntry]>,
+    ) -> Result<Vec<SweepStatus>> {
+        // Nothing is sent until the router executes the quote
+        let mut options = self.swap_options(&router.network().chain_name).map_err(swap_provider::not_sent)?;
+        // A planned swap settles for no less than the reviewed min_out
+        let planned_out = planned.map(|p| (p.iter().map(|e| e.expected_out).sum::<Decimal>(), p.iter().map(|e| e.min_out).sum::<Decimal>()));
+        if let Some((expected_out, min_out)) = planned_out {
+            options.slippage_percent = sweep::planned_slippage_percent(expected_out, min_out);
+        }
+        let mut web3_client = Web3Client::new(router.network().clone(), self.signer.clone()).map_err(swap_provider::not_sent)?;
+        web3_client.set_dry_run(self.dry_run);
+        let gas_price = Self::quote_gas_price(&web3_client, &options).await.map_err(swap_provider::not_sent)?;
 
-        odos_aggregator.swap(token_in, token_out, amount_in).await?;
+        let sender = self.signer.address();
+        let mut quote = match planned.and_then(|p| p.first()) {
+            Some(entry) => router.quote_with(&entry.route, sender, &tokens_in, &token_out, &options).await,
+            None => router.best_quote(sender, &tokens_in, &token_out, &options, gas_price).await,
+        }.map_err(swap_provider::not_sent)?;
+        if let (Some((expected_out, min_out)), Some(entry)) = (planned_out, planned.and_then(|p| p.first())) {
+            let quoted_out = u256_to_decimal(quote.out_amount, token_out.decimals).map_err(swap_provider::not_sent)?;
+            if let Some(reason) = sweep::check_quote_drift(expected_out, min_out, quoted_out, self.sweep_config.drift_tolerance) {
+                return Ok(vec![SweepStatus::Skipped { reason }; tokens_in.len()]);
+            }
+            // Below the expected output the planned slippage would undercut min_out, so tighten it.
+            // Some providers apply slippage when quoting, hence the new quote.
+            if quoted_out < expected_out {
+                options.slippage_percent = sweep::planned_slippage_percent(quoted_out, min_out);
+                quote = router.quote_with(&entry.route, sender, &tokens_in, &token_out, &options).await.map_err(swap_provider::not_sent)?;
+                let requoted_out = u256_to_decimal(quote.out_amount, token_out.decimals).map_err(swap_provider::not_sent)?;
+                if sweep::slippage_min_out(requoted_out, options.slippage_percent) < min_out {
+                    let reason = format!("quoted output {} fell below the planned minimum {}", requoted_out, min_out);
+                    return Ok(vec![SweepStatus::Skipped { reason }; tokens_in.len()]);
+                }
+            }
+        }
+        let (out_value, gas_value) = Self::quote_values(&mut quote, native_price, gas_price).map_err(swap_provider::not_sent)?;
+        if let Some(reason) = sweep::gas_skip_reason(out_value, gas_value) {
+            return Ok(vec![SweepStatus::Skipped { reason }; tokens_in.len()]);
+        }
 
-        Ok(())
+        let in_values: Vec<Decimal> = quote.in_values.iter().map(|v| f64_to_decimal(*v).unwrap_or_default()).collect();
+        let out_shares = sweep::split_by_input_value(out_value, &in_values, tokens_in.len());
+        let gas_shares = sweep::split_by_input_value(gas_value, &in_values, tokens_in.len());
+
+        info!("Swapping {} tokens on {} through {}", tokens_in.len(), router.network().chain_name, quote.provider);
+        let outcome = match router.execute(&web3_client, &quote, &options).await {
+            Ok(outcome) => outcome,
+            // A rejected batch isn't retried token by token
+            Err(e) => match e.downcast_ref::<SwapRejection>() {
+                Some(rejection) => return Ok(vec![SweepStatus::Rejected { rejection: rejection.clone() }; tokens_in.len()]),
+                None => return Err(e),
+            },
+        };
+        let receipt = match outcome.swap {
+            TxOutcome::Sent(ref receipt) => receipt,
+            TxOutcome::Simulated(_) => {
+                let gas_value = match native_price {
+                    Some(price) if !price.is_zero() => {
+                        let cost = format_units(outcome.simulated_gas_cost(), 18)?.parse::<Decimal>()?;
+                        (cost * price).round_sf(6).unwrap_or_default()
+                    }
+                    _ => gas_value,
+                };
+                let gas_shares = sweep::split_by_input_value(gas_value, &in_values, tokens_in.len());
+                return Ok(out_shares.into_iter().zip(gas_shares).map(|(out_value, gas_value)| SweepStatus::Simulated {
+                    out_value,
+                    gas_value,
+                }).collect());
+            }
+        };
+        if !receipt.status() {
+            let error = format!("transaction {} reverted", receipt.transaction_hash);
+            return Ok(vec![SweepStatus::Failed { error }; tokens_in.len()]);
+        }
+        Ok(out_shares.into_iter().zip(gas_shares).map(|(out_value, gas_value)| SweepStatus::Swapped {
+            tx_hash: receipt.transaction_hash.to_string(),
+            out_value,
+            gas_value,
+        }).collect())
+    }
+
+    // Send the signer's native balance on every chain to the collector, less the transfer's gas
+    // and L1 data fee and the reserve left for later transactions. Native prices come from the
+    // signer's latest scan, if any.
+    pub async fn consolidate_native(&self) -> Result<ConsolidationReport> {
+        let collector = match self.consolidation_config.collector {
+            Some(collector) => collector,
+            None => return Err(eyre::eyre!("Consolidation needs a collector address")),
+        };
+        let wallet = self.signer.address();
+        if collector == wallet {
+            return Err(eyre::eyre!("Collector {} is the wallet being consolidated", collector));
+        }
+        let scan = self.results_store.latest(&wallet.to_string()).ok();
+        let mut chains: Vec<&String> = self.chain_data.as_object().map(|c| c.keys().collect()).unwrap_or_default();
+        chains.sort();
+
+        let native: Address = const_types::NATIVE_TOKEN_ADDRESS.parse()?;
+        let mut entries = vec![];
+        for chain in chains {
+            let native_price = scan.as_ref()
+                .and_then(|s| s.balances.get(chain))
+                .and_then(|balances| balances.iter().find(|b| b.token_address == native))
+                .map(|b| b.token_price);
+            match self.consolidate_chain(chain, native_price, collector).await {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Error consolidating {} on {}: {:?}", wallet, chain, e);
+                    let token = self.chain_data[chain]["currency"].as_str().unwrap_or_default();
+                    entries.push(TransferEntry::new(chain, token, U256::ZERO, TransferStatus::Failed { error: e.to_string() }));
+                }
+            }
+        }
+
+        let report = ConsolidationReport {
+            wallet: normalize_address(&wallet.to_string())?,
+            collector,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            dry_run: self.dry_run,
+            entries,
+        };
+        let converter = self.currency_converter().await;
+        print!("{}", report.render(&converter));
+        let path = self.results_store.save_report(&report.wallet, "consolidation", report.timestamp, &report)?;
+        info!("Consolidation report saved to {}", path.display());
+        #[cfg(feature = "sqlite")]
+        for entry in report.sent() {
+            if let TransferStatus::Sent { tx_hash } = &entry.status {
+                self.record_transaction(&TransactionRecord {
+                    hash: tx_hash.clone(),
+                    chain: entry.chain.clone(),
+                    wallet: report.wallet.clone(),
+                    kind: "consolidate".to_owned(),
+                    timestamp: report.timestamp,
+                    success: true,
+                    details: Some(format!("{} {} to {}", entry.amount, entry.token, collector)),
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    // Transfer of one chain, None when there is nothing to move
+    async fn consolidate_chain(&self, chain: &str, native_price: Option<Decimal>, collector: Address) -> Result<Option<TransferEntry>> {
+        let chain_data = &self.chain_data[chain];
+        let token = chain_data["currency"].as_str().unwrap_or_default();
+        let network = Network::from_chain_data(chain, chain_data)?;
+        let mut web3_client = Web3Client::new(network, self.signer.clone())?;
+        web3_client.set_dry_run(self.dry_run);
+        let balance = web3_client.get_user_balance(self.signer.address(), None).await?;
+        if balance.is_zero() {
+            return Ok(None);
+        }
+        let skip = |reason: &str| Ok(Some(TransferEntry::new(chain, token, balance, TransferStatus::Skipped { reason: reason.to_owned() })));
+
+        let config = &self.consolidation_config;
+        let native_price = native_price.filter(|p| !p.is_zero());
+        let reserve = match consolidation::reserve_amount(config.reserve_value, native_price)? {
+            Some(reserve) => reserve,
+            None => return skip("no native price to size the reserve"),
+        };
+
+        // A plain transfer costs the same gas for any amount, so it is estimated on the whole balance
+        let mut tx = TransactionRequest::default()
+            .with_from(self.signer.address())
+            .with_to(collector)
+            .with_value(balance);
+        let gas_limit = web3_client.estimate_tx_gas(&tx, Some(1.0)).await?;
+        // The fee is budgeted at the max fee, what base fee plus tip leave of it stays in the wallet
+        let (gas_price, priority_fee) = web3_client.get_fees(None).await?;
+        tx = tx.with_gas_limit(gas_limit).max_fee_per_gas(gas_price).max_priority_fee_per_gas(priority_fee);
+        let l1_fee = match chain_data["l1GasOracle"].as_str() {
+            Some(oracle) => web3_client.l1_data_fee(oracle.parse()?, &tx).await?,
+            None => U256::ZERO,
+        };
+        let fee = consolidation::transfer_fee(gas_limit, gas_price, l1_fee);
+        let amount = match consolidation::sendable_amount(balance, fee, reserve) {
+            Some(amount) => amount,
+            None => return skip("balance doesn't cover the fee and the reserve"),
+        };
+
+        let mut entry = TransferEntry::new(chain, token, balance, TransferStatus::Simulated);
+        entry.amount = u256_to_decimal(amount, 18)?;
+        entry.fee = u256_to_decimal(fee, 18)?;
+        entry.reserve = u256_to_decimal(reserve, 18)?;
+        entry.value = native_price.map(|p| (entry.amount * p).round_dp(6)).unwrap_or_default();
+        if !config.min_value.is_zero() {
+            match native_price {
+                None => return skip("no native price to check the minimum value"),
+                Some(_) if entry.value < config.min_value => {
+                    entry.status = TransferStatus::Skipped { reason: format!("worth less than {} USD", config.min_value) };
+                    return Ok(Some(entry));
+                }
+                Some(_) => {}
+            }
+        }
+
+        info!("Sending {} {} on {} to {}", entry.amount, token, chain, collector);
+        entry.status = match web3_client.send_tx(tx.with_value(amount), None).await? {
+            TxOutcome::Sent(receipt) if !receipt.status() => TransferStatus::Failed {
+                error: format!("transaction {} reverted", receipt.transaction_hash),
+            },
+            TxOutcome::Sent(receipt) => TransferStatus::Sent { tx_hash: receipt.transaction_hash.to_string() },
+            TxOutcome::Simulated(_) => TransferStatus::Simulated,
+        };
+        Ok(Some(entry))
+    }
+
+    // Send the selected ERC-20 balances of the signer's latest scan as they are to each chain's
+    // destination. Balances are read again right before sending, so the whole current balance
+    // moves and tokens spent since the scan are skipped.
+    pub async fn forward_tokens(&self, filter: &TokenFilter) -> Result<ForwardReport> {
+        let wallet = self.signer.address();
+        let scan = self.results_store.latest(&wallet.to_string())?;
+        let mut chains: Vec<&String> = scan.balances.keys().collect();
+        chains.sort();
+
+        let mut entries = vec![];
+        for chain in chains {
+            let selected: Vec<&Balance> = scan.balances[chain].iter().filter(|b| filter.matches(chain, b)).collect();
+            if selected.is_empty() {
+                continue;
+            }
+            let to = match self.consolidation_config.destination(chain) {
+                Some(to) if to != wallet => to,
+                destination => {
+                    let reason = match destination {
+                        Some(_) => "destination is the wallet itself".to_owned(),
+                        None => format!("no destination for {}", chain),
+                    };
+                    let status = TransferStatus::Skipped { reason };
+                    entries.extend(selected.into_iter().map(|b| ForwardEntry::new(chain, b, destination, status.clone())));
+                    continue;
+                }
+            };
+            let web3_client = match Network::from_chain_data(chain, &self.chain_data[chain.as_str()])
+                .and_then(|network| Web3Client::new(network, self.signer.clone()))
+            {
+                Ok(mut client) => {
+                    client.set_dry_run(self.dry_run);
+                    client
+                }
+                Err(e) => {
+                    let status = TransferStatus::Failed { error: e.to_string() };
+                    entries.extend(selected.into_iter().map(|b| ForwardEntry::new(chain, b, Some(to), status.clone())));
+                    continue;
+                }
+            };
+            for balance in selected {
+                entries.push(self.forward_token(&web3_client, chain, balance, to).await);
+            }
+        }
+
+        let report = ForwardReport {
+            wallet: normalize_address(&wallet.to_string())?,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            dry_run: self.dry_run,
+            entries,
+        };
+        let converter = self.currency_converter().await;
+        print!("{}", report.render(&converter));
+        let path = self.results_store.save_report(&report.wallet, "forward", report.timestamp, &report)?;
+        info!("Forwarding report saved to {}", path.display());
+        #[cfg(feature = "sqlite")]
+        for entry in report.sent() {
+            if let TransferStatus::Sent { tx_hash } = &entry.status {
+                self.record_transaction(&TransactionRecord {
+                    hash: tx_hash.clone(),
+                    chain: entry.chain.clone(),
+                    wallet: report.wallet.clone(),
+                    kind: "forward".to_owned(),
+                    timestamp: report.timestamp,
+                    success: true,
+                    details: Some(format!("{} {} to {}", entry.amount, entry.token, entry.to.unwrap_or_default())),
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    async fn forward_token(&self, web3_client: &Web3Client, chain: &str, balance: &Balance, to: Address) -> ForwardEntry {
+        let mut entry = ForwardEntry::new(chain, balance, Some(to), TransferStatus::Simulated);
+        let on_chain = match web3_client.get_user_balance(self.signer.address(), Some(balance.token_address.to_string())).await {
+            Ok(amount) => amount,
+            Err(e) => {
+                entry.status = TransferStatus::Failed { error: e.to_string() };
+                return entry;
+            }
+        };
+        if on_chain.is_zero() {
+            entry.status = TransferStatus::Skipped { reason: "no balance left".to_owned() };
+            return entry;
+        }
+        let current = Balance { balance: on_chain, ..balance.clone() };
+        entry.amount = current.amount().unwrap_or_default();
+        entry.value = current.value().unwrap_or_default();
+
+        info!("Forwarding {} {} on {} to {}", entry.amount, entry.token, chain, to);
+        entry.status = match web3_client.transfer_token(balance.token_address, to, on_chain).await {
+            Ok(TxOutcome::Sent(receipt)) if !receipt.status() => TransferStatus::Failed {
+                error: format!("transaction {} reverted", receipt.transaction_hash),
+            },
+            Ok(TxOutcome::Sent(receipt)) => TransferStatus::Sent { tx_hash: receipt.transaction_hash.to_string() },
+            Ok(TxOutcome::Simulated(_)) => TransferStatus::Simulated,
+            Err(e) => TransferStatus::Failed { error: e.to_string() },
+        };
+        entry
+    }
+
+    // Bridge the signer's native balance on every other chain with the same native currency to
+    // the target chain, less the bridge and gas fees and the consolidation reserve. Each chain is
+    // quoted on every provider and the best amount arriving after fees wins. Native prices come
+    // from the signer's latest scan, if any.
+    pub async fn bridge_native(&self) -> Result<BridgeReport> {
+        let target = match &self.bridge_config.target_chain {
+            Some(target) => target,
+            None => return Err(eyre::eyre!("Bridging needs a target chain")),
+        };
+        if self.chain_data[target.as_str()].is_null() {
+            return Err(eyre::eyre!("Unknown target chain {}", target));
+        }
+        let to = BridgeChain::from_chain_data(target, &self.chain_data[target.as_str()])?;
+        let router = BridgeRouter::from_config(&self.bridge_config, &self.chain_data)?;
+        let wallet = self.signer.address();
+        let scan = self.results_store.latest(&wallet.to_string()).ok();
+        let mut chains: Vec<&String> = self.chain_data.as_object().map(|c| c.keys().collect()).unwrap_or_default();
+        chains.sort();
+
+        let native: Address = const_types::NATIVE_TOKEN_ADDRESS.parse()?;
+        let mut entries = vec![];
+        for chain in chains {
+            let from = match BridgeChain::from_chain_data(chain, &self.chain_data[chain.as_str()]) {
+                Ok(from) => from,
+                Err(e) => {
+                    warn!("Skipping {}: {:?}", chain, e);
+                    continue;
+                }
+            };
+            // Only native currencies that stay the same across the bridge
+            if chain == target || from.currency != to.currency || !router.supports_route(&from, &to) {
+                continue;
+            }
+            let native_price = scan.as_ref()
+                .and_then(|s| s.balances.get(chain))
+                .and_then(|balances| balances.iter().find(|b| b.token_address == native))
+                .map(|b| b.token_price);
+            match self.bridge_chain(&router, &from, &to, native_price).await {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Error bridging {} from {}: {:?}", wallet, chain, e);
+                    entries.push(BridgeEntry::new(chain, target, &from.currency, Decimal::ZERO, TransferStatus::Failed { error: e.to_string() }));
+                }
+            }
+        }
+
+        let report = BridgeReport {
+            wallet: normalize_address(&wallet.to_string())?,
+            target_chain: target.clone(),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            dry_run: self.dry_run,
+            entries,
+        };
+        let converter = self.currency_converter().await;
+        print!("{}", report.render(&converter));
+        let path = self.results_store.save_report(&report.wallet, "bridge", report.timestamp, &report)?;
+        info!("Bridge report saved to {}", path.display());
+        #[cfg(feature = "sqlite")]
+        for entry in report.sent() {
+            if let TransferStatus::Sent { tx_hash } = &entry.status {
+                self.record_transaction(&TransactionRecord {
+                    hash: tx_hash.clone(),
+                    chain: entry.from_chain.clone(),
+                    wallet: report.wallet.clone(),
+                    kind: "bridge".to_owned(),
+                    timestamp: report.timestamp,
+                    success: true,
+                    details: Some(format!("{} {} to {} via {}", entry.amount, entry.token, entry.to_chain, entry.provider)),
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    // Bridge of one chain, None when there is nothing to move
+    async fn bridge_chain(&self, router: &BridgeRouter, from: &BridgeChain, to: &BridgeChain, native_price: Option<Decimal>) -> Result<Option<BridgeEntry>> {
+        let sender = self.signer.address();
+        let mut web3_client = Web3Client::new(from.network.clone(), self.signer.clone())?;
+        web3_client.set_dry_run(self.dry_run);
+        let balance = web3_client.get_user_balance(sender, None).await?;
+        if balance.is_zero() {
+            return Ok(None);
+        }
+        let mut entry = BridgeEntry::new(from.name(), to.name(), &from.currency, u256_to_decimal(balance, 18)?, TransferStatus::Simulated);
+        let skip = |mut entry: BridgeEntry, reason: &str| {
+            entry.status = TransferStatus::Skipped { reason: reason.to_owned() };
+            Ok(Some(entry))
+        };
+
+        let native_price = native_price.filter(|p| !p.is_zero());
+        let reserve = match consolidation::reserve_amount(self.consolidation_config.reserve_value, native_price)? {
+            Some(reserve) => reserve,
+            None => return skip(entry, "no native price to size the reserve"),
+        };
+        if balance <= reserve {
+            return skip(entry, "balance doesn't cover the reserve");
+        }
+
+        // Gas and the L1 data fee hardly depend on the amount, so they are measured on a probe of
+        // half the balance before the amount is fixed and quoted again
+        let (gas_price, priority_fee) = web3_client.get_fees(None).await?;
+        let probe = router.best_quote(sender, from, to, (balance - reserve) / U256::from(2), gas_price).await?;
+        let mut tx = router.transaction(sender, &probe).await?;
+        let gas_limit = match web3_client.estimate_tx_gas(&tx, Some(1.2)).await {
+            Ok(gas) => gas,
+            Err(e) => {
+                warn!("Could not estimate the {} bridge from {}, using its quoted gas: {:?}", probe.provider, from.name(), e);
+                probe.gas as u128 * 12 / 10
+            }
+        };
+        tx = tx.with_gas_limit(gas_limit).max_fee_per_gas(gas_price).max_priority_fee_per_gas(priority_fee);
+        let l1_fee = match self.chain_data[from.name()]["l1GasOracle"].as_str() {
+            Some(oracle) => web3_client.l1_data_fee(oracle.parse()?, &tx).await?,
+            None => U256::ZERO,
+        };
+        let amount = match bridge::bridge_amount(balance, reserve, &probe, gas_limit, gas_price, l1_fee) {
+            Some(amount) => amount,
+            None => return skip(entry, "balance doesn't cover the fees and the reserve"),
+        };
+
+        let quote = router.quote_with(probe.provider, sender, from, to, amount).await?;
+        let fee = quote.native_fee + consolidation::transfer_fee(gas_limit, gas_price, l1_fee);
+        entry.provider = quote.provider.to_owned();
+        entry.amount = u256_to_decimal(quote.amount_in, 18)?;
+        entry.amount_out = u256_to_decimal(quote.amount_out, 18)?;
+        entry.fee = u256_to_decimal(fee, 18)?;
+        entry.value = native_price.map(|p| (entry.amount * p).round_dp(6)).unwrap_or_default();
+        if quote.amount_in + fee + reserve > balance {
+            return skip(entry, "bridge fee rose above what was budgeted");
+        }
+        let fee_percent = quote.fee_percent(gas_price).round_dp(2);
+        if fee_percent > self.bridge_config.max_fee_percent {
+            return skip(entry, &format!("costs {}% of the amount", fee_percent));
+        }
+        if !self.bridge_config.min_value.is_zero() {
+            match native_price {
+                None => return skip(entry, "no native price to check the minimum value"),
+                Some(_) if entry.value < self.bridge_config.min_value => {
+                    let reason = format!("worth less than {} USD", self.bridge_config.min_value);
+                    return skip(entry, &reason);
+                }
+                Some(_) => {}
+            }
+        }
+
+        info!("Bridging {} {} from {} to {} via {}", entry.amount, entry.token, from.name(), to.name(), quote.provider);
+        let tx = router.transaction(sender, &quote).await?
+            .with_gas_limit(gas_limit)
+            .max_fee_per_gas(gas_price)
+            .max_priority_fee_per_gas(priority_fee);
+        entry.status = match web3_client.send_tx(tx, None).await? {
+            TxOutcome::Sent(receipt) if !receipt.status() => TransferStatus::Failed {
+                error: format!("transaction {} reverted", receipt.transaction_hash),
+            },
+            TxOutcome::Sent(receipt) => TransferStatus::Sent { tx_hash: receipt.transaction_hash.to_string() },
+            TxOutcome::Simulated(_) => TransferStatus::Simulated,
+        };
+        Ok(Some(entry))
     }
 }
 
@@ -303,9 +1601,46 @@ fn test_json_parser() {
     assert_eq!(result.is_ok(), true);
 }
 
+#[tokio::test]
+async fn test_planned_unwrap_of_other_token() {
+    let garbage_collector = GarbageCollector {
+        chain_data: GarbageCollector::parse_json_data("data/chains.json".to_owned()).unwrap(),
+        ..GarbageCollector::default()
+    };
+    // A token routed to "unwrap" by hand is refused before anything is read or sent
+    let entry = PlanEntry {
+        chain: "Base".to_owned(),
+        token: "USDC".to_owned(),
+        address: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".parse().unwrap(),
+        decimals: 6,
+        amount: Decimal::ONE,
+        route: sweep::UNWRAP_ROUTE.to_owned(),
+        expected_out: Decimal::ONE,
+        min_out: Decimal::ONE,
+        value: Decimal::ONE,
+        gas_value: Decimal::ZERO,
+    };
+    match garbage_collector.execute_planned_unwrap("Base", &entry, &[]).await.unwrap() {
+        SweepStatus::Skipped { reason } => assert!(reason.contains("is not the wrapped native token of Base")),
+        status => panic!("unexpected status {:?}", status),
+    }
+}
+
 #[test]
-fn test_read_non_zero_balances() {
-    let _ = GarbageCollector::read_non_zero_balances("0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19".to_owned()).unwrap();
+fn test_report_path() {
+    let mut garbage_collector = GarbageCollector::default();
+    garbage_collector.set_report_format(ReportFormat::Csv, None);
+    assert_eq!(garbage_collector.report_path("report_0xabc"), "results/report_0xabc.csv");
+    // Each wallet keeps its own file in the output directory
+    garbage_collector.set_report_format(ReportFormat::Csv, Some("out/".to_owned()));
+    assert_eq!(garbage_collector.report_path("report_0xabc"), "out/report_0xabc.csv");
+    assert_eq!(garbage_collector.report_path("report_0xdef"), "out/report_0xdef.csv");
+}
+
+#[tokio::test]
+async fn test_read_non_zero_balances() {
+    let garbage_collector = GarbageCollector::default();
+    let _ = garbage_collector.read_non_zero_balances("0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19".to_owned()).await.unwrap();
 }
 
 #[tokio::test]
@@ -333,7 +1668,15 @@ async fn test_get_native_token_price() -> Result<()> {
         U256::from(1000000000),
     );
     let mut balances = vec![balance];
-    let _ = GarbageCollector::get_token_prices("Ethereum", &mut balances).await?;
-    println!("{:?}", balances[0].token_price);
+    let network = Network::new(
+        1,
+        "Ethereum".to_owned(),
+        vec!["https://ethereum.publicnode.com".parse::<Url>().unwrap()],
+        "https://etherscan.io/tx/".to_owned(),
+        "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    )?;
+    let _ = PriceOracle::default().set_token_prices(&network, &mut balances).await?;
+    assert!(balances[0].price_source.is_some());
+    assert!(balances[0].token_price > Decimal::ZERO);
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,188 @@
+use alloy::primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{self, QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
+use crate::helpers::web3_client::Network;
+
+// KyberSwap asks clients to identify themselves
+static CLIENT_ID: &str = "garbage-collector";
+
+pub struct KyberSwapAggregator {
+    network: Network,
+    base_url: Url,
+}
+
+impl KyberSwapAggregator {
+    pub fn new(network: Network) -> Result<Self> {
+        Ok(KyberSwapAggregator {
+            network,
+            base_url: Url::parse("https://aggregator-api.kyberswap.com/")?,
+        })
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    // Chain name in KyberSwap's API paths
+    pub fn chain_key(chain_id: u32) -> Option<&'static str> {
+        match chain_id {
+            1 => Some("ethereum"),
+            10 => Some("optimism"),
+            56 => Some("bsc"),
+            137 => Some("polygon"),
+            250 => Some("fantom"),
+            324 => Some("zksync"),
+            1101 => Some("polygon-zkevm"),
+            5000 => Some("mantle"),
+            8453 => Some("base"),
+            42161 => Some("arbitrum"),
+            43114 => Some("avalanche"),
+            59144 => Some("linea"),
+            81457 => Some("blast"),
+            534352 => Some("scroll"),
+            _ => None,
+        }
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        match Self::chain_key(self.network.id) {
+            Some(chain) => Ok(self.base_url.join(&format!("{}/api/v1/{}", chain, path))?),
+            None => Err(eyre::eyre!("KyberSwapAggregator Network {} not supported by KyberSwap", self.network.chain_name)),
+        }
+    }
+
+    // KyberSwap wraps results in {"code": 0, "data": ...}
+    fn data(json: Value, context: &str) -> Result<Value> {
+        match json["code"].as_i64() {
+            Some(0) => Ok(json["data"].clone()),
+            _ => Err(eyre::eyre!("{} {}", context, json["message"])),
+        }
+    }
+
+    fn metrics(summary: &Value) -> QuoteMetrics {
+        let usd = |key: &str| summary[key].as_str().and_then(|v| v.parse::<f64>().ok());
+        let (in_value, out_value) = (usd("amountInUsd"), usd("amountOutUsd"));
+        QuoteMetrics {
+            price_impact: None,
+            value_loss_percent: match (in_value, out_value) {
+                (Some(i), Some(o)) if i > 0.0 => Some((i - o) / i * 100.0),
+                _ => None,
+            },
+            out_value,
+            gas_value: usd("gasUsd"),
+            net_out_value: None,
+            block_number: None,
+        }
+    }
+}
+
+#[async_trait]
+impl SwapProvider for KyberSwapAggregator {
+    fn name(&self) -> &'static str {
+        "kyberswap"
+    }
+
+    fn supports_network(&self) -> bool {
+        Self::chain_key(self.network.id).is_some()
+    }
+
+    // The route summary is kept as the route, KyberSwap builds the transaction from it
+    async fn quote(&self, _sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), tokens_in)?;
+        let mut query = vec![
+            ("tokenIn", swap_provider::api_address(&token_in.address).to_string()),
+            ("tokenOut", swap_provider::api_address(&token_out.address).to_string()),
+            ("amountIn", amount_in.to_string()),
+            ("gasInclude", "true".to_owned()),
+        ];
+        if !options.source_blacklist.is_empty() {
+            query.push(("excludedSources", options.source_blacklist.join(",")));
+        }
+        if !options.source_whitelist.is_empty() {
+            query.push(("includedSources", options.source_whitelist.join(",")));
+        }
+        let request = reqwest::Client::new().get(self.url("routes")?).header("x-client-id", CLIENT_ID).query(&query);
+        let data = Self::data(swap_provider::send_json(request, "KyberSwapAggregator:quote").await?, "KyberSwapAggregator:quote")?;
+        let summary = &data["routeSummary"];
+
+        let in_values = summary["amountInUsd"].as_str().and_then(|v| v.parse::<f64>().ok()).into_iter().collect();
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: swap_provider::parse_amount(&summary["amountOut"])?,
+            gas: swap_provider::parse_amount(&summary["gas"]).map(|g| g.to::<u64>()).unwrap_or_default(),
+            spender: match data["routerAddress"].as_str() {
+                Some(s) => s.parse()?,
+                None => return Err(eyre::eyre!("KyberSwapAggregator:quote Could not get approval target")),
+            },
+            in_values,
+            metrics: Self::metrics(summary),
+            route: summary.clone(),
+        })
+    }
+
+    async fn build_swap(&self, sender: Address, quote: &SwapQuote, options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        let body = serde_json::json!({
+            "routeSummary": quote.route,
+            "sender": sender,
+            "recipient": sender,
+            "slippageTolerance": options.slippage_bps(),
+        });
+        let request = reqwest::Client::new().post(self.url("route/build")?).header("x-client-id", CLIENT_ID).json(&body);
+        let data = Self::data(swap_provider::send_json(request, "KyberSwapAggregator:build_swap").await?, "KyberSwapAggregator:build_swap")?;
+        Ok(SwapTx {
+            to: match data["routerAddress"].as_str() {
+                Some(to) => to.parse()?,
+                None => return Err(eyre::eyre!("KyberSwapAggregator:build_swap Response has no router")),
+            },
+            data: data["data"].as_str().unwrap_or_default().parse::<Bytes>()?,
+            value: swap_provider::parse_amount(&data["transactionValue"]).unwrap_or_default(),
+            gas: swap_provider::parse_amount(&data["gas"]).ok().map(|g| g.to::<u128>()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_kyberswap_provider() {
+    let router = "0x6131B5fae19EA4f9D964eAc0408E4408b66337b5";
+    let url = swap_provider::mock_server(vec![
+        ("/blast/api/v1/routes", serde_json::json!({
+            "code": 0, "message": "successfully",
+            "data": {
+                "routeSummary": { "tokenIn": "0x4300000000000000000000000000000000000003", "amountIn": "5000000000000000000", "amountInUsd": "5.0", "amountOut": "1650000000000000", "amountOutUsd": "4.95", "gas": "250000", "gasUsd": "0.01" },
+                "routerAddress": router,
+            },
+        })),
+        ("/blast/api/v1/route/build", serde_json::json!({
+            "code": 0, "message": "successfully",
+            "data": { "amountIn": "5000000000000000000", "amountOut": "1650000000000000", "gas": "260000", "data": "0xe21fd0e9", "routerAddress": router, "transactionValue": "0" },
+        })),
+    ]).await;
+    let blast = swap_provider::test_network(81457, "Blast");
+    let aggregator = KyberSwapAggregator::new(blast).unwrap().with_base_url(url.clone());
+    assert!(aggregator.supports_network());
+    assert!(!KyberSwapAggregator::new(swap_provider::test_network(167000, "Taiko")).unwrap().supports_network());
+
+    let usdb = swap_provider::test_token("0x4300000000000000000000000000000000000003", "USDB", 18);
+    let eth = swap_provider::test_token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "ETH", 18);
+    let options = SwapOptions::default();
+    let quote = aggregator.quote(Address::ZERO, &[(usdb.clone(), U256::from(5))], &eth, &options).await.unwrap();
+    assert_eq!(quote.out_amount, U256::from(1_650_000_000_000_000u64));
+    assert_eq!(quote.in_values, vec![5.0]);
+    assert_eq!(quote.metrics.out_value, Some(4.95));
+    let tx = aggregator.build_swap(Address::ZERO, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, quote.spender);
+    assert_eq!(tx.gas, Some(260_000));
+
+    // Errors come back inside a 200 response
+    let failing = swap_provider::mock_server(vec![("/blast/api/v1/routes", serde_json::json!({ "code": 4008, "message": "route not found" }))]).await;
+    let aggregator = KyberSwapAggregator::new(swap_provider::test_network(81457, "Blast")).unwrap().with_base_url(failing);
+    assert!(aggregator.quote(Address::ZERO, &[(usdb, U256::from(5))], &eth, &options).await.is_err());
+}
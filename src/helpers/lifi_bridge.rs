@@ -0,0 +1,113 @@
+use alloy::primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::Url;
+
+use crate::helpers::bridge::{BridgeChain, BridgeProvider, BridgeQuote};
+use crate::helpers::swap_provider::{self, SwapTx};
+
+static SUPPORTED_CHAIN_IDS: [u32; 14] = [1, 10, 56, 100, 137, 250, 324, 5000, 8453, 42161, 43114, 59144, 81457, 534352];
+
+// LI.FI, which quotes the best of the bridges it aggregates and returns the transaction with it
+pub struct LifiBridge {
+    base_url: Url,
+}
+
+impl LifiBridge {
+    pub fn new() -> Result<Self> {
+        Ok(LifiBridge {
+            base_url: Url::parse("https://li.quest/")?,
+        })
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+}
+
+#[async_trait]
+impl BridgeProvider for LifiBridge {
+    fn name(&self) -> &'static str {
+        "lifi"
+    }
+
+    fn supports_route(&self, from: &BridgeChain, to: &BridgeChain) -> bool {
+        from.network.id != to.network.id &&
+        SUPPORTED_CHAIN_IDS.contains(&from.network.id) && SUPPORTED_CHAIN_IDS.contains(&to.network.id)
+    }
+
+    // The quote's transaction is kept as the route. Whatever it sends above the amount is the bridge fee.
+    async fn quote(&self, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256) -> Result<BridgeQuote> {
+        let query = vec![
+            ("fromChain", from.network.id.to_string()),
+            ("toChain", to.network.id.to_string()),
+            ("fromToken", Address::ZERO.to_string()),
+            ("toToken", Address::ZERO.to_string()),
+            ("fromAmount", amount.to_string()),
+            ("fromAddress", sender.to_string()),
+        ];
+        let request = reqwest::Client::new()
+            .get(self.base_url.join("v1/quote")?)
+            .query(&query);
+        let json = swap_provider::send_json(request, "LifiBridge:quote").await?;
+
+        let tx = &json["transactionRequest"];
+        let value = swap_provider::parse_amount(&tx["value"])?;
+        if value < amount {
+            return Err(eyre::eyre!("LifiBridge:quote Transaction sends {} for an amount of {}", value, amount));
+        }
+        Ok(BridgeQuote {
+            provider: self.name(),
+            from_chain: from.name().to_owned(),
+            to_chain: to.name().to_owned(),
+            amount_in: amount,
+            amount_out: swap_provider::parse_amount(&json["estimate"]["toAmount"])?,
+            native_fee: value - amount,
+            gas: swap_provider::parse_amount(&tx["gasLimit"]).map(|g| g.to::<u64>()).unwrap_or_default(),
+            route: tx.clone(),
+        })
+    }
+
+    async fn build_bridge(&self, _sender: Address, quote: &BridgeQuote) -> Result<SwapTx> {
+        let tx = &quote.route;
+        Ok(SwapTx {
+            to: match tx["to"].as_str() {
+                Some(to) => to.parse()?,
+                None => return Err(eyre::eyre!("LifiBridge:build_bridge Quote has no transaction")),
+            },
+            data: tx["data"].as_str().unwrap_or_default().parse::<Bytes>()?,
+            value: swap_provider::parse_amount(&tx["value"])?,
+            gas: swap_provider::parse_amount(&tx["gasLimit"]).ok().map(|g| g.to::<u128>()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_lifi_bridge() {
+    let diamond = "0x1231DEB6f5749EF6cE6943a275A1D3E7486F4EaE";
+    let url = swap_provider::mock_server(vec![
+        ("/v1/quote", serde_json::json!({
+            "tool": "across",
+            "estimate": { "fromAmount": "10000000000000000", "toAmount": "9990000000000000" },
+            "transactionRequest": { "to": diamond, "data": "0x1794958f", "value": "0x2386f26fc10000", "gasLimit": "0x30d40", "chainId": 8453 },
+        })),
+    ]).await;
+    let bridge = LifiBridge::new().unwrap().with_base_url(url);
+    let (base, arbitrum) = (crate::helpers::bridge::test_bridge_chain("Base"), crate::helpers::bridge::test_bridge_chain("Arbitrum"));
+    assert!(bridge.supports_route(&base, &arbitrum));
+    assert!(!bridge.supports_route(&base, &base));
+
+    let amount = U256::from(10_000_000_000_000_000u64);
+    let quote = bridge.quote(Address::ZERO, &base, &arbitrum, amount).await.unwrap();
+    assert_eq!(quote.amount_out, U256::from(9_990_000_000_000_000u64));
+    assert_eq!(quote.native_fee, U256::ZERO);
+    assert_eq!(quote.gas, 200_000);
+    let tx = bridge.build_bridge(Address::ZERO, &quote).await.unwrap();
+    assert_eq!(tx.to, diamond.parse::<Address>().unwrap());
+    assert_eq!(tx.value, amount);
+    assert_eq!(tx.gas, Some(200_000));
+
+    // A transaction sending less than the amount is not a native bridge
+    assert!(bridge.quote(Address::ZERO, &base, &arbitrum, amount * U256::from(2)).await.is_err());
+}
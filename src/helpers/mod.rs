@@ -1,4 +1,24 @@
 pub mod garbage_collector;
 pub mod web3_client;
 pub mod odos_aggregator;
+pub mod oneinch_aggregator;
+pub mod zerox_aggregator;
+pub mod paraswap_aggregator;
+pub mod kyberswap_aggregator;
+pub mod swap_provider;
+pub mod currency;
+pub mod dex_quoter;
+pub mod dex_swapper;
+pub mod price_cache;
+pub mod price_oracle;
+pub mod report;
+pub mod results_store;
+pub mod sweep;
+pub mod allowances;
+pub mod consolidation;
+pub mod bridge;
+pub mod stargate_bridge;
+pub mod lifi_bridge;
+#[cfg(feature = "sqlite")]
+pub mod results_db;
 pub mod utils;
\ No newline at end of file
@@ -1,18 +1,21 @@
 use alloy::{
-    network::TransactionBuilder,
-    primitives::{utils::parse_units, Address, Bytes, U256},
-    rpc::types::TransactionRequest,
+    primitives::{Address, Bytes, U256},
     signers::local::PrivateKeySigner
 };
+use async_trait::async_trait;
 use serde_json::Value;
 use eyre::Result;
 use serde::{Serialize, Deserialize};
 use reqwest::Url;
 
-use crate::helpers::web3_client::{Network, Web3Client, GasMultiplier};
+use crate::helpers::web3_client::{self, Network};
 use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
 
 
+// Most input tokens Odos accepts in one quote
+pub static MAX_INPUT_TOKENS: usize = 6;
+
 static SUPPORTED_NETWORKS: [&str; 12] = [
     "Ethereum",
     "Arbitrum",
@@ -51,8 +54,15 @@ struct OdosQuotePayload {
     user_addr: String,
     slippage_limit_percent: f64,
     path_viz: bool,
-    rederral_code: u8,
+    referral_code: u32,
     simple: bool,
+    compact: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    source_blacklist: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    source_whitelist: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_price: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -63,38 +73,49 @@ struct OdosAssemblePayload {
     simulate: bool,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Default)]
 #[serde(rename_all="camelCase")]
-struct OdosQuoteType {
-    block_number: u64,
-    data_gas_estimate: u64,
-    gas_estimate: f64,
-    gas_estimate_value: f64,
-    gwei_per_gas: f64,
-    in_amounts: Vec<String>,
-    in_tokens: Vec<String>,
-    in_values: Vec<f64>,
-    net_out_value: f64,
-    out_amounts: Vec<String>,
-    out_tokens: Vec<String>,
-    out_values: Vec<f64>,
-    partner_fee_percent: f64,
-    path_id: String,
-    path_viz: Option<String>,
-    percent_diff: f64,
-    price_impact: f64,
+pub struct OdosQuoteType {
+    pub block_number: u64,
+    pub data_gas_estimate: u64,
+    pub gas_estimate: f64,
+    pub gas_estimate_value: f64,
+    pub gwei_per_gas: f64,
+    pub in_amounts: Vec<String>,
+    pub in_tokens: Vec<String>,
+    pub in_values: Vec<f64>,
+    pub net_out_value: f64,
+    pub out_amounts: Vec<String>,
+    pub out_tokens: Vec<String>,
+    pub out_values: Vec<f64>,
+    pub partner_fee_percent: f64,
+    pub path_id: String,
+    pub path_viz: Option<String>,
+    pub percent_diff: f64,
+    pub price_impact: f64,
+}
+
+impl OdosQuoteType {
+    pub fn metrics(&self) -> QuoteMetrics {
+        QuoteMetrics {
+            price_impact: Some(self.price_impact),
+            // Odos reports a loss as a negative percent difference
+            value_loss_percent: Some(-self.percent_diff),
+            out_value: Some(self.out_values.iter().sum()),
+            gas_value: Some(self.gas_estimate_value),
+            net_out_value: Some(self.net_out_value),
+            block_number: Some(self.block_number),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="camelCase")]
 struct OdosAssembleType {
     gas: Option<u128>,
-    gas_price: u128,
     value: String,
     to: Address,
-    from: Address,
     data: Bytes,
-    nonce: u64,
     chain_id: u64,
 }
 
@@ -104,6 +125,7 @@ pub struct OdosAggregator {
     proxies: Vec<String>,
     quote_url: Url,
     assemble_url: Url,
+    info_url: Url,
 }
 
 impl OdosAggregator {
@@ -118,58 +140,99 @@ impl OdosAggregator {
             proxies,
             quote_url: Url::parse("https://api.odos.xyz/sor/quote/v2")?,
             assemble_url: Url::parse("https://api.odos.xyz/sor/assemble")?,
+            info_url: Url::parse("https://api.odos.xyz/info/contract-info/v2/")?,
         })
     }
 
-    pub async fn swap(
-        &self,
-        token_in: TokenData,
-        token_out: TokenData,
-        amount_in: U256,
-    ) -> Result<()> {
-        let quote = self.get_quote(&token_in, &token_out, amount_in).await?;
-        self.execute_swap(&token_in, &token_out, quote).await?;
-        Ok(())
+    pub fn with_base_url(mut self, base_url: Url) -> Result<Self> {
+        self.quote_url = base_url.join("sor/quote/v2")?;
+        self.assemble_url = base_url.join("sor/assemble")?;
+        self.info_url = base_url.join("info/contract-info/v2/")?;
+        Ok(self)
     }
 
-    async fn get_quote(
+    pub fn is_supported(chain_name: &str) -> bool {
+        SUPPORTED_NETWORKS.contains(&chain_name)
+    }
+
+    pub async fn get_quote(
         &self,
         token_in: &TokenData,
         token_out: &TokenData,
-        amount_in: U256
+        amount_in: U256,
+        options: &SwapOptions,
     ) -> Result<OdosQuoteType> {
-        if !SUPPORTED_NETWORKS.contains(&self.network.chain_name.as_str()) {
+        self.get_multi_quote(&[(token_in.clone(), amount_in)], token_out, options).await
+    }
+
+    fn payload_address(token_address: &Address) -> Address {
+        if Self::is_token_native(token_address) {
+            Address::ZERO
+        } else {
+            *token_address
+        }
+    }
+
+    fn quote_payload(
+        &self,
+        tokens_in: &[(TokenData, U256)],
+        token_out: &TokenData,
+        options: &SwapOptions,
+    ) -> Result<OdosQuotePayload> {
+        if !Self::is_supported(&self.network.chain_name) {
             return Err(eyre::eyre!(format!("OdosAggregator:get_quote Network {} not supported by Odos", self.network.chain_name)));
         }
 
-        if Self::is_token_native(&token_in.address) && Self::is_token_native(&token_out.address) {
-            return Err(eyre::eyre!("OdosAggregator:get_quote Both tokens are native"));
+        if tokens_in.is_empty() || tokens_in.len() > MAX_INPUT_TOKENS {
+            return Err(eyre::eyre!("OdosAggregator:get_quote Expected 1 to {} input tokens, got {}", MAX_INPUT_TOKENS, tokens_in.len()));
         }
 
-        let payload = OdosQuotePayload {
-            chain_id: self.network.id,
-            input_tokens: vec![PayloadTokenIn {
-                token_address: if Self::is_token_native(&token_in.address) {
-                    Address::ZERO
-                } else {
-                    token_in.address
-                },
+        if options.slippage_percent <= 0.0 || options.slippage_percent > 50.0 {
+            return Err(eyre::eyre!("OdosAggregator:get_quote Slippage {}% outside (0, 50]", options.slippage_percent));
+        }
+
+        let output_address = Self::payload_address(&token_out.address);
+        let mut input_tokens: Vec<PayloadTokenIn> = vec![];
+        for (token_in, amount_in) in tokens_in.iter() {
+            let token_address = Self::payload_address(&token_in.address);
+            if token_address == output_address {
+                return Err(eyre::eyre!("OdosAggregator:get_quote Input token {} is the output token", token_in.symbol));
+            }
+            if input_tokens.iter().any(|t| t.token_address == token_address) {
+                return Err(eyre::eyre!("OdosAggregator:get_quote Duplicate input token {}", token_in.symbol));
+            }
+            input_tokens.push(PayloadTokenIn {
+                token_address,
                 amount: amount_in.to_string(),
-            }],
+            });
+        }
+
+        Ok(OdosQuotePayload {
+            chain_id: self.network.id,
+            input_tokens,
             output_tokens: vec![PayloadTokenOut {
-                token_address: if Self::is_token_native(&token_out.address) {
-                    Address::ZERO
-                } else {
-                    token_out.address
-                },
+                token_address: output_address,
                 proportion: 1,
-            }], 
+            }],
             user_addr: self.signer.address().to_checksum(None),
-            slippage_limit_percent: 3.0,
+            slippage_limit_percent: options.slippage_percent,
             path_viz: false,
-            rederral_code: 1,
+            referral_code: options.referral_code,
             simple: true,
-        };
+            compact: options.compact,
+            source_blacklist: options.source_blacklist.clone(),
+            source_whitelist: options.source_whitelist.clone(),
+            gas_price: options.gas_price_gwei,
+        })
+    }
+
+    pub async fn get_multi_quote(
+        &self,
+        tokens_in: &[(TokenData, U256)],
+        token_out: &TokenData,
+        options: &SwapOptions,
+    ) -> Result<OdosQuoteType> {
+        let payload = self.quote_payload(tokens_in, token_out, options)?;
 
         let client = reqwest::Client::new();
         let res = client.post(self.quote_url.clone())
@@ -191,79 +254,103 @@ impl OdosAggregator {
         }
     }
 
-    async fn execute_swap(
-        &self,
-        token_in: &TokenData,
-        token_out: &TokenData,
-        quote: OdosQuoteType,
-    ) -> Result<()> {
-        let url_str = format!("https://api.odos.xyz/info/contract-info/v2/{}", self.network.id);
-        let url = Url::parse(&url_str)?;
+    // Router the swaps go through and inputs are approved to
+    pub async fn router_address(&self) -> Result<Address> {
+        let url = self.info_url.join(&self.network.id.to_string())?;
         let client = reqwest::Client::new();
         let res = client.get(url)
             .header("Content-Type", "application/json")
             .send()
             .await?;
         if res.status() != 200 {
-            return Err(eyre::eyre!("OdosAggregator:execute_swap Failed to get contract info"));
+            return Err(eyre::eyre!("OdosAggregator:router_address Failed to get contract info"));
         }
         let json: Value = res.json().await?;
-        let router_address = match json["routerAddress"].as_str() {
-            Some(addr) => addr,
-            None => return Err(eyre::eyre!("OdosAggregator:execute_swap Could not get approval target")),
-        };
-
-        let web3_client = Web3Client::new(self.network.clone(), self.signer.clone())?;
-        web3_client.approve(
-            token_in.address,
-            router_address.parse::<Address>().unwrap(),
-            parse_units(quote.in_amounts[0].as_str(), token_in.decimals).unwrap().into(),
-            Some(parse_units(quote.in_amounts[0].as_str(), token_in.decimals).unwrap().into())
-        ).await?;
+        match json["routerAddress"].as_str() {
+            Some(addr) => Ok(addr.parse::<Address>()?),
+            None => Err(eyre::eyre!("OdosAggregator:router_address Could not get approval target")),
+        }
+    }
 
+    // Turn a quoted path into a transaction, failing when Odos' own simulation of it fails
+    async fn assemble(&self, path_id: String, simulate: bool) -> Result<OdosAssembleType> {
         let payload = OdosAssemblePayload {
             user_addr: self.signer.address().to_checksum(None),
-            path_id: quote.path_id,
-            simulate: true,
+            path_id,
+            simulate,
         };
 
+        let client = reqwest::Client::new();
         let res = client.post(self.assemble_url.clone())
             .header("Content-Type", "application/json")
             .json(&payload)
             .send()
             .await?;
         if res.status() != 200 {
-            return Err(eyre::eyre!("OdosAggregator:execute_swap Failed to assempble swap"));
+            return Err(eyre::eyre!("OdosAggregator:assemble Failed to assemble swap"));
         }
 
         let json: Value = res.json().await?;
-        if !json["simulation"]["isSuccess"].as_bool().unwrap() {
-            return Err(eyre::eyre!("OdosAggregator:execute_swap Failed to simulate swap"));
-        };
-        let tx = match serde_json::from_value::<OdosAssembleType>(json["transaction"].clone()) {
-            Ok(q) => q,
-            Err(e) => return Err(eyre::eyre!(e)),
+        if simulate && !json["simulation"]["isSuccess"].as_bool().unwrap_or(false) {
+            return Err(eyre::eyre!("OdosAggregator:assemble Failed to simulate swap: {}", json["simulation"]["simulationError"]));
         };
+        match serde_json::from_value::<OdosAssembleType>(json["transaction"].clone()) {
+            Ok(tx) => Ok(tx),
+            Err(e) => Err(eyre::eyre!(e)),
+        }
+    }
 
-        let adjusted_tx = TransactionRequest::default()
-            .with_from(tx.from)
-            .with_to(tx.to)
-            .with_nonce(tx.nonce)
-            .with_input(tx.data)
-            .with_gas_price(tx.gas_price)
-            .with_gas_limit(tx.gas.unwrap_or(0));
+    fn is_token_native(token_address: &Address) -> bool {
+        web3_client::is_native_token(token_address)
+    }
+}
 
-        let gas_price_multiplier: f32 = if self.network.chain_name == "Ethereum" || self.network.chain_name == "Polygon" || self.network.chain_name == "Avalanche" {1.1} else {1.0};
+#[async_trait]
+impl SwapProvider for OdosAggregator {
+    fn name(&self) -> &'static str {
+        "odos"
+    }
 
-        let _ = web3_client.send_tx(adjusted_tx, Some(GasMultiplier::new(gas_price_multiplier, 1.1))).await?;
+    fn supports_network(&self) -> bool {
+        Self::is_supported(&self.network.chain_name)
+    }
 
-        Ok(())
+    fn max_inputs(&self) -> usize {
+        MAX_INPUT_TOKENS
     }
 
-    fn is_token_native(token_address: &Address) -> bool {
-        *token_address == Address::ZERO ||
-            *token_address == "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE".parse::<Address>().unwrap() ||
-            *token_address == "0x0000000000000000000000000000000000001010".parse::<Address>().unwrap()
+    async fn quote(&self, _sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote> {
+        let quote = self.get_multi_quote(tokens_in, token_out, options).await?;
+        let out_amount = match quote.out_amounts.first() {
+            Some(amount) => amount.parse::<U256>()?,
+            None => return Err(eyre::eyre!("OdosAggregator:quote Quote has no output amount")),
+        };
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount,
+            gas: quote.gas_estimate as u64,
+            spender: self.router_address().await?,
+            in_values: quote.in_values.clone(),
+            metrics: quote.metrics(),
+            route: serde_json::json!({ "pathId": quote.path_id }),
+        })
+    }
+
+    async fn build_swap(&self, _sender: Address, quote: &SwapQuote, _options: &SwapOptions, simulate: bool) -> Result<SwapTx> {
+        let path_id = match quote.route["pathId"].as_str() {
+            Some(p) => p.to_owned(),
+            None => return Err(eyre::eyre!("OdosAggregator:build_swap Quote has no path id")),
+        };
+        // Odos simulates the swap before handing it out, unless an approval it needs isn't sent yet
+        let tx = self.assemble(path_id, simulate).await?;
+        Ok(SwapTx {
+            to: tx.to,
+            data: tx.data,
+            value: tx.value.parse::<U256>()?,
+            gas: tx.gas,
+        })
     }
 }
 
@@ -291,17 +378,159 @@ async fn test_get_quote() {
         decimals: 18,
     };
     let amount_in = U256::from_str_radix("8ac7230489e80000", 16).unwrap();
-    let d = odos_aggregator.swap(token_in, token_out, amount_in).await;
+    let d = odos_aggregator.get_quote(&token_in, &token_out, amount_in, &SwapOptions::default()).await;
     match d {
         Ok(q) => println!("{:?}", q),
         Err(e) => println!("{:?}", e),
     }
 }
 
+#[test]
+fn test_multi_input_payload() {
+    let network = Network {
+        id: 8453,
+        chain_name: "Base".to_owned(),
+        rpc_url: vec!["https://base.publicnode.com".parse::<Url>().unwrap()],
+        explorer: "https://basescan.org/tx/".to_owned(),
+        multicall: "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    };
+    let odos_aggregator = OdosAggregator::new(PrivateKeySigner::random(), network, vec![]).unwrap();
+    let token = |address: &str, symbol: &str| TokenData {
+        address: address.parse().unwrap(),
+        name: symbol.to_owned(),
+        symbol: symbol.to_owned(),
+        decimals: 18,
+    };
+    let eth = token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "ETH");
+    let usdc = token("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "USDC");
+    let dai = token("0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb", "DAI");
+
+    let options = SwapOptions::default();
+    let payload = odos_aggregator.quote_payload(&[(usdc.clone(), U256::from(1)), (dai.clone(), U256::from(2))], &eth, &options).unwrap();
+    let json = serde_json::to_value(&payload).unwrap();
+    assert_eq!(json["inputTokens"].as_array().unwrap().len(), 2);
+    assert_eq!(json["inputTokens"][1]["amount"], "2");
+    assert_eq!(json["outputTokens"][0]["tokenAddress"], Address::ZERO.to_string());
+
+    assert!(odos_aggregator.quote_payload(&[(usdc.clone(), U256::from(1)), (usdc.clone(), U256::from(2))], &eth, &options).is_err());
+    assert!(odos_aggregator.quote_payload(&[(eth.clone(), U256::from(1))], &eth, &options).is_err());
+    assert!(odos_aggregator.quote_payload(&vec![(usdc, U256::from(1)); MAX_INPUT_TOKENS + 1], &eth, &options).is_err());
+}
+
 #[test]
 fn test_bigint() -> Result<()> {
     // Convert 0x75899e7357ec6f0e00000 to U256
     let amount = U256::from_str_radix("8ac7230489e80000", 16)?;
     println!("{:?}", amount);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_swap_options() {
+    let network = Network {
+        id: 1,
+        chain_name: "Ethereum".to_owned(),
+        rpc_url: vec!["https://ethereum-rpc.publicnode.com".parse::<Url>().unwrap()],
+        explorer: "https://etherscan.io/tx/".to_owned(),
+        multicall: "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    };
+    let odos_aggregator = OdosAggregator::new(PrivateKeySigner::random(), network, vec![]).unwrap();
+    let token = |address: &str| TokenData { address: address.parse().unwrap(), name: "T".to_owned(), symbol: "T".to_owned(), decimals: 18 };
+    let tokens_in = [(token("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), U256::from(1))];
+    let eth = token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+    let chain: Value = serde_json::json!({ "swapOptions": { "slippagePercent": 0.5, "referralCode": 7, "sourceBlacklist": ["Hashflow"], "gasPriceGwei": 12.5 } });
+    let options = SwapOptions::from_chain_data(&chain).unwrap();
+    assert!(options.compact);
+    assert_eq!(options.gas_price_wei().unwrap(), Some(12_500_000_000));
+    let json = serde_json::to_value(odos_aggregator.quote_payload(&tokens_in, &eth, &options).unwrap()).unwrap();
+    assert_eq!(json["slippageLimitPercent"], 0.5);
+    assert_eq!(json["referralCode"], 7);
+    assert_eq!(json["sourceBlacklist"][0], "Hashflow");
+    assert_eq!(json["gasPrice"], 12.5);
+    assert!(json.get("sourceWhitelist").is_none());
+
+    let defaults = SwapOptions::from_chain_data(&serde_json::json!({})).unwrap();
+    assert_eq!(defaults, SwapOptions::default());
+    let json = serde_json::to_value(odos_aggregator.quote_payload(&tokens_in, &eth, &defaults).unwrap()).unwrap();
+    assert!(json.get("gasPrice").is_none());
+
+    let too_loose = SwapOptions { slippage_percent: 60.0, ..SwapOptions::default() };
+    assert!(odos_aggregator.quote_payload(&tokens_in, &eth, &too_loose).is_err());
+}
+
+#[test]
+fn test_swap_guards() {
+    use crate::helpers::swap_provider;
+    let guards = swap_provider::SwapGuards::default();
+    let quote = OdosQuoteType {
+        block_number: 100,
+        gas_estimate_value: 0.5,
+        net_out_value: 9.5,
+        out_values: vec![10.0],
+        percent_diff: -1.0,
+        price_impact: -0.2,
+        ..Default::default()
+    };
+    assert_eq!(guards.check(&quote.metrics(), 110), Ok(()));
+    assert_eq!(
+        guards.check(&quote.metrics(), 131),
+        Err(swap_provider::SwapRejection::StaleQuote { quote_block: 100, current_block: 131, limit: 30 }),
+    );
+    assert_eq!(
+        guards.check(&OdosQuoteType { price_impact: -7.5, ..quote.clone() }.metrics(), 100),
+        Err(swap_provider::SwapRejection::PriceImpact { impact: 7.5, limit: 5.0 }),
+    );
+    assert_eq!(
+        guards.check(&OdosQuoteType { percent_diff: -12.0, ..quote.clone() }.metrics(), 100),
+        Err(swap_provider::SwapRejection::ValueLoss { loss: 12.0, limit: 10.0 }),
+    );
+    let expensive = OdosQuoteType { gas_estimate_value: 6.0, net_out_value: 4.0, ..quote.clone() };
+    assert!(matches!(guards.check(&expensive.metrics(), 100), Err(swap_provider::SwapRejection::GasCost { .. })));
+    let loose = swap_provider::SwapGuards { max_gas_fraction: 2.0, ..guards.clone() };
+    let underwater = OdosQuoteType { gas_estimate_value: 11.0, net_out_value: -1.0, ..quote };
+    assert_eq!(loose.check(&underwater.metrics(), 100), Err(swap_provider::SwapRejection::NoNetValue { net_out_value: -1.0 }));
+
+    // Rejections survive being passed on as errors
+    let report = eyre::Report::new(swap_provider::SwapRejection::NoNetValue { net_out_value: -1.0 });
+    assert!(report.downcast_ref::<swap_provider::SwapRejection>().is_some());
+}
+
+#[tokio::test]
+async fn test_odos_provider() {
+    use crate::helpers::swap_provider;
+    let router = "0x19cEeAd7105607Cd444F5ad10dd51356436095a1";
+    let url = swap_provider::mock_server(vec![
+        ("/sor/quote/v2", serde_json::json!({
+            "blockNumber": 100, "dataGasEstimate": 0, "gasEstimate": 180000.0, "gasEstimateValue": 0.4, "gweiPerGas": 0.01,
+            "inAmounts": ["1000000"], "inTokens": ["0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"], "inValues": [1.0],
+            "netOutValue": 0.59, "outAmounts": ["330000000000000"], "outTokens": ["0x0000000000000000000000000000000000000000"],
+            "outValues": [0.99], "partnerFeePercent": 0.0, "pathId": "abc", "percentDiff": -1.0, "priceImpact": -0.1,
+        })),
+        ("/sor/assemble", serde_json::json!({
+            "transaction": {
+                "gas": 200000, "gasPrice": 10000000, "value": "0", "to": router, "from": "0x0000000000000000000000000000000000000001",
+                "data": "0x83bd37f9", "nonce": 3, "chainId": 8453,
+            },
+            "simulation": { "isSuccess": true, "simulationError": null },
+        })),
+        ("/info/contract-info/v2/8453", serde_json::json!({ "routerAddress": router })),
+    ]).await;
+    let network = swap_provider::test_network(8453, "Base");
+    let odos_aggregator = OdosAggregator::new(PrivateKeySigner::random(), network, vec![]).unwrap().with_base_url(url).unwrap();
+    let usdc = swap_provider::test_token("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "USDC", 6);
+    let eth = swap_provider::test_token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "ETH", 18);
+    let options = SwapOptions::default();
+
+    assert!(odos_aggregator.supports_network());
+    let quote = odos_aggregator.quote(Address::ZERO, &[(usdc, U256::from(1_000_000))], &eth, &options).await.unwrap();
+    assert_eq!(quote.provider, "odos");
+    assert_eq!(quote.out_amount, U256::from(330_000_000_000_000u64));
+    assert_eq!(quote.gas, 180_000);
+    assert_eq!(quote.spender, router.parse::<Address>().unwrap());
+    assert_eq!(quote.metrics.block_number, Some(100));
+    let tx = odos_aggregator.build_swap(Address::ZERO, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, quote.spender);
+    assert_eq!(tx.gas, Some(200_000));
+    assert_eq!(tx.data.to_string(), "0x83bd37f9");
+}
@@ -0,0 +1,144 @@
+use alloy::primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{self, QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
+use crate::helpers::web3_client::Network;
+
+static SUPPORTED_CHAIN_IDS: [u32; 11] = [1, 10, 56, 100, 137, 250, 324, 8453, 42161, 43114, 59144];
+
+pub struct OneInchAggregator {
+    network: Network,
+    api_key: String,
+    base_url: Url,
+}
+
+impl OneInchAggregator {
+    pub fn new(network: Network, api_key: String) -> Result<Self> {
+        Ok(OneInchAggregator {
+            network,
+            api_key,
+            base_url: Url::parse("https://api.1inch.dev/swap/v6.0/")?,
+        })
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        Ok(self.base_url.join(&format!("{}/{}", self.network.id, path))?)
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, String)], context: &str) -> Result<Value> {
+        let request = reqwest::Client::new()
+            .get(self.url(path)?)
+            .bearer_auth(&self.api_key)
+            .query(query);
+        swap_provider::send_json(request, context).await
+    }
+
+    fn swap_query(token_in: &TokenData, amount_in: U256, token_out: &TokenData, options: &SwapOptions) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("src", swap_provider::api_address(&token_in.address).to_string()),
+            ("dst", swap_provider::api_address(&token_out.address).to_string()),
+            ("amount", amount_in.to_string()),
+        ];
+        // 1inch can only be limited to a set of protocols
+        if !options.source_whitelist.is_empty() {
+            query.push(("protocols", options.source_whitelist.join(",")));
+        }
+        query
+    }
+}
+
+#[async_trait]
+impl SwapProvider for OneInchAggregator {
+    fn name(&self) -> &'static str {
+        "1inch"
+    }
+
+    fn supports_network(&self) -> bool {
+        SUPPORTED_CHAIN_IDS.contains(&self.network.id)
+    }
+
+    async fn quote(&self, _sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), tokens_in)?;
+        let mut query = Self::swap_query(token_in, *amount_in, token_out, options);
+        query.push(("includeGas", "true".to_owned()));
+        let json = self.get("quote", &query, "OneInchAggregator:quote").await?;
+        let spender = self.get("approve/spender", &[], "OneInchAggregator:quote").await?;
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: swap_provider::parse_amount(&json["dstAmount"])?,
+            gas: json["gas"].as_u64().unwrap_or_default(),
+            spender: match spender["address"].as_str() {
+                Some(a) => a.parse()?,
+                None => return Err(eyre::eyre!("OneInchAggregator:quote Could not get approval target")),
+            },
+            in_values: vec![],
+            metrics: QuoteMetrics::default(),
+            route: Value::Null,
+        })
+    }
+
+    async fn build_swap(&self, sender: Address, quote: &SwapQuote, options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), &quote.tokens_in)?;
+        let mut query = Self::swap_query(token_in, *amount_in, &quote.token_out, options);
+        query.extend([
+            ("from", sender.to_string()),
+            ("origin", sender.to_string()),
+            ("slippage", options.slippage_percent.to_string()),
+            // The allowance may only be simulated in a dry run
+            ("disableEstimate", "true".to_owned()),
+        ]);
+        let json = self.get("swap", &query, "OneInchAggregator:build_swap").await?;
+        let tx = &json["tx"];
+        Ok(SwapTx {
+            to: match tx["to"].as_str() {
+                Some(to) => to.parse()?,
+                None => return Err(eyre::eyre!("OneInchAggregator:build_swap Swap has no transaction")),
+            },
+            data: tx["data"].as_str().unwrap_or_default().parse::<Bytes>()?,
+            value: swap_provider::parse_amount(&tx["value"])?,
+            gas: tx["gas"].as_u64().filter(|g| *g > 0).map(u128::from),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_oneinch_provider() {
+    let router = "0x111111125421cA6dc452d289314280a0f8842A65";
+    let url = swap_provider::mock_server(vec![
+        ("/100/quote", serde_json::json!({ "dstAmount": "2500000000000000000", "gas": 210000 })),
+        ("/100/approve/spender", serde_json::json!({ "address": router })),
+        ("/100/swap", serde_json::json!({
+            "dstAmount": "2500000000000000000",
+            "tx": { "from": "0x0000000000000000000000000000000000000001", "to": router, "data": "0x07ed2379", "value": "0", "gas": 0, "gasPrice": "1000000000" },
+        })),
+    ]).await;
+    let gnosis = swap_provider::test_network(100, "Gnosis");
+    let aggregator = OneInchAggregator::new(gnosis, "key".to_owned()).unwrap().with_base_url(url);
+    assert!(aggregator.supports_network());
+    assert!(!OneInchAggregator::new(swap_provider::test_network(42220, "Celo"), String::new()).unwrap().supports_network());
+
+    let usdc = swap_provider::test_token("0xDDAfbb505ad214D7b80b1f830fcCc89B60fb7A83", "USDC", 6);
+    let xdai = swap_provider::test_token("0x0000000000000000000000000000000000000000", "XDAI", 18);
+    let options = SwapOptions::default();
+    let quote = aggregator.quote(Address::ZERO, &[(usdc.clone(), U256::from(2_500_000))], &xdai, &options).await.unwrap();
+    assert_eq!(quote.out_amount, U256::from(2_500_000_000_000_000_000u64));
+    assert_eq!(quote.gas, 210_000);
+    assert_eq!(quote.spender, router.parse::<Address>().unwrap());
+    let tx = aggregator.build_swap(Address::ZERO, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, quote.spender);
+    assert_eq!(tx.gas, None);
+
+    let batch = [(usdc.clone(), U256::from(1)), (usdc, U256::from(1))];
+    assert!(aggregator.quote(Address::ZERO, &batch, &xdai, &options).await.is_err());
+}
@@ -0,0 +1,158 @@
+use alloy::primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{self, QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
+use crate::helpers::web3_client::Network;
+
+static SUPPORTED_CHAIN_IDS: [u32; 10] = [1, 10, 56, 100, 137, 250, 1101, 8453, 42161, 43114];
+
+pub struct ParaSwapAggregator {
+    network: Network,
+    base_url: Url,
+}
+
+impl ParaSwapAggregator {
+    pub fn new(network: Network) -> Result<Self> {
+        Ok(ParaSwapAggregator {
+            network,
+            base_url: Url::parse("https://api.paraswap.io/")?,
+        })
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    // USD values ParaSwap reports with its price route
+    fn metrics(price_route: &Value) -> QuoteMetrics {
+        let usd = |key: &str| price_route[key].as_str().and_then(|v| v.parse::<f64>().ok());
+        let (in_value, out_value) = (usd("srcUSD"), usd("destUSD"));
+        QuoteMetrics {
+            price_impact: None,
+            value_loss_percent: match (in_value, out_value) {
+                (Some(i), Some(o)) if i > 0.0 => Some((i - o) / i * 100.0),
+                _ => None,
+            },
+            out_value,
+            gas_value: usd("gasCostUSD"),
+            net_out_value: None,
+            block_number: price_route["blockNumber"].as_u64(),
+        }
+    }
+}
+
+#[async_trait]
+impl SwapProvider for ParaSwapAggregator {
+    fn name(&self) -> &'static str {
+        "paraswap"
+    }
+
+    fn supports_network(&self) -> bool {
+        SUPPORTED_CHAIN_IDS.contains(&self.network.id)
+    }
+
+    // The price route is kept as the route, ParaSwap builds the transaction from it
+    async fn quote(&self, sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), tokens_in)?;
+        let mut query = vec![
+            ("srcToken", swap_provider::api_address(&token_in.address).to_string()),
+            ("srcDecimals", token_in.decimals.to_string()),
+            ("destToken", swap_provider::api_address(&token_out.address).to_string()),
+            ("destDecimals", token_out.decimals.to_string()),
+            ("amount", amount_in.to_string()),
+            ("side", "SELL".to_owned()),
+            ("network", self.network.id.to_string()),
+            ("userAddress", sender.to_string()),
+            ("version", "6.2".to_owned()),
+        ];
+        if !options.source_blacklist.is_empty() {
+            query.push(("excludeDEXS", options.source_blacklist.join(",")));
+        }
+        if !options.source_whitelist.is_empty() {
+            query.push(("includeDEXS", options.source_whitelist.join(",")));
+        }
+        let request = reqwest::Client::new().get(self.base_url.join("prices")?).query(&query);
+        let json = swap_provider::send_json(request, "ParaSwapAggregator:quote").await?;
+        let price_route = &json["priceRoute"];
+
+        let in_values = price_route["srcUSD"].as_str().and_then(|v| v.parse::<f64>().ok()).into_iter().collect();
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: swap_provider::parse_amount(&price_route["destAmount"])?,
+            gas: swap_provider::parse_amount(&price_route["gasCost"]).map(|g| g.to::<u64>()).unwrap_or_default(),
+            spender: match price_route["tokenTransferProxy"].as_str() {
+                Some(s) => s.parse()?,
+                None => return Err(eyre::eyre!("ParaSwapAggregator:quote Could not get approval target")),
+            },
+            in_values,
+            metrics: Self::metrics(price_route),
+            route: price_route.clone(),
+        })
+    }
+
+    async fn build_swap(&self, sender: Address, quote: &SwapQuote, options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), &quote.tokens_in)?;
+        let body = serde_json::json!({
+            "srcToken": swap_provider::api_address(&token_in.address),
+            "srcDecimals": token_in.decimals,
+            "destToken": swap_provider::api_address(&quote.token_out.address),
+            "destDecimals": quote.token_out.decimals,
+            "srcAmount": amount_in.to_string(),
+            "slippage": options.slippage_bps(),
+            "priceRoute": quote.route,
+            "userAddress": sender,
+        });
+        let url = self.base_url.join(&format!("transactions/{}", self.network.id))?;
+        // Balance and allowance checks fail while the approval is only simulated
+        let request = reqwest::Client::new().post(url).query(&[("ignoreChecks", "true")]).json(&body);
+        let tx = swap_provider::send_json(request, "ParaSwapAggregator:build_swap").await?;
+        Ok(SwapTx {
+            to: match tx["to"].as_str() {
+                Some(to) => to.parse()?,
+                None => return Err(eyre::eyre!("ParaSwapAggregator:build_swap Response has no transaction")),
+            },
+            data: tx["data"].as_str().unwrap_or_default().parse::<Bytes>()?,
+            value: swap_provider::parse_amount(&tx["value"])?,
+            gas: swap_provider::parse_amount(&tx["gas"]).ok().map(|g| g.to::<u128>()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_paraswap_provider() {
+    let augustus = "0x6A000F20005980200259B80c5102003040001068";
+    let url = swap_provider::mock_server(vec![
+        ("/prices", serde_json::json!({
+            "priceRoute": {
+                "blockNumber": 35000000, "srcAmount": "3000000", "destAmount": "2990000000000000000",
+                "gasCost": "187000", "gasCostUSD": "0.0004", "srcUSD": "3.0000", "destUSD": "2.9900",
+                "tokenTransferProxy": augustus, "contractAddress": augustus,
+            },
+        })),
+        ("/transactions/100", serde_json::json!({ "from": "0x0000000000000000000000000000000000000001", "to": augustus, "value": "0", "data": "0xe3ead59e", "chainId": 100 })),
+    ]).await;
+    let gnosis = swap_provider::test_network(100, "Gnosis");
+    let aggregator = ParaSwapAggregator::new(gnosis).unwrap().with_base_url(url);
+    assert!(aggregator.supports_network());
+
+    let usdc = swap_provider::test_token("0xDDAfbb505ad214D7b80b1f830fcCc89B60fb7A83", "USDC", 6);
+    let xdai = swap_provider::test_token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "XDAI", 18);
+    let options = SwapOptions::default();
+    let quote = aggregator.quote(Address::ZERO, &[(usdc, U256::from(3_000_000))], &xdai, &options).await.unwrap();
+    assert_eq!(quote.out_amount, U256::from(2_990_000_000_000_000_000u64));
+    assert_eq!(quote.gas, 187_000);
+    assert_eq!(quote.metrics.gas_value, Some(0.0004));
+    assert_eq!(quote.metrics.block_number, Some(35_000_000));
+    assert!((quote.metrics.value_loss_percent.unwrap() - 0.3333).abs() < 0.001);
+    assert_eq!(options.guards.check(&quote.metrics, 35_000_010), Ok(()));
+    let tx = aggregator.build_swap(Address::ZERO, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, quote.spender);
+    assert_eq!(tx.gas, None);
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, fs, io::Write, path::Path};
+
+use alloy::primitives::Address;
+use eyre::Result;
+use log::warn;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::to_string_pretty;
+
+use crate::helpers::price_oracle::PriceConfidence;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedPrice {
+    pub price: Decimal,
+    pub confidence: PriceConfidence,
+    pub source: String,
+    // Unix seconds the price was fetched at, or the historical time it is valid for
+    pub timestamp: u64,
+}
+
+// Prices keyed by "<chain>:<token>" for current prices and "<chain>:<token>:<timestamp>" for historical ones
+#[derive(Default, Serialize, Deserialize)]
+pub struct PriceCache {
+    #[serde(skip)]
+    path: String,
+    #[serde(skip)]
+    max_age: u64,
+    current: HashMap<String, CachedPrice>,
+    historical: HashMap<String, CachedPrice>,
+}
+
+impl PriceCache {
+    // Load the cache file, starting empty if it is missing or unreadable
+    pub fn load(path: &str, max_age: u64) -> Self {
+        let mut cache = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str::<PriceCache>(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable price cache {}: {:?}", path, e);
+                PriceCache::default()
+            }),
+            Err(_) => PriceCache::default(),
+        };
+        cache.path = path.to_owned();
+        cache.max_age = max_age;
+        cache
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = Path::new(&self.path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::File::create(&self.path)?;
+        file.write_all(to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn key(chain_name: &str, token: &Address) -> String {
+        format!("{}:{}", chain_name, token.to_string().to_lowercase())
+    }
+
+    fn historical_key(chain_name: &str, token: &Address, timestamp: u64) -> String {
+        format!("{}:{}", Self::key(chain_name, token), timestamp)
+    }
+
+    // Returns the cached price only while it is younger than max_age
+    pub fn get(&self, chain_name: &str, token: &Address, now: u64) -> Option<&CachedPrice> {
+        self.current
+            .get(&Self::key(chain_name, token))
+            .filter(|p| now.saturating_sub(p.timestamp) <= self.max_age)
+    }
+
+    pub fn insert(&mut self, chain_name: &str, token: &Address, price: CachedPrice) {
+        self.current.insert(Self::key(chain_name, token), price);
+    }
+
+    // Historical prices never go stale
+    pub fn get_historical(&self, chain_name: &str, token: &Address, timestamp: u64) -> Option<&CachedPrice> {
+        self.historical.get(&Self::historical_key(chain_name, token, timestamp))
+    }
+
+    pub fn insert_historical(&mut self, chain_name: &str, token: &Address, price: CachedPrice) {
+        self.historical.insert(Self::historical_key(chain_name, token, price.timestamp), price);
+    }
+
+    // Drop stale current prices so the file does not grow forever
+    pub fn prune(&mut self, now: u64) {
+        let max_age = self.max_age;
+        self.current.retain(|_, p| now.saturating_sub(p.timestamp) <= max_age);
+    }
+}
+
+#[test]
+fn test_price_cache_staleness() {
+    let token: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let mut cache = PriceCache::load("target/test_price_cache_missing.json", 60);
+    cache.insert("Ethereum", &token, CachedPrice {
+        price: Decimal::ONE,
+        confidence: PriceConfidence::High,
+        source: "defillama".to_owned(),
+        timestamp: 1_000,
+    });
+
+    assert_eq!(cache.get("Ethereum", &token, 1_060).map(|p| p.price), Some(Decimal::ONE));
+    assert!(cache.get("Ethereum", &token, 1_061).is_none());
+    assert!(cache.get("Base", &token, 1_000).is_none());
+
+    cache.prune(2_000);
+    assert!(cache.current.is_empty());
+}
+
+#[test]
+fn test_price_cache_roundtrip() {
+    let path = "target/test_price_cache.json";
+    let token: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let mut cache = PriceCache::load(path, 60);
+    cache.insert_historical("Ethereum", &token, CachedPrice {
+        price: Decimal::new(98, 2),
+        confidence: PriceConfidence::Medium,
+        source: "defillama".to_owned(),
+        timestamp: 1_700_000_000,
+    });
+    cache.save().unwrap();
+
+    let cache = PriceCache::load(path, 60);
+    let price = cache.get_historical("Ethereum", &token, 1_700_000_000).unwrap();
+    assert_eq!(price.price, Decimal::new(98, 2));
+    assert_eq!(price.confidence, PriceConfidence::Medium);
+    let _ = fs::remove_file(path);
+}
@@ -0,0 +1,680 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use alloy::primitives::{Address, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use reqwest::Url;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::const_types::{self, ChainName, Env, NATIVE_TOKEN_ADDRESS};
+use crate::helpers::dex_quoter::{DexConfig, DexQuote, DexQuoter, QuoteRequest};
+use crate::helpers::price_cache::{CachedPrice, PriceCache};
+use crate::helpers::utils::{f64_to_decimal, u256_to_decimal};
+use crate::helpers::web3_client::{Balance, Network};
+
+// A single token that needs a price
+#[derive(Clone, Debug)]
+pub struct PriceQuery {
+    pub address: Address,
+    pub decimals: u8,
+    // Held amount, lets on-chain sources check that the price holds for the whole balance
+    pub amount: U256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceConfidence {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TokenPrice {
+    pub price: Decimal,
+    pub confidence: PriceConfidence,
+}
+
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Returns USD prices for the tokens it knows about. Unknown tokens are simply left out.
+    async fn fetch_prices(&self, network: &Network, tokens: &[PriceQuery]) -> Result<HashMap<Address, TokenPrice>>;
+
+    // Prices at a past unix timestamp. Sources without history know no tokens.
+    async fn fetch_historical_prices(&self, _network: &Network, _tokens: &[PriceQuery], _timestamp: u64) -> Result<HashMap<Address, TokenPrice>> {
+        Ok(HashMap::new())
+    }
+}
+
+pub fn timestamp_from_date(date: &str) -> Result<u64> {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+    match date.and_hms_opt(0, 0, 0) {
+        Some(d) => Ok(d.and_utc().timestamp() as u64),
+        None => Err(eyre::eyre!("Invalid date {}", date)),
+    }
+}
+
+// Keep request URLs well below common server and proxy limits
+static DEFILLAMA_MAX_COINS_PER_REQUEST: usize = 80;
+static DEFILLAMA_MAX_URL_LENGTH: usize = 4000;
+static DEFILLAMA_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+pub struct DefiLlamaPriceSource {
+    base_url: Url,
+    max_coins_per_request: usize,
+    max_url_length: usize,
+    max_concurrent_requests: usize,
+}
+
+impl DefiLlamaPriceSource {
+    pub fn new() -> Result<Self> {
+        Ok(DefiLlamaPriceSource {
+            base_url: Url::parse("https://coins.llama.fi")?,
+            max_coins_per_request: DEFILLAMA_MAX_COINS_PER_REQUEST,
+            max_url_length: DEFILLAMA_MAX_URL_LENGTH,
+            max_concurrent_requests: DEFILLAMA_MAX_CONCURRENT_REQUESTS,
+        })
+    }
+
+    // Split coins into groups that respect both the coin count and the URL length limit
+    fn chunk_coins(coins: &[String], prefix_length: usize, max_count: usize, max_length: usize) -> Vec<Vec<String>> {
+        let mut chunks: Vec<Vec<String>> = vec![];
+        let mut current: Vec<String> = vec![];
+        let mut current_length = prefix_length;
+        for coin in coins {
+            // +1 for the separating comma
+            let coin_length = coin.len() + 1;
+            if !current.is_empty() && (current.len() >= max_count || current_length + coin_length > max_length) {
+                chunks.push(std::mem::take(&mut current));
+                current_length = prefix_length;
+            }
+            current_length += coin_length;
+            current.push(coin.clone());
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+
+    async fn fetch_chunk(&self, path: &str, coins: &[String]) -> Result<HashMap<Address, TokenPrice>> {
+        let url = self.base_url.join(&format!("{}{}", path, coins.join(",")))?;
+        let res = reqwest::get(url).await?;
+        if res.status() != 200 {
+            return Err(eyre::eyre!("DefiLlamaPriceSource:fetch_chunk Request failed with status {}", res.status()));
+        }
+        let json: Value = res.json().await?;
+        Self::parse_prices(&json)
+    }
+
+    async fn fetch_chunked(&self, network: &Network, tokens: &[PriceQuery], path: &str) -> Result<HashMap<Address, TokenPrice>> {
+        let chain = Self::chain_key(&network.chain_name);
+        let coins: Vec<String> = tokens.iter().map(|t| format!("{}:{}", chain, t.address)).collect();
+
+        let prefix_length = self.base_url.join(path)?.as_str().len();
+        let chunks = Self::chunk_coins(&coins, prefix_length, self.max_coins_per_request, self.max_url_length);
+        let chunk_count = chunks.len();
+
+        let results: Vec<Result<HashMap<Address, TokenPrice>>> = stream::iter(chunks)
+            .map(|chunk| async move { self.fetch_chunk(path, &chunk).await })
+            .buffer_unordered(self.max_concurrent_requests)
+            .collect()
+            .await;
+
+        // A failed chunk only loses its own tokens, the rest can still be priced
+        let mut prices = HashMap::new();
+        let mut failed = 0;
+        for result in results {
+            match result {
+                Ok(p) => prices.extend(p),
+                Err(e) => {
+                    failed += 1;
+                    warn!("DefiLlama price chunk failed for {}: {:?}", network.chain_name, e);
+                }
+            }
+        }
+        if failed == chunk_count {
+            return Err(eyre::eyre!("DefiLlamaPriceSource:fetch_chunked All {} requests failed", chunk_count));
+        }
+        Ok(prices)
+    }
+
+    pub fn chain_key(chain_name: &str) -> String {
+        match chain_name {
+            "Zksync" => "era".to_owned(),
+            "Nova" => "arbitrum_nova".to_owned(),
+            v => v.to_owned(),
+        }
+    }
+
+    fn parse_prices(json: &Value) -> Result<HashMap<Address, TokenPrice>> {
+        let coins = match json["coins"].as_object() {
+            Some(c) => c,
+            None => return Err(eyre::eyre!("DefiLlamaPriceSource:parse_prices Coins data is null")),
+        };
+
+        let mut prices = HashMap::new();
+        for (k, v) in coins {
+            let token_address = match k.split(':').nth(1).and_then(|a| a.parse::<Address>().ok()) {
+                Some(a) => a,
+                None => continue,
+            };
+            if let Some(price) = v["price"].as_f64().and_then(f64_to_decimal) {
+                // DefiLlama reports its own 0-1 confidence score
+                let confidence = match v["confidence"].as_f64() {
+                    Some(c) if c < 0.5 => PriceConfidence::Low,
+                    Some(c) if c < 0.9 => PriceConfidence::Medium,
+                    _ => PriceConfidence::High,
+                };
+                prices.insert(token_address, TokenPrice { price, confidence });
+            }
+        }
+        Ok(prices)
+    }
+}
+
+#[async_trait]
+impl PriceSource for DefiLlamaPriceSource {
+    fn name(&self) -> &'static str {
+        "defillama"
+    }
+
+    async fn fetch_prices(&self, network: &Network, tokens: &[PriceQuery]) -> Result<HashMap<Address, TokenPrice>> {
+        self.fetch_chunked(network, tokens, "prices/current/").await
+    }
+
+    async fn fetch_historical_prices(&self, network: &Network, tokens: &[PriceQuery], timestamp: u64) -> Result<HashMap<Address, TokenPrice>> {
+        self.fetch_chunked(network, tokens, &format!("prices/historical/{}/", timestamp)).await
+    }
+}
+
+pub struct CoinGeckoPriceSource {
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl CoinGeckoPriceSource {
+    pub fn new(api_key: Option<String>) -> Result<Self> {
+        Ok(CoinGeckoPriceSource {
+            base_url: Url::parse("https://api.coingecko.com/api/v3/")?,
+            api_key: api_key.filter(|k| !k.is_empty()),
+        })
+    }
+
+    async fn get_json(&self, url: Url) -> Result<Value> {
+        let client = reqwest::Client::new();
+        let mut req = client.get(url).header("Accept", "application/json");
+        if let Some(key) = &self.api_key {
+            req = req.header("x-cg-demo-api-key", key);
+        }
+        let res = req.send().await?;
+        if res.status() != 200 {
+            return Err(eyre::eyre!("CoinGeckoPriceSource:get_json Request failed with status {}", res.status()));
+        }
+        Ok(res.json().await?)
+    }
+
+    fn parse_token_prices(json: &Value) -> HashMap<Address, TokenPrice> {
+        let mut prices = HashMap::new();
+        if let Some(tokens) = json.as_object() {
+            for (k, v) in tokens {
+                if let (Ok(address), Some(price)) = (k.parse::<Address>(), v["usd"].as_f64().and_then(f64_to_decimal)) {
+                    prices.insert(address, TokenPrice { price, confidence: PriceConfidence::High });
+                }
+            }
+        }
+        prices
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinGeckoPriceSource {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    async fn fetch_prices(&self, network: &Network, tokens: &[PriceQuery]) -> Result<HashMap<Address, TokenPrice>> {
+        let native = NATIVE_TOKEN_ADDRESS.parse::<Address>()?;
+        let chain_name = ChainName::from(network.chain_name.as_str());
+        let mut prices = HashMap::new();
+
+        if tokens.iter().any(|t| t.address == native) {
+            let native_id = const_types::convert_network_name_to_coingecko_native_id(ChainName::from(network.chain_name.as_str()));
+            let mut url = self.base_url.join("simple/price")?;
+            url.query_pairs_mut()
+                .append_pair("ids", &native_id)
+                .append_pair("vs_currencies", "usd");
+            let json = self.get_json(url).await?;
+            if let Some(price) = json[&native_id]["usd"].as_f64().and_then(f64_to_decimal) {
+                prices.insert(native, TokenPrice { price, confidence: PriceConfidence::High });
+            }
+        }
+
+        let contracts: Vec<String> = tokens.iter()
+            .filter(|t| t.address != native)
+            .map(|t| t.address.to_string().to_lowercase())
+            .collect();
+        if contracts.is_empty() {
+            return Ok(prices);
+        }
+
+        let platform = const_types::convert_network_name_to_coingecko_query_string(chain_name);
+        let mut url = self.base_url.join(&format!("simple/token_price/{}", platform))?;
+        url.query_pairs_mut()
+            .append_pair("contract_addresses", &contracts.join(","))
+            .append_pair("vs_currencies", "usd");
+        let json = self.get_json(url).await?;
+        prices.extend(Self::parse_token_prices(&json));
+        Ok(prices)
+    }
+}
+
+pub struct DexQuotePriceSource {
+    chains: HashMap<String, DexConfig>,
+}
+
+impl DexQuotePriceSource {
+    pub fn new(chain_data: &Value) -> Self {
+        let mut chains = HashMap::new();
+        if let Some(chain_data) = chain_data.as_object() {
+            for (k, v) in chain_data {
+                if let Some(config) = DexConfig::from_chain_data(v) {
+                    chains.insert(k.clone(), config);
+                }
+            }
+        }
+        DexQuotePriceSource { chains }
+    }
+
+    fn to_decimal(amount: U256, decimals: u8) -> Decimal {
+        u256_to_decimal(amount, decimals).unwrap_or(Decimal::ZERO)
+    }
+
+    // USD value of a quote that ended in either the stablecoin or the wrapped native token
+    fn quote_value(config: &DexConfig, quote: &Option<DexQuote>, native_price: Decimal, to_stable: bool) -> Decimal {
+        match quote {
+            Some(q) if to_stable => Self::to_decimal(q.amount_out, config.stablecoin_decimals),
+            Some(q) => Self::to_decimal(q.amount_out, config.wrapped_native_decimals)
+                .checked_mul(native_price)
+                .unwrap_or(Decimal::ZERO),
+            None => Decimal::ZERO,
+        }
+    }
+
+    // Price impact of selling the whole balance compared to selling one unit
+    pub fn confidence_from_impact(impact: Decimal) -> PriceConfidence {
+        if impact < Decimal::new(2, 2) {
+            PriceConfidence::High
+        } else if impact < Decimal::new(1, 1) {
+            PriceConfidence::Medium
+        } else {
+            PriceConfidence::Low
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for DexQuotePriceSource {
+    fn name(&self) -> &'static str {
+        "dex"
+    }
+
+    async fn fetch_prices(&self, network: &Network, tokens: &[PriceQuery]) -> Result<HashMap<Address, TokenPrice>> {
+        let config = match self.chains.get(&network.chain_name) {
+            Some(c) => c.clone(),
+            None => return Err(eyre::eyre!("DexQuotePriceSource:fetch_prices No DEX configured for {}", network.chain_name)),
+        };
+        let stablecoin = match config.stablecoin {
+            Some(s) => s,
+            None => return Err(eyre::eyre!("DexQuotePriceSource:fetch_prices No stablecoin configured for {}", network.chain_name)),
+        };
+        let quoter = DexQuoter::new(network.clone(), config.clone())?;
+        let unit = |decimals: u8| U256::from(10).pow(U256::from(decimals));
+
+        let mut prices = HashMap::new();
+        let priced: Vec<&PriceQuery> = tokens.iter().filter(|t| {
+            if t.address == stablecoin {
+                prices.insert(t.address, TokenPrice { price: Decimal::ONE, confidence: PriceConfidence::Medium });
+                false
+            } else {
+                true
+            }
+        }).collect();
+
+        // One unit of WNATIVE first, then every token against both the stablecoin and WNATIVE
+        let mut requests = vec![QuoteRequest {
+            token_in: config.wrapped_native,
+            token_out: stablecoin,
+            amount_in: unit(config.wrapped_native_decimals),
+        }];
+        for token in priced.iter() {
+            for token_out in [stablecoin, config.wrapped_native] {
+                requests.push(QuoteRequest { token_in: token.address, token_out, amount_in: unit(token.decimals) });
+            }
+        }
+        let quotes = quoter.best_quotes(&requests).await?;
+        let native_price = Self::quote_value(&config, &quotes[0], Decimal::ZERO, true);
+
+        let mut unit_prices: Vec<(usize, Decimal, bool)> = vec![];
+        for (i, token) in priced.iter().enumerate() {
+            let via_stable = Self::quote_value(&config, &quotes[1 + i * 2], native_price, true);
+            let via_native = if quoter.routes(token.address, config.wrapped_native).is_empty() {
+                native_price
+            } else {
+                Self::quote_value(&config, &quotes[2 + i * 2], native_price, false)
+            };
+            let price = via_stable.max(via_native);
+            if price > Decimal::ZERO {
+                unit_prices.push((i, price, via_stable >= via_native));
+            }
+        }
+
+        // Quote the full balance over the winning leg to see how much the price holds up
+        let depth_requests: Vec<QuoteRequest> = unit_prices.iter()
+            .filter(|(i, _, _)| priced[*i].amount > unit(priced[*i].decimals))
+            .map(|(i, _, to_stable)| QuoteRequest {
+                token_in: priced[*i].address,
+                token_out: if *to_stable { stablecoin } else { config.wrapped_native },
+                amount_in: priced[*i].amount,
+            })
+            .collect();
+        let depth_quotes = quoter.best_quotes(&depth_requests).await?;
+
+        let mut depth_index = 0;
+        for (i, price, to_stable) in unit_prices {
+            let token = priced[i];
+            let mut confidence = PriceConfidence::High;
+            if token.amount > unit(token.decimals) {
+                let expected = Self::to_decimal(token.amount, token.decimals).checked_mul(price);
+                let realised = Self::quote_value(&config, &depth_quotes[depth_index], native_price, to_stable);
+                depth_index += 1;
+                confidence = match expected.and_then(|e| realised.checked_div(e)) {
+                    Some(ratio) => Self::confidence_from_impact(Decimal::ONE - ratio),
+                    None => PriceConfidence::Low,
+                };
+            }
+            prices.insert(token.address, TokenPrice { price, confidence });
+        }
+        Ok(prices)
+    }
+}
+
+pub struct PriceOracle {
+    sources: Vec<Box<dyn PriceSource>>,
+    cache: Option<Mutex<PriceCache>>,
+}
+
+impl PriceOracle {
+    pub fn new(sources: Vec<Box<dyn PriceSource>>) -> Self {
+        PriceOracle { sources, cache: None }
+    }
+
+    pub fn with_cache(mut self, cache: PriceCache) -> Self {
+        self.cache = Some(Mutex::new(cache));
+        self
+    }
+
+    // Build the oracle from the PRICE_SOURCES priority list
+    pub fn from_env(env: &Env, chain_data: &Value) -> Result<Self> {
+        let mut sources: Vec<Box<dyn PriceSource>> = vec![];
+        for source in env.price_sources.iter() {
+            match source.as_str() {
+                "defillama" => sources.push(Box::new(DefiLlamaPriceSource::new()?)),
+                "coingecko" => sources.push(Box::new(CoinGeckoPriceSource::new(Some(env.coingecko_api_key.clone()))?)),
+                "dex" => sources.push(Box::new(DexQuotePriceSource::new(chain_data))),
+                s => warn!("Unknown price source {}, skipping", s),
+            }
+        }
+        let oracle = Self::new(sources);
+        if env.price_cache_max_age == 0 {
+            return Ok(oracle);
+        }
+        Ok(oracle.with_cache(PriceCache::load("data/price_cache.json", env.price_cache_max_age)))
+    }
+
+    fn now() -> u64 {
+        chrono::Utc::now().timestamp() as u64
+    }
+
+    // Fill in current token prices from the cache, then ask each source in order for the tokens that are still unpriced
+    pub async fn set_token_prices(&self, network: &Network, balances: &mut [Balance]) -> Result<()> {
+        let now = Self::now();
+        if let Some(cache) = &self.cache {
+            let cache = cache.lock().unwrap();
+            for balance in balances.iter_mut().filter(|b| b.price_source.is_none()) {
+                if let Some(cached) = cache.get(&network.chain_name, &balance.token_address, now) {
+                    balance.set_token_price(cached.price);
+                    balance.set_price_source(&cached.source, cached.confidence);
+                }
+            }
+        }
+
+        let fetched = self.query_sources(network, balances, None).await;
+        self.update_cache(network, fetched, now, false);
+        Ok(())
+    }
+
+    // Same as set_token_prices but values balances at a past unix timestamp
+    pub async fn set_historical_token_prices(&self, network: &Network, balances: &mut [Balance], timestamp: u64) -> Result<()> {
+        if let Some(cache) = &self.cache {
+            let cache = cache.lock().unwrap();
+            for balance in balances.iter_mut().filter(|b| b.price_source.is_none()) {
+                if let Some(cached) = cache.get_historical(&network.chain_name, &balance.token_address, timestamp) {
+                    balance.set_token_price(cached.price);
+                    balance.set_price_source(&cached.source, cached.confidence);
+                }
+            }
+        }
+
+        let fetched = self.query_sources(network, balances, Some(timestamp)).await;
+        self.update_cache(network, fetched, timestamp, true);
+        Ok(())
+    }
+
+    // Returns what was newly priced, per token, so it can be cached
+    async fn query_sources(&self, network: &Network, balances: &mut [Balance], timestamp: Option<u64>) -> Vec<(Address, TokenPrice, &'static str)> {
+        let mut fetched = vec![];
+        for source in self.sources.iter() {
+            let missing: Vec<PriceQuery> = balances.iter()
+                .filter(|b| b.price_source.is_none())
+                .map(|b| PriceQuery { address: b.token_address, decimals: b.decimals, amount: b.balance })
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+
+            let result = match timestamp {
+                Some(t) => source.fetch_historical_prices(network, &missing, t).await,
+                None => source.fetch_prices(network, &missing).await,
+            };
+            let prices = match result {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Price source {} failed for {}: {:?}", source.name(), network.chain_name, e);
+                    continue;
+                }
+            };
+            for balance in balances.iter_mut().filter(|b| b.price_source.is_none()) {
+                if let Some(price) = prices.get(&balance.token_address) {
+                    balance.set_token_price(price.price);
+                    balance.set_price_source(source.name(), price.confidence);
+                    fetched.push((balance.token_address, *price, source.name()));
+                }
+            }
+        }
+
+        let unpriced = balances.iter().filter(|b| b.price_source.is_none()).count();
+        if unpriced > 0 {
+            info!("{} tokens on {} have no price from any source", unpriced, network.chain_name);
+        }
+        fetched
+    }
+
+    fn update_cache(&self, network: &Network, fetched: Vec<(Address, TokenPrice, &'static str)>, timestamp: u64, historical: bool) {
+        let cache = match &self.cache {
+            Some(c) => c,
+            None => return,
+        };
+        if fetched.is_empty() {
+            return;
+        }
+
+        let mut cache = cache.lock().unwrap();
+        for (token, price, source) in fetched {
+            let cached = CachedPrice {
+                price: price.price,
+                confidence: price.confidence,
+                source: source.to_owned(),
+                timestamp,
+            };
+            if historical {
+                cache.insert_historical(&network.chain_name, &token, cached);
+            } else {
+                cache.insert(&network.chain_name, &token, cached);
+            }
+        }
+        cache.prune(Self::now());
+        if let Err(e) = cache.save() {
+            warn!("Failed to save price cache: {:?}", e);
+        }
+    }
+}
+
+impl Default for PriceOracle {
+    fn default() -> Self {
+        PriceOracle::new(vec![Box::new(DefiLlamaPriceSource::new().unwrap())])
+    }
+}
+
+#[cfg(test)]
+struct StaticPriceSource {
+    name: &'static str,
+    prices: HashMap<Address, TokenPrice>,
+    fail: bool,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl PriceSource for StaticPriceSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn fetch_prices(&self, _network: &Network, tokens: &[PriceQuery]) -> Result<HashMap<Address, TokenPrice>> {
+        if self.fail {
+            return Err(eyre::eyre!("source is down"));
+        }
+        Ok(tokens.iter().filter_map(|t| self.prices.get(&t.address).map(|p| (t.address, *p))).collect())
+    }
+}
+
+#[test]
+fn test_parse_defillama_prices() {
+    let json: Value = serde_json::from_str(r#"{
+        "coins": {
+            "ethereum:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48": {"decimals": 6, "symbol": "USDC", "price": 0.9998, "timestamp": 1721000000, "confidence": 0.99},
+            "ethereum:0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE": {"decimals": 18, "symbol": "ETH", "price": 3400.5, "timestamp": 1721000000, "confidence": 0.99}
+        }
+    }"#).unwrap();
+    let prices = DefiLlamaPriceSource::parse_prices(&json).unwrap();
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices[&NATIVE_TOKEN_ADDRESS.parse::<Address>().unwrap()].price, Decimal::new(34005, 1));
+}
+
+#[test]
+fn test_chunk_defillama_coins() {
+    let coins: Vec<String> = (0..250).map(|i| format!("ethereum:0x{:040x}", i)).collect();
+    let chunks = DefiLlamaPriceSource::chunk_coins(&coins, 40, 100, 4000);
+    assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 250);
+    for chunk in chunks.iter() {
+        assert!(chunk.len() <= 100);
+        assert!(40 + chunk.iter().map(|c| c.len() + 1).sum::<usize>() <= 4000);
+    }
+    // 52 chars per coin with its comma, so the URL limit kicks in before the count limit
+    assert_eq!(chunks[0].len(), 76);
+}
+
+#[test]
+fn test_dex_confidence_from_impact() {
+    assert_eq!(DexQuotePriceSource::confidence_from_impact(Decimal::new(1, 3)), PriceConfidence::High);
+    assert_eq!(DexQuotePriceSource::confidence_from_impact(Decimal::new(5, 2)), PriceConfidence::Medium);
+    assert_eq!(DexQuotePriceSource::confidence_from_impact(Decimal::new(4, 1)), PriceConfidence::Low);
+}
+
+#[test]
+fn test_parse_coingecko_prices() {
+    let json: Value = serde_json::from_str(r#"{
+        "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": {"usd": 1.0},
+        "0x6b175474e89094c44da98b954eedeac495271d0f": {}
+    }"#).unwrap();
+    let prices = CoinGeckoPriceSource::parse_token_prices(&json);
+    assert_eq!(prices.len(), 1);
+    assert_eq!(prices[&"0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse::<Address>().unwrap()].price, Decimal::ONE);
+}
+
+#[tokio::test]
+async fn test_price_oracle_fallback() {
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let dai: Address = "0x6B175474E89094C44Da98b954EedeAC495271d0F".parse().unwrap();
+    let network = Network::new(
+        1,
+        "Ethereum".to_owned(),
+        vec!["https://ethereum.publicnode.com".parse::<Url>().unwrap()],
+        "https://etherscan.io/tx/".to_owned(),
+        "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    ).unwrap();
+    let price = |price: Decimal| TokenPrice { price, confidence: PriceConfidence::High };
+    let oracle = PriceOracle::new(vec![
+        Box::new(StaticPriceSource { name: "down", prices: HashMap::new(), fail: true }),
+        Box::new(StaticPriceSource { name: "first", prices: HashMap::from([(usdc, price(Decimal::ONE))]), fail: false }),
+        Box::new(StaticPriceSource { name: "second", prices: HashMap::from([(usdc, price(Decimal::TWO)), (dai, price(Decimal::new(99, 2)))]), fail: false }),
+    ]);
+    let mut balances = vec![
+        Balance::new(usdc, "USDC".to_owned(), "USDC".to_owned(), 6, U256::from(1)),
+        Balance::new(dai, "DAI".to_owned(), "DAI".to_owned(), 18, U256::from(1)),
+    ];
+    oracle.set_token_prices(&network, &mut balances).await.unwrap();
+    assert_eq!(balances[0].token_price, Decimal::ONE);
+    assert_eq!(balances[0].price_source.as_deref(), Some("first"));
+    assert_eq!(balances[1].token_price, Decimal::new(99, 2));
+    assert_eq!(balances[1].price_source.as_deref(), Some("second"));
+}
+
+#[tokio::test]
+async fn test_price_oracle_uses_cache() {
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let network = Network::new(
+        1,
+        "Ethereum".to_owned(),
+        vec!["https://ethereum.publicnode.com".parse::<Url>().unwrap()],
+        "https://etherscan.io/tx/".to_owned(),
+        "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    ).unwrap();
+    let mut cache = PriceCache::load("target/test_oracle_price_cache.json", 600);
+    cache.insert("Ethereum", &usdc, CachedPrice {
+        price: Decimal::new(999, 3),
+        confidence: PriceConfidence::High,
+        source: "defillama".to_owned(),
+        timestamp: PriceOracle::now(),
+    });
+    // The only source is down, so the price has to come from the cache
+    let oracle = PriceOracle::new(vec![
+        Box::new(StaticPriceSource { name: "down", prices: HashMap::new(), fail: true }),
+    ]).with_cache(cache);
+    let mut balances = vec![Balance::new(usdc, "USDC".to_owned(), "USDC".to_owned(), 6, U256::from(1))];
+    oracle.set_token_prices(&network, &mut balances).await.unwrap();
+    assert_eq!(balances[0].token_price, Decimal::new(999, 3));
+    assert_eq!(balances[0].price_source.as_deref(), Some("defillama"));
+}
+
+#[test]
+fn test_timestamp_from_date() {
+    assert_eq!(timestamp_from_date("2024-01-01").unwrap(), 1_704_067_200);
+    assert!(timestamp_from_date("01/01/2024").is_err());
+}
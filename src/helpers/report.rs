@@ -0,0 +1,620 @@
+use std::{collections::HashMap, fs, io::Write, path::Path};
+
+use alloy::primitives::Address;
+use eyre::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::constants::const_types::NATIVE_TOKEN_ADDRESS;
+use crate::helpers::currency::CurrencyConverter;
+use crate::helpers::utils::{paint, DEBUG_COLOR, INFO_COLOR, WARN_COLOR};
+use crate::helpers::web3_client::Balance;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    // Plain terminal output only
+    Text,
+    Csv,
+    Markdown,
+    Html,
+    Jsonl,
+}
+
+impl ReportFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "" | "text" => Ok(ReportFormat::Text),
+            "csv" => Ok(ReportFormat::Csv),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            "jsonl" => Ok(ReportFormat::Jsonl),
+            f => Err(eyre::eyre!("Unknown report format {}", f)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => "txt",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+            ReportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+// One token balance of one wallet, price and value already in the report currency
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportRow {
+    pub wallet: String,
+    pub chain: String,
+    pub token: String,
+    pub address: Address,
+    pub amount: Decimal,
+    pub price: Decimal,
+    pub value: Decimal,
+    pub currency: String,
+    pub explorer_link: String,
+}
+
+static COLUMNS: [&str; 8] = ["Wallet", "Chain", "Token", "Address", "Amount", "Price", "Value", "Explorer"];
+
+// Explorer URLs in chains.json point at transactions, e.g. https://etherscan.io/tx/
+pub fn explorer_link(explorer: &str, wallet: &str, token: &Address) -> String {
+    let base = explorer.trim_end_matches('/').trim_end_matches("/tx");
+    if base.is_empty() {
+        return String::new();
+    }
+    if *token == NATIVE_TOKEN_ADDRESS.parse::<Address>().unwrap() {
+        format!("{}/address/{}", base, wallet)
+    } else {
+        format!("{}/token/{}?a={}", base, token, wallet)
+    }
+}
+
+// Flatten a wallet scan into rows, sorted by chain then by value
+pub fn build_rows(
+    wallet: &str,
+    balances: &HashMap<String, Vec<Balance>>,
+    explorers: &HashMap<String, String>,
+    converter: &CurrencyConverter,
+) -> Vec<ReportRow> {
+    let mut rows = vec![];
+    for (chain, chain_balances) in balances.iter() {
+        let explorer = explorers.get(chain).map(|e| e.as_str()).unwrap_or_default();
+        for balance in chain_balances.iter() {
+            rows.push(ReportRow {
+                wallet: wallet.to_owned(),
+                chain: chain.clone(),
+                token: balance.token_symbol.clone(),
+                address: balance.token_address,
+                amount: balance.amount().unwrap_or_default(),
+                price: converter.convert(balance.token_price),
+                value: converter.convert(balance.value().unwrap_or_default()),
+                currency: converter.currency().code().to_owned(),
+                explorer_link: explorer_link(explorer, wallet, &balance.token_address),
+            });
+        }
+    }
+    rows.sort_by(|a, b| a.wallet.cmp(&b.wallet).then(a.chain.cmp(&b.chain)).then(b.value.cmp(&a.value)));
+    rows
+}
+
+fn fields(row: &ReportRow) -> [String; 8] {
+    [
+        row.wallet.clone(),
+        row.chain.clone(),
+        row.token.clone(),
+        row.address.to_string(),
+        row.amount.to_string(),
+        row.price.to_string(),
+        row.value.round_dp(6).to_string(),
+        row.explorer_link.clone(),
+    ]
+}
+
+fn headers(currency: &str) -> Vec<String> {
+    COLUMNS.iter().map(|c| match *c {
+        "Price" | "Value" => format!("{} ({})", c, currency),
+        c => c.to_owned(),
+    }).collect()
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn html_escape(field: &str) -> String {
+    field
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn render_csv(rows: &[ReportRow], currency: &str) -> String {
+    let mut out = headers(currency).iter().map(|h| csv_escape(h)).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for row in rows {
+        out.push_str(&fields(row).iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn render_markdown(rows: &[ReportRow], currency: &str) -> String {
+    let headers = headers(currency);
+    let mut out = format!("| {} |\n", headers.join(" | "));
+    out.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+    for row in rows {
+        let mut cells = fields(row).map(|f| f.replace('|', "\\|"));
+        if !row.explorer_link.is_empty() {
+            cells[7] = format!("[link]({})", row.explorer_link);
+        }
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+pub fn render_html(rows: &[ReportRow], currency: &str) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Garbage Collector Report</title>\n<style>\n\
+        body { font-family: sans-serif; margin: 2em; }\n\
+        table { border-collapse: collapse; }\n\
+        th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+        th { background: #f0f0f0; }\n\
+        td.num { text-align: right; font-family: monospace; }\n\
+        </style>\n</head>\n<body>\n<table>\n<tr>",
+    );
+    for header in headers(currency) {
+        out.push_str(&format!("<th>{}</th>", html_escape(&header)));
+    }
+    out.push_str("</tr>\n");
+    for row in rows {
+        let cells = fields(row);
+        out.push_str("<tr>");
+        for (i, cell) in cells.iter().enumerate().take(7) {
+            let class = if (4..=6).contains(&i) { " class=\"num\"" } else { "" };
+            out.push_str(&format!("<td{}>{}</td>", class, html_escape(cell)));
+        }
+        if row.explorer_link.is_empty() {
+            out.push_str("<td></td>");
+        } else {
+            out.push_str(&format!("<td><a href=\"{}\">link</a></td>", html_escape(&row.explorer_link)));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+pub fn render_jsonl(rows: &[ReportRow]) -> Result<String> {
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&serde_json::to_string(row)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub fn render(format: ReportFormat, rows: &[ReportRow], currency: &str) -> Result<String> {
+    match format {
+        ReportFormat::Csv => Ok(render_csv(rows, currency)),
+        ReportFormat::Markdown => Ok(render_markdown(rows, currency)),
+        ReportFormat::Html => Ok(render_html(rows, currency)),
+        ReportFormat::Jsonl => render_jsonl(rows),
+        ReportFormat::Text => Err(eyre::eyre!("Text reports are printed, not rendered")),
+    }
+}
+
+// A balance with its amount and USD value, None when they can't be computed
+type TableRow<'a> = (&'a Balance, Option<(Decimal, Decimal)>);
+
+// Terminal table of one wallet. Chains and tokens are sorted by value and balances worth less
+// than min_value USD are collapsed into one dust line per chain.
+pub fn render_terminal(
+    balances: &HashMap<String, Vec<Balance>>,
+    converter: &CurrencyConverter,
+    min_value: Decimal,
+    color: bool,
+) -> String {
+    let mut chains: Vec<(&String, Vec<TableRow>, Decimal)> = balances.iter().map(|(chain, chain_balances)| {
+        let mut rows: Vec<TableRow> = chain_balances.iter().map(|b| {
+            (b, b.amount().ok().zip(b.value().ok()))
+        }).collect();
+        rows.sort_by(|a, b| b.1.map(|v| v.1).cmp(&a.1.map(|v| v.1)).then(a.0.token_symbol.cmp(&b.0.token_symbol)));
+        let total = rows.iter().filter_map(|r| r.1.map(|v| v.1)).sum();
+        (chain, rows, total)
+    }).collect();
+    chains.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+
+    let cells = |balance: &Balance, amount: Decimal, value: Decimal| -> [String; 4] {
+        let price = converter.convert(balance.token_price).round_sf(6).unwrap_or_default().normalize();
+        [
+            balance.token_symbol.clone(),
+            amount.round_dp(8).normalize().to_string(),
+            format!("{} {}", price, converter.currency().code()),
+            converter.format(value),
+        ]
+    };
+    let mut widths = ["Token".len(), "Amount".len(), "Price".len(), "Value".len()];
+    for (_, rows, _) in chains.iter() {
+        for (balance, values) in rows.iter() {
+            if let Some((amount, value)) = values.filter(|v| v.1 >= min_value) {
+                for (width, cell) in widths.iter_mut().zip(cells(balance, amount, value)) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+        }
+    }
+    let line = |cells: [String; 4]| -> String {
+        format!(
+            "  {:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            cells[0], cells[1], cells[2], cells[3],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3],
+        )
+    };
+
+    let mut out = String::new();
+    let mut total_balance = Decimal::ZERO;
+    for (chain, rows, total) in chains.iter() {
+        total_balance += *total;
+        out.push_str(&paint(&format!("Chain: {} ({})", chain, converter.format(*total)), INFO_COLOR, color));
+        out.push('\n');
+        if rows.iter().any(|(_, values)| values.is_none_or(|v| v.1 >= min_value)) {
+            out.push_str(&line(["Token", "Amount", "Price", "Value"].map(|h| h.to_owned())));
+            out.push('\n');
+        }
+        let mut dust_count = 0;
+        let mut dust_total = Decimal::ZERO;
+        for (balance, values) in rows.iter() {
+            match values {
+                Some((_, value)) if *value < min_value => {
+                    dust_count += 1;
+                    dust_total += *value;
+                }
+                Some((amount, value)) => {
+                    out.push_str(&line(cells(balance, *amount, *value)));
+                    out.push('\n');
+                }
+                None => {
+                    let failed = format!("  {:<w$}  Failed to format balance", balance.token_symbol, w = widths[0]);
+                    out.push_str(&paint(&failed, WARN_COLOR, color));
+                    out.push('\n');
+                }
+            }
+        }
+        if dust_count > 0 {
+            let dust = format!("  {} dust tokens totaling {}", dust_count, converter.format(dust_total));
+            out.push_str(&paint(&dust, DEBUG_COLOR, color));
+            out.push('\n');
+        }
+        out.push_str("---------------------------------\n");
+    }
+    out.push_str(&paint(&format!("Total balance: {}", converter.format(total_balance)), INFO_COLOR, color));
+    out.push('\n');
+    out
+}
+
+// One line of the portfolio summary, e.g. the total of one chain across all wallets
+#[derive(Clone, Debug, Serialize)]
+pub struct SummaryLine {
+    pub section: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+    pub value: Decimal,
+    // Number of balances behind the line
+    pub count: usize,
+}
+
+// Aggregate of every scanned wallet, values in the report currency
+#[derive(Clone, Debug)]
+pub struct PortfolioSummary {
+    pub currency: String,
+    pub lines: Vec<SummaryLine>,
+}
+
+static SUMMARY_SECTIONS: [(&str, &str); 6] = [
+    ("total", "Total"),
+    ("wallet", "Per wallet"),
+    ("chain", "Per chain"),
+    ("token", "Per token"),
+    ("top", "Top holdings"),
+    ("split", "Dust vs meaningful"),
+];
+
+fn sorted_lines(section: &str, totals: HashMap<String, (Option<Decimal>, Decimal, usize)>) -> Vec<SummaryLine> {
+    let mut lines: Vec<SummaryLine> = totals.into_iter().map(|(name, (amount, value, count))| SummaryLine {
+        section: section.to_owned(),
+        name,
+        amount,
+        value,
+        count,
+    }).collect();
+    lines.sort_by(|a, b| b.value.cmp(&a.value).then(a.name.cmp(&b.name)));
+    lines
+}
+
+impl PortfolioSummary {
+    pub fn from_rows(rows: &[ReportRow], dust_threshold: Decimal, top: usize, currency: &str) -> Self {
+        let mut wallets = HashMap::new();
+        let mut chains = HashMap::new();
+        let mut tokens = HashMap::new();
+        let mut split = HashMap::new();
+        let mut total = Decimal::ZERO;
+        for row in rows {
+            total += row.value;
+            for (totals, name) in [(&mut wallets, row.wallet.to_lowercase()), (&mut chains, row.chain.clone())] {
+                let entry = totals.entry(name).or_insert((None, Decimal::ZERO, 0));
+                entry.1 += row.value;
+                entry.2 += 1;
+            }
+            // Same token on different chains stays separate
+            let entry = tokens.entry(format!("{} on {}", row.token, row.chain)).or_insert((Some(Decimal::ZERO), Decimal::ZERO, 0));
+            entry.0 = entry.0.map(|a| a + row.amount);
+            entry.1 += row.value;
+            entry.2 += 1;
+            let kind = if row.value < dust_threshold { "dust" } else { "meaningful" };
+            let entry = split.entry(kind.to_owned()).or_insert((None, Decimal::ZERO, 0));
+            entry.1 += row.value;
+            entry.2 += 1;
+        }
+
+        let mut holdings: Vec<&ReportRow> = rows.iter().collect();
+        holdings.sort_by_key(|r| std::cmp::Reverse(r.value));
+        let top_lines = holdings.into_iter().take(top).map(|row| SummaryLine {
+            section: "top".to_owned(),
+            name: format!("{} on {} in {}", row.token, row.chain, row.wallet),
+            amount: Some(row.amount),
+            value: row.value,
+            count: 1,
+        });
+
+        let mut lines = vec![SummaryLine {
+            section: "total".to_owned(),
+            name: "Portfolio".to_owned(),
+            amount: None,
+            value: total,
+            count: rows.len(),
+        }];
+        lines.extend(sorted_lines("wallet", wallets));
+        lines.extend(sorted_lines("chain", chains));
+        lines.extend(sorted_lines("token", tokens));
+        lines.extend(top_lines);
+        lines.extend(sorted_lines("split", split));
+        PortfolioSummary { currency: currency.to_owned(), lines }
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.section("total").map(|l| l.value).sum()
+    }
+
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = &'a SummaryLine> {
+        self.lines.iter().filter(move |l| l.section == section)
+    }
+}
+
+fn summary_fields(line: &SummaryLine) -> [String; 4] {
+    [
+        line.name.clone(),
+        line.amount.map(|a| a.to_string()).unwrap_or_default(),
+        line.value.round_dp(6).to_string(),
+        line.count.to_string(),
+    ]
+}
+
+fn summary_headers(currency: &str) -> [String; 4] {
+    ["Name".to_owned(), "Amount".to_owned(), format!("Value ({})", currency), "Balances".to_owned()]
+}
+
+pub fn render_summary_text(summary: &PortfolioSummary) -> String {
+    let mut out = String::new();
+    for (section, title) in SUMMARY_SECTIONS {
+        out.push_str(&format!("{}:\n", title));
+        for line in summary.section(section) {
+            let amount = line.amount.map(|a| format!(", Amount: {}", a)).unwrap_or_default();
+            out.push_str(&format!("  {}{}, Value: {:.2} {} ({} balances)\n", line.name, amount, line.value.round_dp(2), summary.currency, line.count));
+        }
+    }
+    out
+}
+
+pub fn render_summary_csv(summary: &PortfolioSummary) -> String {
+    let mut out = format!("Section,{}\n", summary_headers(&summary.currency).join(","));
+    for line in &summary.lines {
+        let fields = summary_fields(line).iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(",");
+        out.push_str(&format!("{},{}\n", line.section, fields));
+    }
+    out
+}
+
+pub fn render_summary_markdown(summary: &PortfolioSummary) -> String {
+    let mut out = String::from("# Portfolio\n");
+    for (section, title) in SUMMARY_SECTIONS {
+        out.push_str(&format!("\n## {}\n\n| {} |\n|{}\n", title, summary_headers(&summary.currency).join(" | "), " --- |".repeat(4)));
+        for line in summary.section(section) {
+            out.push_str(&format!("| {} |\n", summary_fields(line).map(|f| f.replace('|', "\\|")).join(" | ")));
+        }
+    }
+    out
+}
+
+pub fn render_summary_html(summary: &PortfolioSummary) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Garbage Collector Portfolio</title>\n<style>\n\
+        body { font-family: sans-serif; margin: 2em; }\n\
+        table { border-collapse: collapse; margin-bottom: 1em; }\n\
+        th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+        th { background: #f0f0f0; }\n\
+        td.num { text-align: right; font-family: monospace; }\n\
+        </style>\n</head>\n<body>\n<h1>Portfolio</h1>\n",
+    );
+    for (section, title) in SUMMARY_SECTIONS {
+        out.push_str(&format!("<h2>{}</h2>\n<table>\n<tr>", title));
+        for header in summary_headers(&summary.currency) {
+            out.push_str(&format!("<th>{}</th>", html_escape(&header)));
+        }
+        out.push_str("</tr>\n");
+        for line in summary.section(section) {
+            out.push_str("<tr>");
+            for (i, cell) in summary_fields(line).iter().enumerate() {
+                let class = if i > 0 { " class=\"num\"" } else { "" };
+                out.push_str(&format!("<td{}>{}</td>", class, html_escape(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn render_summary(format: ReportFormat, summary: &PortfolioSummary) -> Result<String> {
+    match format {
+        ReportFormat::Text => Ok(render_summary_text(summary)),
+        ReportFormat::Csv => Ok(render_summary_csv(summary)),
+        ReportFormat::Markdown => Ok(render_summary_markdown(summary)),
+        ReportFormat::Html => Ok(render_summary_html(summary)),
+        ReportFormat::Jsonl => {
+            let mut out = String::new();
+            for line in &summary.lines {
+                out.push_str(&serde_json::to_string(line)?);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+fn write_file(path: &str, contents: &str) -> Result<()> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_report(format: ReportFormat, rows: &[ReportRow], currency: &str, path: &str) -> Result<()> {
+    write_file(path, &render(format, rows, currency)?)
+}
+
+pub fn write_summary(format: ReportFormat, summary: &PortfolioSummary, path: &str) -> Result<()> {
+    write_file(path, &render_summary(format, summary)?)
+}
+
+#[cfg(test)]
+fn test_rows() -> Vec<ReportRow> {
+    let wallet = "0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19";
+    let mut usdc = Balance::new(
+        "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(),
+        "USD Coin".to_owned(),
+        "USDC".to_owned(),
+        6,
+        alloy::primitives::U256::from(2_500_000),
+    );
+    usdc.set_token_price(Decimal::ONE);
+    let native = Balance::new(
+        NATIVE_TOKEN_ADDRESS.parse().unwrap(),
+        "ETH".to_owned(),
+        "ETH".to_owned(),
+        18,
+        alloy::primitives::U256::from(10).pow(alloy::primitives::U256::from(15)),
+    );
+    let balances = HashMap::from([("Ethereum".to_owned(), vec![native, usdc])]);
+    let explorers = HashMap::from([("Ethereum".to_owned(), "https://etherscan.io/tx/".to_owned())]);
+    build_rows(wallet, &balances, &explorers, &CurrencyConverter::usd())
+}
+
+#[test]
+fn test_render_csv_and_jsonl() {
+    let rows = test_rows();
+    assert_eq!(rows[0].token, "USDC");
+
+    let csv = render_csv(&rows, "USD");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "Wallet,Chain,Token,Address,Amount,Price (USD),Value (USD),Explorer");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].ends_with(",2.5,1,2.5,https://etherscan.io/token/0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48?a=0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19"));
+    assert!(lines[2].ends_with("https://etherscan.io/address/0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19"));
+
+    let jsonl = render_jsonl(&rows).unwrap();
+    let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+    assert_eq!(first["chain"], "Ethereum");
+    assert_eq!(first["value"], "2.5");
+}
+
+#[test]
+fn test_render_markdown_and_html() {
+    let rows = test_rows();
+    let markdown = render_markdown(&rows, "EUR");
+    assert!(markdown.starts_with("| Wallet | Chain | Token | Address | Amount | Price (EUR) | Value (EUR) | Explorer |\n| --- |"));
+    assert_eq!(markdown.lines().count(), 4);
+
+    let html = render_html(&rows, "USD");
+    assert!(html.contains("<th>Value (USD)</th>"));
+    assert!(html.contains("<a href=\"https://etherscan.io/token/0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48?a=0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19\">link</a>"));
+
+    assert_eq!(csv_escape("a,b"), "\"a,b\"");
+    assert_eq!(html_escape("<b>"), "&lt;b&gt;");
+}
+
+#[test]
+fn test_portfolio_summary() {
+    let mut rows = test_rows();
+    let mut other = rows[0].clone();
+    other.wallet = "0xf63feA8d383b8089BAbFf2A712AB3190CB21732D".to_owned();
+    other.value = Decimal::new(10, 0);
+    other.amount = Decimal::new(10, 0);
+    rows.push(other);
+
+    let summary = PortfolioSummary::from_rows(&rows, Decimal::ONE, 2, "USD");
+    assert_eq!(summary.total(), Decimal::new(125, 1));
+    assert_eq!(summary.section("wallet").count(), 2);
+    assert_eq!(summary.section("chain").next().unwrap().count, 3);
+
+    // USDC summed across both wallets
+    let usdc = summary.section("token").next().unwrap();
+    assert_eq!(usdc.name, "USDC on Ethereum");
+    assert_eq!(usdc.amount, Some(Decimal::new(125, 1)));
+    assert_eq!(usdc.count, 2);
+
+    assert_eq!(summary.section("top").count(), 2);
+    let split: Vec<(&str, usize)> = summary.section("split").map(|l| (l.name.as_str(), l.count)).collect();
+    assert_eq!(split, vec![("meaningful", 2), ("dust", 1)]);
+
+    let markdown = render_summary_markdown(&summary);
+    assert!(markdown.contains("## Dust vs meaningful"));
+    let csv = render_summary_csv(&summary);
+    assert!(csv.starts_with("Section,Name,Amount,Value (USD),Balances\ntotal,Portfolio,,12.5,3\n"));
+}
+
+#[test]
+fn test_render_terminal() {
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let balance = |symbol: &str, amount: u64, price: i64| {
+        let mut b = Balance::new(usdc, symbol.to_owned(), symbol.to_owned(), 6, alloy::primitives::U256::from(amount));
+        b.set_token_price(Decimal::new(price, 2));
+        b
+    };
+    let balances = HashMap::from([
+        ("Base".to_owned(), vec![balance("DUST", 1, 1), balance("TINY", 2, 1)]),
+        ("Ethereum".to_owned(), vec![balance("USDC", 2_000_000, 100), balance("DAI", 3_000_000, 100)]),
+    ]);
+    let out = render_terminal(&balances, &CurrencyConverter::usd(), Decimal::ONE, false);
+    let lines: Vec<&str> = out.lines().collect();
+
+    assert_eq!(lines[0], "Chain: Ethereum (5.00 USD)");
+    assert_eq!(lines[1], "  Token  Amount  Price     Value");
+    assert_eq!(lines[2], "  DAI         3  1 USD  3.00 USD");
+    assert_eq!(lines[3], "  USDC        2  1 USD  2.00 USD");
+    assert_eq!(lines[6], "  2 dust tokens totaling 0.00 USD");
+    assert_eq!(lines[8], "Total balance: 5.00 USD");
+
+    assert!(render_terminal(&balances, &CurrencyConverter::usd(), Decimal::ONE, true).contains("\x1B[32mChain: Ethereum"));
+}
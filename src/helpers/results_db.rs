@@ -0,0 +1,311 @@
+use std::path::Path;
+
+use eyre::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+
+use crate::helpers::results_store::{normalize_address, ResultsStore, ScanResult};
+
+// Bumped with every change to SCHEMA, applied through PRAGMA user_version
+static DB_VERSION: i64 = 1;
+
+static SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS wallets (
+    address TEXT PRIMARY KEY,
+    first_seen INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    wallet TEXT NOT NULL REFERENCES wallets(address),
+    timestamp INTEGER NOT NULL,
+    schema_version INTEGER NOT NULL,
+    UNIQUE (wallet, timestamp)
+);
+CREATE TABLE IF NOT EXISTS scan_chains (
+    scan_id INTEGER NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+    chain TEXT NOT NULL,
+    block_number INTEGER,
+    price_timestamp INTEGER,
+    PRIMARY KEY (scan_id, chain)
+);
+CREATE TABLE IF NOT EXISTS balances (
+    scan_id INTEGER NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+    chain TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    name TEXT NOT NULL,
+    decimals INTEGER NOT NULL,
+    raw_balance TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    price TEXT NOT NULL,
+    value TEXT NOT NULL,
+    price_source TEXT,
+    price_confidence TEXT,
+    PRIMARY KEY (scan_id, chain, token_address)
+);
+CREATE INDEX IF NOT EXISTS balances_token ON balances (token_address);
+CREATE TABLE IF NOT EXISTS prices (
+    chain TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    price TEXT NOT NULL,
+    source TEXT,
+    confidence TEXT,
+    PRIMARY KEY (chain, token_address, timestamp)
+);
+CREATE TABLE IF NOT EXISTS transactions (
+    hash TEXT PRIMARY KEY,
+    chain TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    success INTEGER NOT NULL,
+    details TEXT
+);
+";
+
+// A transaction sent by the garbage collector, e.g. a swap or an approval
+#[derive(Clone, Debug)]
+pub struct TransactionRecord {
+    pub hash: String,
+    pub chain: String,
+    pub wallet: String,
+    pub kind: String,
+    pub timestamp: u64,
+    pub success: bool,
+    pub details: Option<String>,
+}
+
+// One wallet's balance of a token in its latest scan
+#[derive(Clone, Debug)]
+pub struct TokenHolding {
+    pub wallet: String,
+    pub chain: String,
+    pub symbol: String,
+    pub token_address: String,
+    pub amount: Decimal,
+    pub value: Decimal,
+    pub timestamp: u64,
+}
+
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    pub fn open(path: &str) -> Result<Self> {
+        if let Some(dir) = Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > DB_VERSION {
+            return Err(eyre::eyre!("Results database has unsupported version {}", version));
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.execute_batch(&format!("PRAGMA user_version = {};", DB_VERSION))?;
+        Ok(ResultsDb { conn })
+    }
+
+    // Insert a scan with its balances and prices, replacing an earlier copy of the same scan
+    pub fn insert_scan(&mut self, scan: &ScanResult) -> Result<i64> {
+        let wallet = normalize_address(&scan.wallet)?;
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO wallets (address, first_seen) VALUES (?1, ?2)",
+            params![wallet, scan.timestamp as i64],
+        )?;
+        tx.execute("DELETE FROM scans WHERE wallet = ?1 AND timestamp = ?2", params![wallet, scan.timestamp as i64])?;
+        tx.execute(
+            "INSERT INTO scans (wallet, timestamp, schema_version) VALUES (?1, ?2, ?3)",
+            params![wallet, scan.timestamp as i64, scan.schema_version],
+        )?;
+        let scan_id = tx.last_insert_rowid();
+
+        for (chain, balances) in scan.balances.iter() {
+            let price_timestamp = scan.price_timestamps.get(chain).copied().unwrap_or(scan.timestamp);
+            tx.execute(
+                "INSERT INTO scan_chains (scan_id, chain, block_number, price_timestamp) VALUES (?1, ?2, ?3, ?4)",
+                params![scan_id, chain, scan.block_numbers.get(chain).map(|b| *b as i64), price_timestamp as i64],
+            )?;
+            for balance in balances.iter() {
+                let token = balance.token_address.to_string().to_lowercase();
+                let confidence = match balance.price_confidence {
+                    Some(c) => serde_json::to_value(c)?.as_str().map(|c| c.to_owned()),
+                    None => None,
+                };
+                tx.execute(
+                    "INSERT OR REPLACE INTO balances (scan_id, chain, token_address, symbol, name, decimals, raw_balance, amount, price, value, price_source, price_confidence)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                    params![
+                        scan_id,
+                        chain,
+                        token,
+                        balance.token_symbol,
+                        balance.token_name,
+                        balance.decimals,
+                        balance.balance.to_string(),
+                        balance.amount().unwrap_or_default().to_string(),
+                        balance.token_price.to_string(),
+                        balance.value().unwrap_or_default().to_string(),
+                        balance.price_source,
+                        confidence,
+                    ],
+                )?;
+                if !balance.token_price.is_zero() {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO prices (chain, token_address, timestamp, price, source, confidence) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![chain, token, price_timestamp as i64, balance.token_price.to_string(), balance.price_source, confidence],
+                    )?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(scan_id)
+    }
+
+    pub fn record_transaction(&self, record: &TransactionRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO transactions (hash, chain, wallet, kind, timestamp, success, details) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.hash,
+                record.chain,
+                normalize_address(&record.wallet)?,
+                record.kind,
+                record.timestamp as i64,
+                record.success,
+                record.details,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Balances of a token, matched by symbol or address, in the latest scan of every wallet
+    pub fn token_balances(&self, token: &str) -> Result<Vec<TokenHolding>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.wallet, b.chain, b.symbol, b.token_address, b.amount, b.value, s.timestamp
+            FROM balances b JOIN scans s ON s.id = b.scan_id
+            WHERE (b.token_address = lower(?1) OR upper(b.symbol) = upper(?1))
+            AND s.timestamp = (SELECT MAX(timestamp) FROM scans WHERE wallet = s.wallet)",
+        )?;
+        let rows = stmt.query_map(params![token.trim()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        })?;
+        let mut holdings = vec![];
+        for row in rows {
+            let (wallet, chain, symbol, token_address, amount, value, timestamp) = row?;
+            holdings.push(TokenHolding {
+                wallet,
+                chain,
+                symbol,
+                token_address,
+                amount: amount.parse()?,
+                value: value.parse()?,
+                timestamp: timestamp as u64,
+            });
+        }
+        holdings.sort_by_key(|h| std::cmp::Reverse(h.value));
+        Ok(holdings)
+    }
+
+    // Total USD value of every scan of a wallet, oldest first
+    pub fn value_history(&self, wallet: &str) -> Result<Vec<(u64, Decimal)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.timestamp, b.value FROM scans s LEFT JOIN balances b ON b.scan_id = s.id
+            WHERE s.wallet = ?1 ORDER BY s.timestamp",
+        )?;
+        let rows = stmt.query_map(params![normalize_address(wallet)?], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+        let mut history: Vec<(u64, Decimal)> = vec![];
+        for row in rows {
+            let (timestamp, value) = row?;
+            let value: Decimal = match value {
+                Some(v) => v.parse()?,
+                None => Decimal::ZERO,
+            };
+            match history.last_mut() {
+                Some(last) if last.0 == timestamp as u64 => last.1 += value,
+                _ => history.push((timestamp as u64, value)),
+            }
+        }
+        Ok(history)
+    }
+
+    pub fn has_scan(&self, wallet: &str, timestamp: u64) -> Result<bool> {
+        let id: Option<i64> = self.conn.query_row(
+            "SELECT id FROM scans WHERE wallet = ?1 AND timestamp = ?2",
+            params![normalize_address(wallet)?, timestamp as i64],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(id.is_some())
+    }
+
+    // Copy every JSON scan, including legacy files, that is not in the database yet
+    pub fn import_json(&mut self, store: &ResultsStore) -> Result<usize> {
+        let mut imported = 0;
+        for wallet in store.wallets()? {
+            for scan in store.all_scans(&wallet)? {
+                if !self.has_scan(&scan.wallet, scan.timestamp)? {
+                    self.insert_scan(&scan)?;
+                    imported += 1;
+                }
+            }
+        }
+        Ok(imported)
+    }
+}
+
+#[test]
+fn test_results_db() {
+    use alloy::primitives::U256;
+    use crate::helpers::web3_client::Balance;
+
+    let mut db = ResultsDb::open_in_memory().unwrap();
+    let wallet = "0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19";
+    let usdc = |amount: u64| {
+        let mut balance = Balance::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(),
+            "USD Coin".to_owned(),
+            "USDC".to_owned(),
+            6,
+            U256::from(amount),
+        );
+        balance.set_token_price(Decimal::ONE);
+        balance
+    };
+    let scan = |timestamp: u64, amount: u64| {
+        let balances = std::collections::HashMap::from([("Ethereum".to_owned(), vec![usdc(amount)])]);
+        ScanResult::new(wallet, timestamp, balances).unwrap()
+    };
+    db.insert_scan(&scan(100, 1_000_000)).unwrap();
+    db.insert_scan(&scan(200, 3_000_000)).unwrap();
+    // Re-inserting a scan replaces it
+    db.insert_scan(&scan(200, 2_500_000)).unwrap();
+
+    let holdings = db.token_balances("usdc").unwrap();
+    assert_eq!(holdings.len(), 1);
+    assert_eq!(holdings[0].amount, Decimal::new(25, 1));
+    assert_eq!(holdings[0].timestamp, 200);
+    assert_eq!(db.token_balances("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap().len(), 1);
+
+    let history = db.value_history(&wallet.to_lowercase()).unwrap();
+    assert_eq!(history, vec![(100, Decimal::ONE), (200, Decimal::new(25, 1))]);
+    assert!(db.has_scan(wallet, 100).unwrap());
+}
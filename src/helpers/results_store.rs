@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fs, io::Write, path::{Path, PathBuf}};
+
+use alloy::primitives::Address;
+use eyre::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::to_string_pretty;
+
+use crate::helpers::sweep::SweepPlan;
+use crate::helpers::web3_client::Balance;
+
+// Bump when the layout of ScanResult changes. Legacy tokens_<addr>.json files load as version 0.
+pub static SCHEMA_VERSION: u32 = 1;
+
+// One balance scan of one wallet across all chains
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub schema_version: u32,
+    // Lowercase wallet address
+    pub wallet: String,
+    // Unix seconds the scan finished at
+    pub timestamp: u64,
+    // Block each chain's balances were read at
+    #[serde(default)]
+    pub block_numbers: HashMap<String, u64>,
+    // Unix seconds each chain's prices are valid for
+    #[serde(default)]
+    pub price_timestamps: HashMap<String, u64>,
+    pub balances: HashMap<String, Vec<Balance>>,
+}
+
+impl ScanResult {
+    pub fn new(wallet: &str, timestamp: u64, balances: HashMap<String, Vec<Balance>>) -> Result<Self> {
+        Ok(ScanResult {
+            schema_version: SCHEMA_VERSION,
+            wallet: normalize_address(wallet)?,
+            timestamp,
+            block_numbers: HashMap::new(),
+            price_timestamps: HashMap::new(),
+            balances,
+        })
+    }
+}
+
+// Lowercase 0x address, the only form used for file and directory names
+pub fn normalize_address(address: &str) -> Result<String> {
+    let address: Address = address.trim().parse()?;
+    Ok(address.to_string().to_lowercase())
+}
+
+// Scans stored as <dir>/<wallet>/<timestamp>.json, newest last
+pub struct ResultsStore {
+    dir: PathBuf,
+}
+
+impl Default for ResultsStore {
+    fn default() -> Self {
+        ResultsStore::new("results")
+    }
+}
+
+impl ResultsStore {
+    pub fn new(dir: &str) -> Self {
+        ResultsStore { dir: PathBuf::from(dir) }
+    }
+
+    fn wallet_dir(&self, wallet: &str) -> Result<PathBuf> {
+        Ok(self.dir.join(normalize_address(wallet)?))
+    }
+
+    pub fn save(&self, scan: &ScanResult) -> Result<PathBuf> {
+        self.write_new(&scan.wallet, &format!("{}.json", scan.timestamp), scan)
+    }
+
+    // Reports and plans sit next to the scans as <prefix>_<timestamp>.json, e.g. sweep_1700000000.json
+    pub fn save_report<T: Serialize>(&self, wallet: &str, prefix: &str, timestamp: u64, report: &T) -> Result<PathBuf> {
+        self.write_new(wallet, &format!("{}_{}.json", prefix, timestamp), report)
+    }
+
+    // Files are keyed by their second, a second one within it would replace the first
+    fn write_new<T: Serialize>(&self, wallet: &str, file_name: &str, data: &T) -> Result<PathBuf> {
+        let dir = self.wallet_dir(wallet)?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(file_name);
+        let mut file = match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(eyre::eyre!("ResultsStore:save {} already exists", path.display()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(to_string_pretty(data)?.as_bytes())?;
+        Ok(path)
+    }
+
+    // Plans may have been edited or moved, so they load from any path. Edits that can't be
+    // executed reject the whole plan.
+    pub fn load_plan(path: &str) -> Result<SweepPlan> {
+        let plan: SweepPlan = serde_json::from_str(&fs::read_to_string(path)?)?;
+        plan.validate()?;
+        Ok(plan)
+    }
+
+    // Timestamps of every stored scan of a wallet, oldest first
+    pub fn history(&self, wallet: &str) -> Result<Vec<u64>> {
+        let dir = self.wallet_dir(wallet)?;
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut timestamps: Vec<u64> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.path().file_stem()?.to_str()?.parse::<u64>().ok())
+            .collect();
+        timestamps.sort();
+        Ok(timestamps)
+    }
+
+    pub fn load(&self, wallet: &str, timestamp: u64) -> Result<ScanResult> {
+        let path = self.wallet_dir(wallet)?.join(format!("{}.json", timestamp));
+        let scan: ScanResult = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if scan.schema_version > SCHEMA_VERSION {
+            return Err(eyre::eyre!("Scan {} of {} has unsupported schema version {}", timestamp, wallet, scan.schema_version));
+        }
+        Ok(scan)
+    }
+
+    // Newest scan of a wallet, falling back to a legacy tokens_<addr>.json file
+    pub fn latest(&self, wallet: &str) -> Result<ScanResult> {
+        match self.history(wallet)?.last() {
+            Some(timestamp) => self.load(wallet, *timestamp),
+            None => self.load_legacy(wallet),
+        }
+    }
+
+    // Every stored scan of a wallet oldest first, or the legacy file when there are none
+    pub fn all_scans(&self, wallet: &str) -> Result<Vec<ScanResult>> {
+        let history = self.history(wallet)?;
+        if history.is_empty() {
+            return Ok(vec![self.load_legacy(wallet)?]);
+        }
+        history.into_iter().map(|timestamp| self.load(wallet, timestamp)).collect()
+    }
+
+    fn legacy_path(&self, wallet: &str) -> Option<PathBuf> {
+        let wanted = format!("tokens_{}.json", normalize_address(wallet).ok()?);
+        fs::read_dir(&self.dir).ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.is_file() && Self::file_name(path).is_some_and(|name| name.to_lowercase() == wanted))
+    }
+
+    fn load_legacy(&self, wallet: &str) -> Result<ScanResult> {
+        let path = match self.legacy_path(wallet) {
+            Some(p) => p,
+            None => return Err(eyre::eyre!("No results found for {}", wallet)),
+        };
+        let balances: HashMap<String, Vec<Balance>> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let timestamp = fs::metadata(&path)?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        Ok(ScanResult {
+            schema_version: 0,
+            ..ScanResult::new(wallet, timestamp, balances)?
+        })
+    }
+
+    fn file_name(path: &Path) -> Option<String> {
+        Some(path.file_name()?.to_str()?.to_owned())
+    }
+
+    // Every wallet with stored scans, including legacy files
+    pub fn wallets(&self) -> Result<Vec<String>> {
+        let mut wallets = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let name = match Self::file_name(&path) {
+                Some(n) => n,
+                None => continue,
+            };
+            let wallet = if path.is_dir() {
+                name.clone()
+            } else if let Some(address) = name.strip_prefix("tokens_").and_then(|n| n.strip_suffix(".json")) {
+                address.to_owned()
+            } else {
+                continue;
+            };
+            match normalize_address(&wallet) {
+                Ok(w) => wallets.push(w),
+                Err(_) => warn!("Skipping unrecognized results entry {}", name),
+            }
+        }
+        wallets.sort();
+        wallets.dedup();
+        Ok(wallets)
+    }
+}
+
+#[test]
+fn test_results_store() {
+    let dir = "target/test_results_store";
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+    let store = ResultsStore::new(dir);
+    let wallet = "0xBF17a4730Fe4a1ea36Cf536B8473Cc25ba146F19";
+
+    // Legacy file written with checksum case is still found
+    fs::write(format!("{}/tokens_{}.json", dir, wallet), r#"{"Ethereum": []}"#).unwrap();
+    let legacy = store.latest(&wallet.to_lowercase()).unwrap();
+    assert_eq!(legacy.schema_version, 0);
+    assert!(legacy.balances.contains_key("Ethereum"));
+
+    let mut scan = ScanResult::new(wallet, 1_700_000_000, HashMap::new()).unwrap();
+    scan.block_numbers.insert("Ethereum".to_owned(), 19_000_000);
+    store.save(&scan).unwrap();
+    store.save(&ScanResult::new(wallet, 1_700_000_100, HashMap::new()).unwrap()).unwrap();
+    assert!(store.save(&ScanResult::new(wallet, 1_700_000_100, HashMap::new()).unwrap()).is_err());
+
+    assert_eq!(store.history(wallet).unwrap(), vec![1_700_000_000, 1_700_000_100]);
+    assert_eq!(store.latest(wallet).unwrap().timestamp, 1_700_000_100);
+    assert_eq!(store.load(wallet, 1_700_000_000).unwrap().block_numbers["Ethereum"], 19_000_000);
+    assert_eq!(store.wallets().unwrap(), vec![wallet.to_lowercase()]);
+
+    // Plans don't count as scans
+    let plan = SweepPlan { wallet: wallet.to_owned(), timestamp: 1_700_000_200, scan_timestamp: 1_700_000_100, entries: vec![], skipped: vec![] };
+    let path = store.save_report(&plan.wallet, "plan", plan.timestamp, &plan).unwrap();
+    // A second report of the same kind within the second doesn't replace the first
+    assert!(store.save_report(&plan.wallet, "plan", plan.timestamp, &plan).is_err());
+    assert_eq!(ResultsStore::load_plan(path.to_str().unwrap()).unwrap().scan_timestamp, 1_700_000_100);
+    assert_eq!(store.history(wallet).unwrap().len(), 2);
+    let _ = fs::remove_dir_all(dir);
+}
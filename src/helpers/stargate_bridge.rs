@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    signers::local::PrivateKeySigner,
+    sol_types::SolCall,
+};
+use async_trait::async_trait;
+use eyre::Result;
+use serde_json::Value;
+
+use crate::helpers::bridge::{BridgeChain, BridgeProvider, BridgeQuote};
+use crate::helpers::swap_provider::{self, SwapTx};
+use crate::helpers::web3_client::{StargatePool, Web3Client};
+
+// Rough gas of a native pool send, the transaction itself is estimated
+static SEND_GAS: u64 = 300_000;
+// Native pools move amounts in 6 shared decimals, anything below is dust the pool keeps
+static SHARED_DECIMALS_DUST: u64 = 1_000_000_000_000;
+
+// Stargate V2 native pools, which move ETH between chains over LayerZero in taxi mode.
+// Pools come from the "bridge" sections of chains.json.
+pub struct StargateBridge {
+    pools: HashMap<String, Address>,
+}
+
+impl StargateBridge {
+    pub fn new(chain_data: &Value) -> Self {
+        let pools = chain_data.as_object()
+            .map(|chains| chains.iter()
+                .filter_map(|(name, chain)| {
+                    let pool = chain["bridge"]["stargateNativePool"].as_str()?.parse::<Address>().ok()?;
+                    Some((name.clone(), pool))
+                })
+                .collect())
+            .unwrap_or_default();
+        StargateBridge { pools }
+    }
+
+    fn pool(&self, chain: &BridgeChain) -> Result<Address> {
+        match self.pools.get(chain.name()) {
+            Some(pool) => Ok(*pool),
+            None => Err(eyre::eyre!("StargateBridge No native pool on {}", chain.name())),
+        }
+    }
+
+    // Taxi mode send to the sender's own address on the destination chain
+    pub fn send_param(sender: Address, dst_eid: u32, amount: U256, min_amount: U256) -> StargatePool::SendParam {
+        StargatePool::SendParam {
+            dstEid: dst_eid,
+            to: sender.into_word(),
+            amountLD: amount,
+            minAmountLD: min_amount,
+            extraOptions: Bytes::new(),
+            composeMsg: Bytes::new(),
+            oftCmd: Bytes::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl BridgeProvider for StargateBridge {
+    fn name(&self) -> &'static str {
+        "stargate"
+    }
+
+    fn supports_route(&self, from: &BridgeChain, to: &BridgeChain) -> bool {
+        from.name() != to.name() &&
+        self.pools.contains_key(from.name()) && self.pools.contains_key(to.name()) &&
+        from.lz_eid().is_some() && to.lz_eid().is_some()
+    }
+
+    // The pool quotes what arrives and the LayerZero fee paid on top, both with plain calls
+    async fn quote(&self, sender: Address, from: &BridgeChain, to: &BridgeChain, amount: U256) -> Result<BridgeQuote> {
+        let pool = self.pool(from)?;
+        let dst_eid = match to.lz_eid() {
+            Some(eid) => eid,
+            None => return Err(eyre::eyre!("StargateBridge:quote No LayerZero id for {}", to.name())),
+        };
+        let amount = amount - amount % U256::from(SHARED_DECIMALS_DUST);
+        if amount.is_zero() {
+            return Err(eyre::eyre!("StargateBridge:quote Amount is below the pool's precision"));
+        }
+        let web3_client = Web3Client::new(from.network.clone(), PrivateKeySigner::random())?;
+
+        let data = StargatePool::quoteOFTCall { _sendParam: Self::send_param(sender, dst_eid, amount, U256::ZERO) }.abi_encode();
+        let output = web3_client.call(pool, data.into()).await?;
+        let oft = StargatePool::quoteOFTCall::abi_decode_returns(&output, true)?;
+        if amount > oft.limit.maxAmountLD {
+            return Err(eyre::eyre!("StargateBridge:quote {} is above the pool limit {}", amount, oft.limit.maxAmountLD));
+        }
+        let min_amount = oft.receipt.amountReceivedLD;
+
+        let send_param = Self::send_param(sender, dst_eid, amount, min_amount);
+        let data = StargatePool::quoteSendCall { _sendParam: send_param, _payInLzToken: false }.abi_encode();
+        let output = web3_client.call(pool, data.into()).await?;
+        let native_fee = StargatePool::quoteSendCall::abi_decode_returns(&output, true)?.fee.nativeFee;
+
+        Ok(BridgeQuote {
+            provider: self.name(),
+            from_chain: from.name().to_owned(),
+            to_chain: to.name().to_owned(),
+            amount_in: amount,
+            amount_out: min_amount,
+            native_fee,
+            gas: SEND_GAS,
+            route: serde_json::json!({
+                "pool": pool.to_string(),
+                "dstEid": dst_eid,
+                "minAmount": min_amount.to_string(),
+                "nativeFee": native_fee.to_string(),
+            }),
+        })
+    }
+
+    async fn build_bridge(&self, sender: Address, quote: &BridgeQuote) -> Result<SwapTx> {
+        let pool: Address = match quote.route["pool"].as_str() {
+            Some(pool) => pool.parse()?,
+            None => return Err(eyre::eyre!("StargateBridge:build_bridge Quote has no pool")),
+        };
+        let dst_eid = match quote.route["dstEid"].as_u64() {
+            Some(eid) => eid as u32,
+            None => return Err(eyre::eyre!("StargateBridge:build_bridge Quote has no destination")),
+        };
+        let min_amount = swap_provider::parse_amount(&quote.route["minAmount"])?;
+        let native_fee = swap_provider::parse_amount(&quote.route["nativeFee"])?;
+        let data = StargatePool::sendCall {
+            _sendParam: Self::send_param(sender, dst_eid, quote.amount_in, min_amount),
+            _fee: StargatePool::MessagingFee { nativeFee: native_fee, lzTokenFee: U256::ZERO },
+            _refundAddress: sender,
+        }.abi_encode();
+        Ok(SwapTx {
+            to: pool,
+            data: data.into(),
+            value: quote.amount_in + native_fee,
+            gas: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_stargate_send() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    let stargate = StargateBridge::new(&chain_data);
+    let (base, arbitrum, bsc) = (
+        crate::helpers::bridge::test_bridge_chain("Base"),
+        crate::helpers::bridge::test_bridge_chain("Arbitrum"),
+        crate::helpers::bridge::test_bridge_chain("Bsc"),
+    );
+    assert!(stargate.supports_route(&base, &arbitrum));
+    assert!(!stargate.supports_route(&base, &base));
+    assert!(!stargate.supports_route(&bsc, &arbitrum));
+
+    let sender: Address = "0x0000000000000000000000000000000000000001".parse().unwrap();
+    let amount = U256::from(50_000_000_000_000_000u64);
+    let quote = BridgeQuote {
+        provider: "stargate",
+        from_chain: "Base".to_owned(),
+        to_chain: "Arbitrum".to_owned(),
+        amount_in: amount,
+        amount_out: U256::from(49_995_000_000_000_000u64),
+        native_fee: U256::from(30_000_000_000_000u64),
+        gas: SEND_GAS,
+        route: serde_json::json!({
+            "pool": "0xdc181Bd607330aeeBEF6ea62e03e5e1Fb4B6F7C7",
+            "dstEid": arbitrum.lz_eid().unwrap(),
+            "minAmount": "49995000000000000",
+            "nativeFee": "30000000000000",
+        }),
+    };
+    let tx = stargate.build_bridge(sender, &quote).await.unwrap();
+    assert_eq!(tx.to, stargate.pool(&base).unwrap());
+    assert_eq!(tx.value, amount + quote.native_fee);
+    let call = StargatePool::sendCall::abi_decode(&tx.data, true).unwrap();
+    assert_eq!(call._sendParam.dstEid, 30110);
+    assert_eq!(call._sendParam.to, sender.into_word());
+    assert_eq!(call._sendParam.minAmountLD, quote.amount_out);
+    assert!(call._sendParam.oftCmd.is_empty());
+    assert_eq!(call._fee.nativeFee, quote.native_fee);
+    assert_eq!(call._refundAddress, sender);
+}
@@ -0,0 +1,730 @@
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{utils::parse_units, Address, Bytes, U256},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner
+};
+use async_trait::async_trait;
+use eyre::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::const_types::{Env, NATIVE_TOKEN_ADDRESS};
+use crate::helpers::dex_swapper::DexSwapper;
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::kyberswap_aggregator::KyberSwapAggregator;
+use crate::helpers::odos_aggregator::OdosAggregator;
+use crate::helpers::oneinch_aggregator::OneInchAggregator;
+use crate::helpers::paraswap_aggregator::ParaSwapAggregator;
+use crate::helpers::web3_client::{self, ApprovalStrategy, GasMultiplier, Network, SimulatedTx, TxOutcome, Web3Client};
+use crate::helpers::zerox_aggregator::ZeroExAggregator;
+
+// Quote and execution settings. chains.json sets per-chain defaults under "swapOptions".
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SwapOptions {
+    pub slippage_percent: f64,
+    // Odos referral code, 0 for none
+    pub referral_code: u32,
+    // Liquidity sources to route around, or the only ones to route through
+    pub source_blacklist: Vec<String>,
+    pub source_whitelist: Vec<String>,
+    // Shorter calldata through Odos' compact encoding
+    pub compact: bool,
+    // Gas price in gwei to quote and send with instead of the network's
+    pub gas_price_gwei: Option<f64>,
+    // The routers pull inputs with transferFrom, so Permit2 allowances don't reach them and the
+    // permit2 strategy is refused
+    pub approval: ApprovalStrategy,
+    pub guards: SwapGuards,
+}
+
+impl Default for SwapOptions {
+    fn default() -> Self {
+        SwapOptions {
+            slippage_percent: 3.0,
+            referral_code: 0,
+            source_blacklist: vec![],
+            source_whitelist: vec![],
+            compact: true,
+            gas_price_gwei: None,
+            approval: ApprovalStrategy::Exact,
+            guards: SwapGuards::default(),
+        }
+    }
+}
+
+impl SwapOptions {
+    pub fn from_chain_data(chain: &Value) -> Result<Self> {
+        let options: SwapOptions = match chain.get("swapOptions") {
+            Some(options) => serde_json::from_value(options.clone())?,
+            None => SwapOptions::default(),
+        };
+        // It would approve Permit2 and then every swap's transferFrom would fail
+        if options.approval == ApprovalStrategy::Permit2 {
+            return Err(eyre::eyre!("SwapOptions:from_chain_data No swap router pulls inputs through Permit2, use exact, unlimited or permit approvals"));
+        }
+        Ok(options)
+    }
+
+    pub fn gas_price_wei(&self) -> Result<Option<u128>> {
+        match self.gas_price_gwei {
+            Some(gwei) => Ok(Some(parse_units(&gwei.to_string(), "gwei")?.get_absolute().to::<u128>())),
+            None => Ok(None),
+        }
+    }
+
+    // Slippage in basis points, as most aggregators take it
+    pub fn slippage_bps(&self) -> u32 {
+        (self.slippage_percent * 100.0).round() as u32
+    }
+}
+
+// Limits a quote has to stay within for the swap to be executed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SwapGuards {
+    // Percent the route may move the price
+    pub max_price_impact_percent: f64,
+    // Percent of the input value the output may lose, fees and price impact included
+    pub max_value_loss_percent: f64,
+    // Gas cost as a fraction of the output value
+    pub max_gas_fraction: f64,
+    // Blocks a quote may age before it has to be requested again
+    pub max_quote_age_blocks: u64,
+}
+
+impl Default for SwapGuards {
+    fn default() -> Self {
+        SwapGuards {
+            max_price_impact_percent: 5.0,
+            max_value_loss_percent: 10.0,
+            max_gas_fraction: 0.5,
+            max_quote_age_blocks: 30,
+        }
+    }
+}
+
+// Why a guard refused to execute a quote
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum SwapRejection {
+    PriceImpact { impact: f64, limit: f64 },
+    ValueLoss { loss: f64, limit: f64 },
+    GasCost { gas_value: f64, out_value: f64, limit: f64 },
+    NoNetValue { net_out_value: f64 },
+    StaleQuote { quote_block: u64, current_block: u64, limit: u64 },
+}
+
+impl std::fmt::Display for SwapRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwapRejection::PriceImpact { impact, limit } => write!(f, "price impact {}% exceeds {}%", impact, limit),
+            SwapRejection::ValueLoss { loss, limit } => write!(f, "output is worth {}% less than the input, limit {}%", loss, limit),
+            SwapRejection::GasCost { gas_value, out_value, limit } => {
+                write!(f, "gas {} USD is more than {} of the output {} USD", gas_value, limit, out_value)
+            }
+            SwapRejection::NoNetValue { net_out_value } => write!(f, "net output {} USD after gas", net_out_value),
+            SwapRejection::StaleQuote { quote_block, current_block, limit } => {
+                write!(f, "quote from block {} is older than {} blocks at block {}", quote_block, limit, current_block)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SwapRejection {}
+
+// A swap that failed before any of its transactions was broadcast, so it can be retried another way
+#[derive(Debug)]
+pub struct SwapNotSent(pub eyre::Report);
+
+impl std::fmt::Display for SwapNotSent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SwapNotSent {}
+
+pub fn not_sent(error: eyre::Report) -> eyre::Report {
+    eyre::Report::new(SwapNotSent(error))
+}
+
+// What the guards look at. Aggregators report different parts of it, missing values skip their check.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuoteMetrics {
+    pub price_impact: Option<f64>,
+    pub value_loss_percent: Option<f64>,
+    // USD values
+    pub out_value: Option<f64>,
+    pub gas_value: Option<f64>,
+    pub net_out_value: Option<f64>,
+    pub block_number: Option<u64>,
+}
+
+impl QuoteMetrics {
+    pub fn net_out_value(&self) -> Option<f64> {
+        match (self.net_out_value, self.out_value, self.gas_value) {
+            (Some(net), _, _) => Some(net),
+            (None, Some(out), Some(gas)) => Some(out - gas),
+            _ => None,
+        }
+    }
+}
+
+impl SwapGuards {
+    pub fn check(&self, metrics: &QuoteMetrics, current_block: u64) -> Result<(), SwapRejection> {
+        if let Some(impact) = metrics.price_impact.map(f64::abs).filter(|i| *i > self.max_price_impact_percent) {
+            return Err(SwapRejection::PriceImpact { impact, limit: self.max_price_impact_percent });
+        }
+        if let Some(loss) = metrics.value_loss_percent.filter(|l| *l > self.max_value_loss_percent) {
+            return Err(SwapRejection::ValueLoss { loss, limit: self.max_value_loss_percent });
+        }
+        if let (Some(out_value), Some(gas_value)) = (metrics.out_value, metrics.gas_value) {
+            if gas_value > out_value * self.max_gas_fraction {
+                return Err(SwapRejection::GasCost { gas_value, out_value, limit: self.max_gas_fraction });
+            }
+        }
+        if let Some(net_out_value) = metrics.net_out_value().filter(|n| *n <= 0.0) {
+            return Err(SwapRejection::NoNetValue { net_out_value });
+        }
+        if let Some(quote_block) = metrics.block_number {
+            if current_block.saturating_sub(quote_block) > self.max_quote_age_blocks {
+                return Err(SwapRejection::StaleQuote { quote_block, current_block, limit: self.max_quote_age_blocks });
+            }
+        }
+        Ok(())
+    }
+}
+
+// A quote from any provider, enough to compare it with the others and to build its swap
+#[derive(Clone, Debug)]
+pub struct SwapQuote {
+    pub provider: &'static str,
+    pub tokens_in: Vec<(TokenData, U256)>,
+    pub token_out: TokenData,
+    pub out_amount: U256,
+    // Gas units the provider expects the swap to use
+    pub gas: u64,
+    // Contract the inputs have to be approved to
+    pub spender: Address,
+    // USD value of each input when the provider reports it
+    pub in_values: Vec<f64>,
+    pub metrics: QuoteMetrics,
+    // Provider specific data the swap is built from, e.g. Odos' path id
+    pub route: Value,
+}
+
+impl SwapQuote {
+    pub fn gas_cost(&self, gas_price: u128) -> U256 {
+        U256::from(self.gas) * U256::from(gas_price)
+    }
+
+    // Output left after paying for gas. Gas can only be taken off a native output, other outputs compare as they are.
+    pub fn net_out(&self, gas_price: u128) -> U256 {
+        if web3_client::is_native_token(&self.token_out.address) {
+            self.out_amount.saturating_sub(self.gas_cost(gas_price))
+        } else {
+            self.out_amount
+        }
+    }
+}
+
+// Transaction a provider built for a quote
+#[derive(Clone, Debug)]
+pub struct SwapTx {
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas: Option<u128>,
+}
+
+// Approvals and the swap itself, each either sent or simulated
+#[derive(Debug)]
+pub struct SwapOutcome {
+    pub approvals: Vec<TxOutcome>,
+    pub swap: TxOutcome,
+}
+
+impl SwapOutcome {
+    // Wei the simulated transactions would spend on gas
+    pub fn simulated_gas_cost(&self) -> U256 {
+        self.approvals.iter().chain([&self.swap]).map(|outcome| match outcome {
+            TxOutcome::Simulated(tx) => tx.gas_cost(),
+            TxOutcome::Sent(_) => U256::ZERO,
+        }).sum()
+    }
+}
+
+#[async_trait]
+pub trait SwapProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether the provider's API covers the network it was created for
+    fn supports_network(&self) -> bool;
+
+    // Most input tokens one swap can take
+    fn max_inputs(&self) -> usize {
+        1
+    }
+
+    async fn quote(&self, sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote>;
+
+    // Transaction that executes a quote of this provider, with the inputs already approved to its spender.
+    // Providers that can simulate it first do so when simulate is set, which it isn't while an approval is pending.
+    async fn build_swap(&self, sender: Address, quote: &SwapQuote, options: &SwapOptions, simulate: bool) -> Result<SwapTx>;
+}
+
+// The only input of a single-input swap
+pub fn single_input<'a>(provider: &str, tokens_in: &'a [(TokenData, U256)]) -> Result<&'a (TokenData, U256)> {
+    match tokens_in {
+        [token_in] => Ok(token_in),
+        _ => Err(eyre::eyre!("{} swaps exactly one input token, got {}", provider, tokens_in.len())),
+    }
+}
+
+// Aggregators take the 0xEeee placeholder for the native token
+pub fn api_address(token_address: &Address) -> Address {
+    if web3_client::is_native_token(token_address) {
+        NATIVE_TOKEN_ADDRESS.parse().unwrap()
+    } else {
+        *token_address
+    }
+}
+
+// Amounts come as decimal strings or plain numbers
+pub fn parse_amount(value: &Value) -> Result<U256> {
+    match value {
+        Value::String(s) => Ok(s.parse::<U256>()?),
+        Value::Number(n) => Ok(n.to_string().parse::<U256>()?),
+        v => Err(eyre::eyre!("Expected an amount, got {}", v)),
+    }
+}
+
+pub async fn send_json(request: reqwest::RequestBuilder, context: &str) -> Result<Value> {
+    let res = request.header("Accept", "application/json").send().await?;
+    if res.status() != 200 {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        return Err(eyre::eyre!("{} Request failed with status {}: {}", context, status, body));
+    }
+    Ok(res.json().await?)
+}
+
+pub fn gas_price_multiplier(chain_name: &str) -> f32 {
+    if chain_name == "Ethereum" || chain_name == "Polygon" || chain_name == "Avalanche" {1.1} else {1.0}
+}
+
+pub static DEFAULT_PROVIDERS: [&str; 6] = ["odos", "kyberswap", "paraswap", "1inch", "0x", "dex"];
+
+// Which providers to ask and their credentials
+#[derive(Clone, Debug)]
+pub struct SwapProviderConfig {
+    pub providers: Vec<String>,
+    pub oneinch_api_key: String,
+    pub zerox_api_key: String,
+}
+
+impl Default for SwapProviderConfig {
+    fn default() -> Self {
+        SwapProviderConfig {
+            providers: DEFAULT_PROVIDERS.iter().map(|p| p.to_string()).collect(),
+            oneinch_api_key: String::new(),
+            zerox_api_key: String::new(),
+        }
+    }
+}
+
+impl SwapProviderConfig {
+    pub fn from_env(env: &Env) -> Self {
+        SwapProviderConfig {
+            providers: env.swap_providers.clone(),
+            oneinch_api_key: env.oneinch_api_key.clone(),
+            zerox_api_key: env.zerox_api_key.clone(),
+        }
+    }
+}
+
+// The providers that cover one network. Quotes are compared on the output left after gas.
+pub struct SwapRouter {
+    network: Network,
+    providers: Vec<Box<dyn SwapProvider>>,
+}
+
+impl SwapRouter {
+    pub fn new(network: Network, providers: Vec<Box<dyn SwapProvider>>) -> Self {
+        SwapRouter {
+            network,
+            providers: providers.into_iter().filter(|p| p.supports_network()).collect(),
+        }
+    }
+
+    pub fn from_config(config: &SwapProviderConfig, network: &Network, chain_data: &Value, signer: &PrivateKeySigner) -> Result<Self> {
+        let mut providers: Vec<Box<dyn SwapProvider>> = vec![];
+        for provider in config.providers.iter() {
+            match provider.as_str() {
+                "odos" => providers.push(Box::new(OdosAggregator::new(signer.clone(), network.clone(), vec![])?)),
+                // 1inch and 0x only answer with an API key
+                "1inch" if !config.oneinch_api_key.is_empty() => {
+                    providers.push(Box::new(OneInchAggregator::new(network.clone(), config.oneinch_api_key.clone())?));
+                }
+                "0x" if !config.zerox_api_key.is_empty() => {
+                    providers.push(Box::new(ZeroExAggregator::new(network.clone(), config.zerox_api_key.clone())?));
+                }
+                "1inch" | "0x" => {}
+                "paraswap" => providers.push(Box::new(ParaSwapAggregator::new(network.clone())?)),
+                "kyberswap" => providers.push(Box::new(KyberSwapAggregator::new(network.clone())?)),
+                // Uniswap routers from the chain's "dex" section
+                "dex" => providers.push(Box::new(DexSwapper::new(network.clone(), chain_data)?)),
+                p => warn!("Unknown swap provider {}, skipping", p),
+            }
+        }
+        Ok(Self::new(network.clone(), providers))
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn is_supported(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&dyn SwapProvider> {
+        self.providers.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    pub fn max_inputs(&self) -> usize {
+        self.providers.iter().map(|p| p.max_inputs()).max().unwrap_or(1)
+    }
+
+    // Quotes of every provider that takes this many inputs, best first. Failed providers are left out.
+    pub async fn quotes(
+        &self,
+        sender: Address,
+        tokens_in: &[(TokenData, U256)],
+        token_out: &TokenData,
+        options: &SwapOptions,
+        gas_price: u128,
+    ) -> Vec<SwapQuote> {
+        let requests = self.providers.iter()
+            .filter(|p| p.max_inputs() >= tokens_in.len())
+            .map(|p| async move { (p.name(), p.quote(sender, tokens_in, token_out, options).await) });
+        let mut quotes = vec![];
+        for (name, result) in futures::future::join_all(requests).await {
+            match result {
+                Ok(q) => quotes.push(q),
+                Err(e) => warn!("No {} quote on {}: {:?}", name, self.network.chain_name, e),
+            }
+        }
+        rank_quotes(&mut quotes, gas_price);
+        quotes
+    }
+
+    pub async fn best_quote(
+        &self,
+        sender: Address,
+        tokens_in: &[(TokenData, U256)],
+        token_out: &TokenData,
+        options: &SwapOptions,
+        gas_price: u128,
+    ) -> Result<SwapQuote> {
+        match self.quotes(sender, tokens_in, token_out, options, gas_price).await.into_iter().next() {
+            Some(q) => Ok(q),
+            None => Err(eyre::eyre!("SwapRouter:best_quote No provider could quote the swap on {}", self.network.chain_name)),
+        }
+    }
+
+    // Quote on one named provider, e.g. the one a sweep plan was made with
+    pub async fn quote_with(
+        &self,
+        provider: &str,
+        sender: Address,
+        tokens_in: &[(TokenData, U256)],
+        token_out: &TokenData,
+        options: &SwapOptions,
+    ) -> Result<SwapQuote> {
+        match self.provider(provider) {
+            Some(p) => p.quote(sender, tokens_in, token_out, options).await,
+            None => Err(eyre::eyre!("SwapRouter:quote_with Provider {} not available on {}", provider, self.network.chain_name)),
+        }
+    }
+
+    // Check the guards, approve the inputs for the quote's spender, then build and send the swap.
+    // Errors before any approval or the swap was broadcast come back as SwapNotSent.
+    pub async fn execute(&self, web3_client: &Web3Client, quote: &SwapQuote, options: &SwapOptions) -> Result<SwapOutcome> {
+        let provider = match self.provider(quote.provider) {
+            Some(p) => p,
+            None => return Err(not_sent(eyre::eyre!("SwapRouter:execute Provider {} not available", quote.provider))),
+        };
+        // Rejections come back as SwapRejection errors
+        let current_block = web3_client.get_block_number().await.map_err(not_sent)?;
+        options.guards.check(&quote.metrics, current_block)?;
+
+        let mut approvals = vec![];
+        for (token_in, amount) in quote.tokens_in.iter() {
+            approvals.extend(web3_client.approve_with_strategy(token_in.address, quote.spender, *amount, options.approval).await?);
+        }
+        // Without the allowance in place the swap itself can't be simulated
+        let pending_approval = approvals.iter().any(|a| matches!(a, TxOutcome::Simulated(_)));
+
+        let sender = web3_client.address();
+        let approved = approvals.iter().any(|a| matches!(a, TxOutcome::Sent(_)));
+        let swap_tx = provider.build_swap(sender, quote, options, !pending_approval).await
+            .map_err(|e| if approved { e } else { not_sent(e) })?;
+        let gas_price_override = options.gas_price_wei()?;
+        let swap = if pending_approval {
+            let gas_price = match gas_price_override {
+                Some(gas_price) => gas_price,
+                None => web3_client.get_gas_price(Some(1.0)).await?,
+            };
+            TxOutcome::Simulated(SimulatedTx {
+                to: Some(swap_tx.to),
+                gas: swap_tx.gas.unwrap_or(quote.gas as u128),
+                gas_price,
+                output: None,
+            })
+        } else {
+            let mut tx = TransactionRequest::default()
+                .with_from(sender)
+                .with_to(swap_tx.to)
+                .with_input(swap_tx.data)
+                .with_value(swap_tx.value);
+            if let Some(gas) = swap_tx.gas {
+                tx = tx.with_gas_limit(gas);
+            }
+            let gas_multiplier = match gas_price_override {
+                Some(gas_price) => {
+                    tx = tx.max_fee_per_gas(gas_price).max_priority_fee_per_gas(gas_price);
+                    None
+                }
+                None => Some(GasMultiplier::new(gas_price_multiplier(&self.network.chain_name), 1.1)),
+            };
+            web3_client.send_tx(tx, gas_multiplier).await?
+        };
+
+        Ok(SwapOutcome { approvals, swap })
+    }
+}
+
+// Best net output first
+pub fn rank_quotes(quotes: &mut [SwapQuote], gas_price: u128) {
+    quotes.sort_by_key(|q| std::cmp::Reverse(q.net_out(gas_price)));
+}
+
+// Answers requests whose path starts with one of the prefixes with its JSON, anything else with a 404
+#[cfg(test)]
+pub async fn mock_server(routes: Vec<(&'static str, Value)>) -> reqwest::Url {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = reqwest::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = vec![];
+            let mut buf = [0u8; 4096];
+            // Read the head and as much body as Content-Length announces
+            loop {
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let complete = match text.find("\r\n\r\n") {
+                    Some(end) => {
+                        let length = text.lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap_or(0)))
+                            .unwrap_or(0);
+                        request.len() >= end + 4 + length
+                    }
+                    None => false,
+                };
+                if n == 0 || complete {
+                    break;
+                }
+            }
+            let text = String::from_utf8_lossy(&request).to_string();
+            let path = text.split_whitespace().nth(1).unwrap_or("/").to_owned();
+            let (status, body) = match routes.iter().find(|(prefix, _)| path.starts_with(prefix)) {
+                Some((_, body)) => ("200 OK", body.to_string()),
+                None => ("404 Not Found", format!("{{\"error\":\"no route for {}\"}}", path)),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body,
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    url
+}
+
+#[cfg(test)]
+pub fn test_network(id: u32, chain_name: &str) -> Network {
+    Network {
+        id,
+        chain_name: chain_name.to_owned(),
+        rpc_url: vec!["http://127.0.0.1:1".parse().unwrap()],
+        explorer: String::new(),
+        multicall: "0xcA11bde05977b3631167028862bE2a173976CA11".parse().unwrap(),
+    }
+}
+
+#[cfg(test)]
+pub fn test_token(address: &str, symbol: &str, decimals: u8) -> TokenData {
+    TokenData { address: address.parse().unwrap(), name: symbol.to_owned(), symbol: symbol.to_owned(), decimals }
+}
+
+#[cfg(test)]
+struct StaticSwapProvider {
+    name: &'static str,
+    out_amount: u64,
+    gas: u64,
+    max_inputs: usize,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl SwapProvider for StaticSwapProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn supports_network(&self) -> bool {
+        self.out_amount > 0
+    }
+
+    fn max_inputs(&self) -> usize {
+        self.max_inputs
+    }
+
+    async fn quote(&self, _sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, _options: &SwapOptions) -> Result<SwapQuote> {
+        if self.gas == 0 {
+            return Err(eyre::eyre!("no route"));
+        }
+        Ok(SwapQuote {
+            provider: self.name,
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: U256::from(self.out_amount),
+            gas: self.gas,
+            spender: Address::ZERO,
+            in_values: vec![],
+            metrics: QuoteMetrics::default(),
+            route: Value::Null,
+        })
+    }
+
+    async fn build_swap(&self, _sender: Address, _quote: &SwapQuote, _options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        Err(eyre::eyre!("not executable"))
+    }
+}
+
+#[test]
+fn test_swap_outcome_gas_cost() {
+    let simulated = |gas: u128| TxOutcome::Simulated(SimulatedTx { to: None, gas, gas_price: 1_000_000_000, output: None });
+    let outcome = SwapOutcome { approvals: vec![simulated(50_000)], swap: simulated(200_000) };
+    assert_eq!(outcome.simulated_gas_cost(), U256::from(250_000_000_000_000u128));
+}
+
+#[tokio::test]
+async fn test_best_net_quote() {
+    let provider = |name: &'static str, out_amount: u64, gas: u64, max_inputs: usize| -> Box<dyn SwapProvider> {
+        Box::new(StaticSwapProvider { name, out_amount, gas, max_inputs })
+    };
+    let router = SwapRouter::new(test_network(81457, "Blast"), vec![
+        // Most output but the most gas too
+        provider("heavy", 1_000_000, 600, 1),
+        provider("light", 900_000, 100, 1),
+        provider("batching", 800_000, 100, 6),
+        provider("down", 2_000_000, 0, 1),
+        provider("elsewhere", 0, 100, 1),
+    ]);
+    assert!(router.is_supported());
+    assert!(router.provider("elsewhere").is_none());
+    assert_eq!(router.max_inputs(), 6);
+
+    let usdb = test_token("0x4300000000000000000000000000000000000003", "USDB", 18);
+    let eth = test_token(NATIVE_TOKEN_ADDRESS, "ETH", 18);
+    let sender = Address::ZERO;
+    let options = SwapOptions::default();
+    let tokens_in = [(usdb.clone(), U256::from(1000))];
+
+    let quotes = router.quotes(sender, &tokens_in, &eth, &options, 1000).await;
+    let names: Vec<&str> = quotes.iter().map(|q| q.provider).collect();
+    assert_eq!(names, vec!["light", "batching", "heavy"]);
+    assert_eq!(quotes[0].net_out(1000), U256::from(800_000));
+    // Without gas costs the biggest output wins
+    assert_eq!(router.best_quote(sender, &tokens_in, &eth, &options, 0).await.unwrap().provider, "heavy");
+    // A non-native output can't pay for gas
+    assert_eq!(router.best_quote(sender, &[(eth.clone(), U256::from(1))], &usdb, &options, 1000).await.unwrap().provider, "heavy");
+
+    let batch = [(usdb.clone(), U256::from(1)), (test_token("0x4300000000000000000000000000000000000004", "WETH", 18), U256::from(1))];
+    let quotes = router.quotes(sender, &batch, &eth, &options, 1000).await;
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].provider, "batching");
+    assert!(router.quote_with("elsewhere", sender, &tokens_in, &eth, &options).await.is_err());
+}
+
+#[tokio::test]
+async fn test_execute_not_sent() {
+    let router = SwapRouter::new(test_network(81457, "Blast"), vec![
+        Box::new(StaticSwapProvider { name: "static", out_amount: 1_000, gas: 100, max_inputs: 1 }),
+    ]);
+    let usdb = test_token("0x4300000000000000000000000000000000000003", "USDB", 18);
+    let eth = test_token(NATIVE_TOKEN_ADDRESS, "ETH", 18);
+    let options = SwapOptions::default();
+    let quote = router.best_quote(Address::ZERO, &[(usdb, U256::from(1000))], &eth, &options, 0).await.unwrap();
+    // The test network's RPC is unreachable, so execution stops before anything is sent
+    let web3_client = Web3Client::new(router.network().clone(), PrivateKeySigner::random()).unwrap();
+    let error = router.execute(&web3_client, &quote, &options).await.unwrap_err();
+    assert!(error.downcast_ref::<SwapNotSent>().is_some());
+    assert!(error.downcast_ref::<SwapRejection>().is_none());
+}
+
+// Chains nothing can swap on: no aggregator lists them and chains.json has no "dex" section for them
+#[test]
+fn test_uncovered_chains() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    let config = SwapProviderConfig {
+        providers: DEFAULT_PROVIDERS.iter().map(|p| p.to_string()).collect(),
+        oneinch_api_key: "key".to_owned(),
+        zerox_api_key: "key".to_owned(),
+    };
+    let signer = PrivateKeySigner::random();
+    let uncovered: Vec<&str> = chain_data.as_object().unwrap().iter()
+        .filter(|(name, chain)| {
+            let network = Network::from_chain_data(name, chain).unwrap();
+            !SwapRouter::from_config(&config, &network, chain, &signer).unwrap().is_supported()
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(uncovered, vec!["Core", "Manta", "Nova", "Opbnb", "Taiko"]);
+}
+
+#[test]
+fn test_guards_on_partial_metrics() {
+    let guards = SwapGuards::default();
+    assert_eq!(guards.check(&QuoteMetrics::default(), 1_000), Ok(()));
+    let metrics = QuoteMetrics { out_value: Some(10.0), gas_value: Some(12.0), ..Default::default() };
+    assert!(matches!(guards.check(&metrics, 0), Err(SwapRejection::GasCost { .. })));
+    let loose = SwapGuards { max_gas_fraction: 2.0, ..guards };
+    assert_eq!(loose.check(&metrics, 0), Err(SwapRejection::NoNetValue { net_out_value: -2.0 }));
+    assert_eq!(SwapOptions { slippage_percent: 0.5, ..SwapOptions::default() }.slippage_bps(), 50);
+}
+
+#[test]
+fn test_swap_options_approval() {
+    let options = |approval: &str| SwapOptions::from_chain_data(&serde_json::json!({ "swapOptions": { "approval": approval } }));
+    assert_eq!(options("unlimited").unwrap().approval, ApprovalStrategy::Unlimited);
+    assert_eq!(options("permit").unwrap().approval, ApprovalStrategy::Permit);
+    assert!(options("permit2").is_err());
+}
+
+#[tokio::test]
+async fn test_mock_server() {
+    let url = mock_server(vec![("/quote", serde_json::json!({ "amount": "12" }))]).await;
+    let client = reqwest::Client::new();
+    let json = send_json(client.post(url.join("quote?x=1").unwrap()).json(&serde_json::json!({ "a": 1 })), "test").await.unwrap();
+    assert_eq!(parse_amount(&json["amount"]).unwrap(), U256::from(12));
+    assert!(send_json(client.get(url.join("missing").unwrap()), "test").await.is_err());
+}
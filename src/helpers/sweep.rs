@@ -0,0 +1,580 @@
+use alloy::primitives::{utils::parse_units, Address, U256};
+use eyre::Result;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::constants::const_types::NATIVE_TOKEN_ADDRESS;
+use crate::helpers::currency::CurrencyConverter;
+use crate::helpers::swap_provider::SwapRejection;
+use crate::helpers::web3_client::Balance;
+
+// Plan route of wrapped native balances, which are unwrapped instead of swapped
+pub static UNWRAP_ROUTE: &str = "unwrap";
+// Rough gas of a WETH9 style withdraw, only used to value it before sending
+pub static UNWRAP_GAS: u64 = 40_000;
+
+// USD value range of the balances a sweep swaps to native
+#[derive(Clone, Debug)]
+pub struct SweepConfig {
+    // Smaller balances aren't worth a transaction
+    pub min_value: Decimal,
+    // Larger balances are left for manual handling
+    pub max_value: Decimal,
+    // Percent a balance or quote may move between planning and executing a sweep
+    pub drift_tolerance: Decimal,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        SweepConfig {
+            min_value: Decimal::new(5, 1),
+            max_value: Decimal::new(50, 0),
+            drift_tolerance: Decimal::ONE,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum SweepStatus {
+    Swapped {
+        tx_hash: String,
+        // USD value of the native output and of the gas, as quoted
+        out_value: Decimal,
+        gas_value: Decimal,
+    },
+    // Wrapped native turned back into native with the wrapper's withdraw
+    Unwrapped {
+        tx_hash: String,
+        out_value: Decimal,
+        gas_value: Decimal,
+    },
+    // Dry run: the swap was built and simulated but not sent
+    Simulated {
+        out_value: Decimal,
+        gas_value: Decimal,
+    },
+    // A swap guard refused the quote
+    Rejected { rejection: SwapRejection },
+    Skipped { reason: String },
+    Failed { error: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepEntry {
+    pub chain: String,
+    pub token: String,
+    pub address: Address,
+    pub amount: Decimal,
+    // USD value at scan time
+    pub value: Decimal,
+    #[serde(flatten)]
+    pub status: SweepStatus,
+}
+
+impl SweepEntry {
+    pub fn new(chain: &str, balance: &Balance, status: SweepStatus) -> Self {
+        SweepEntry {
+            chain: chain.to_owned(),
+            token: balance.token_symbol.clone(),
+            address: balance.token_address,
+            amount: balance.amount().unwrap_or_default(),
+            value: balance.value().unwrap_or_default(),
+            status,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub wallet: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub entries: Vec<SweepEntry>,
+}
+
+impl SweepReport {
+    pub fn swapped(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Swapped { .. }))
+    }
+
+    pub fn unwrapped(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Unwrapped { .. }))
+    }
+
+    pub fn simulated(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Simulated { .. }))
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Rejected { .. }))
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Skipped { .. }))
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &SweepEntry> {
+        self.entries.iter().filter(|e| matches!(e.status, SweepStatus::Failed { .. }))
+    }
+
+    pub fn render(&self, converter: &CurrencyConverter) -> String {
+        let mut out = match self.dry_run {
+            true => format!("Sweep plan for {} (dry run, nothing sent)\n", self.wallet),
+            false => format!("Sweep of {}\n", self.wallet),
+        };
+        let mut sections = match self.dry_run {
+            true => vec![("Planned", self.simulated().collect::<Vec<_>>())],
+            false => vec![("Swapped", self.swapped().collect()), ("Unwrapped", self.unwrapped().collect())],
+        };
+        sections.extend([
+            ("Rejected", self.rejected().collect()),
+            ("Skipped", self.skipped().collect()),
+            ("Failed", self.failed().collect()),
+        ]);
+        for (title, entries) in sections {
+            if title == "Unwrapped" && entries.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("{} ({}):\n", title, entries.len()));
+            for entry in entries {
+                let detail = match &entry.status {
+                    SweepStatus::Swapped { tx_hash, out_value, gas_value } |
+                    SweepStatus::Unwrapped { tx_hash, out_value, gas_value } => {
+                        format!("got {} for {} gas, tx {}", converter.format(*out_value), converter.format(*gas_value), tx_hash)
+                    }
+                    SweepStatus::Simulated { out_value, gas_value } => format!(
+                        "would get {} for {} gas, net {}",
+                        converter.format(*out_value), converter.format(*gas_value), converter.format(out_value - gas_value),
+                    ),
+                    SweepStatus::Rejected { rejection } => rejection.to_string(),
+                    SweepStatus::Skipped { reason } => reason.clone(),
+                    SweepStatus::Failed { error } => error.clone(),
+                };
+                out.push_str(&format!(
+                    "  {:<10} {:<8} {:>24} {:>14}  {}\n",
+                    entry.chain, entry.token, entry.amount, converter.format(entry.value), detail,
+                ));
+            }
+        }
+        let swapped_value: Decimal = self.swapped().chain(self.unwrapped()).map(|e| match e.status {
+            SweepStatus::Swapped { out_value, .. } | SweepStatus::Unwrapped { out_value, .. } => out_value,
+            _ => Decimal::ZERO,
+        }).sum();
+        if self.dry_run {
+            let (out_value, gas_value) = self.simulated().fold((Decimal::ZERO, Decimal::ZERO), |acc, e| match e.status {
+                SweepStatus::Simulated { out_value, gas_value } => (acc.0 + out_value, acc.1 + gas_value),
+                _ => acc,
+            });
+            out.push_str(&format!(
+                "Expected output: {}, gas: {}, net: {}\n",
+                converter.format(out_value), converter.format(gas_value), converter.format(out_value - gas_value),
+            ));
+        } else {
+            out.push_str(&format!("Swapped to native: {}\n", converter.format(swapped_value)));
+        }
+        out
+    }
+}
+
+// One token of a sweep plan, amounts in token and native units so the file can be reviewed and edited
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub chain: String,
+    pub token: String,
+    pub address: Address,
+    pub decimals: u8,
+    pub amount: Decimal,
+    // Aggregator the output was quoted on
+    pub route: String,
+    // Quoted native output and the least output the execution accepts
+    pub expected_out: Decimal,
+    pub min_out: Decimal,
+    // USD values at planning time
+    pub value: Decimal,
+    pub gas_value: Decimal,
+}
+
+impl PlanEntry {
+    // Planned amount in token units. An edited amount with more fraction digits than the token has fails.
+    pub fn raw_amount(&self) -> Result<U256> {
+        let amount = self.amount.normalize();
+        if amount.is_sign_negative() || amount.scale() > self.decimals as u32 {
+            return Err(eyre::eyre!("PlanEntry:raw_amount {} is not an amount of {} with {} decimals", self.amount, self.token, self.decimals));
+        }
+        Ok(parse_units(&amount.to_string(), self.decimals)?.into())
+    }
+
+    pub fn sweep_entry(&self, status: SweepStatus) -> SweepEntry {
+        SweepEntry {
+            chain: self.chain.clone(),
+            token: self.token.clone(),
+            address: self.address,
+            amount: self.amount,
+            value: self.value,
+            status,
+        }
+    }
+}
+
+// Swaps to review before executing them. Removing an entry skips that token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepPlan {
+    pub wallet: String,
+    pub timestamp: u64,
+    // Timestamp of the scan the plan was made from
+    pub scan_timestamp: u64,
+    pub entries: Vec<PlanEntry>,
+    // Balances left out of the plan and why
+    #[serde(default)]
+    pub skipped: Vec<SweepEntry>,
+}
+
+impl SweepPlan {
+    // Reject a plan with any amount that can't be swapped before anything of it is sent
+    pub fn validate(&self) -> Result<()> {
+        for entry in self.entries.iter() {
+            entry.raw_amount().map_err(|e| eyre::eyre!("Sweep plan entry {} on {}: {}", entry.token, entry.chain, e))?;
+        }
+        Ok(())
+    }
+
+    // Entries grouped by chain, chains sorted by name
+    pub fn chains(&self) -> Vec<(&str, Vec<&PlanEntry>)> {
+        let mut chains: Vec<(&str, Vec<&PlanEntry>)> = vec![];
+        for entry in self.entries.iter() {
+            match chains.iter_mut().find(|(chain, _)| *chain == entry.chain) {
+                Some((_, entries)) => entries.push(entry),
+                None => chains.push((&entry.chain, vec![entry])),
+            }
+        }
+        chains.sort_by_key(|(chain, _)| *chain);
+        chains
+    }
+
+    pub fn render(&self, converter: &CurrencyConverter) -> String {
+        let mut out = format!("Sweep plan for {} from scan {}\n", self.wallet, self.scan_timestamp);
+        out.push_str(&format!(
+            "  {:<10} {:<8} {:>24} {:>14} {:<6} {:>24} {:>24} {:>12}\n",
+            "Chain", "Token", "Amount", "Value", "Route", "Expected out", "Min out", "Gas",
+        ));
+        for entry in self.entries.iter() {
+            out.push_str(&format!(
+                "  {:<10} {:<8} {:>24} {:>14} {:<6} {:>24} {:>24} {:>12}\n",
+                entry.chain, entry.token, entry.amount, converter.format(entry.value), entry.route,
+                entry.expected_out, entry.min_out, converter.format(entry.gas_value),
+            ));
+        }
+        for entry in self.skipped.iter() {
+            let reason = match &entry.status {
+                SweepStatus::Skipped { reason } => reason.clone(),
+                SweepStatus::Rejected { rejection } => rejection.to_string(),
+                _ => continue,
+            };
+            out.push_str(&format!("  skipped {} {} on {}: {}\n", entry.amount, entry.token, entry.chain, reason));
+        }
+        let value: Decimal = self.entries.iter().map(|e| e.value).sum();
+        let gas_value: Decimal = self.entries.iter().map(|e| e.gas_value).sum();
+        out.push_str(&format!(
+            "{} swaps, value {}, estimated gas {}\n",
+            self.entries.len(), converter.format(value), converter.format(gas_value),
+        ));
+        out
+    }
+}
+
+// Reason to skip a swap whose gas costs more than its output is worth
+pub fn gas_skip_reason(out_value: Decimal, gas_value: Decimal) -> Option<String> {
+    if gas_value >= out_value {
+        return Some(format!("gas {} USD exceeds output {} USD", gas_value.round_dp(4), out_value.round_dp(4)));
+    }
+    None
+}
+
+// Relative change from planned to actual in percent, 100 when nothing was planned
+pub fn drift_percent(planned: Decimal, actual: Decimal) -> Decimal {
+    if planned.is_zero() {
+        return if actual.is_zero() { Decimal::ZERO } else { Decimal::ONE_HUNDRED };
+    }
+    ((actual - planned) / planned * Decimal::ONE_HUNDRED).abs()
+}
+
+// Reason to refuse a planned swap when the on-chain balance no longer covers the planned amount.
+// Only the planned amount is swapped, so a reviewer may lower it below the balance.
+pub fn check_balance_drift(planned: Decimal, on_chain: Decimal) -> Option<String> {
+    if on_chain < planned {
+        return Some(format!("balance {} is below the planned {}", on_chain, planned));
+    }
+    None
+}
+
+// Reason to refuse a planned batch when its new quote is worse than planned
+pub fn check_quote_drift(expected_out: Decimal, min_out: Decimal, quoted_out: Decimal, tolerance: Decimal) -> Option<String> {
+    if quoted_out < min_out {
+        return Some(format!("quoted output {} is below the planned minimum {}", quoted_out, min_out));
+    }
+    if quoted_out < expected_out && drift_percent(expected_out, quoted_out) > tolerance {
+        return Some(format!("quoted output {} drifted more than {}% from the planned {}", quoted_out, tolerance, expected_out));
+    }
+    None
+}
+
+// Wrapped native token of a chain from the "tokens" section of chains.json. None when WNATIVE is
+// the ERC-20 view of the native balance itself, like CELO's GoldToken, which can't be unwrapped.
+pub fn wrapped_native(chain: &Value) -> Option<Address> {
+    let wnative = &chain["tokens"]["WNATIVE"];
+    if wnative["nativeErc20"].as_bool() == Some(true) {
+        return None;
+    }
+    wnative["address"].as_str().and_then(|a| a.parse().ok())
+}
+
+// Split sweep candidates into balances to swap and wrapped native balances to unwrap
+pub fn split_wrapped_native(selected: Vec<&Balance>, wrapped_native: Option<Address>) -> (Vec<&Balance>, Vec<&Balance>) {
+    selected.into_iter().partition(|b| Some(b.token_address) != wrapped_native)
+}
+
+// Slippage that keeps the on-chain minimum of a quote at or above a planned min_out, rounded
+// down to whole basis points
+pub fn planned_slippage_percent(quoted_out: Decimal, min_out: Decimal) -> f64 {
+    if quoted_out <= min_out || quoted_out.is_zero() {
+        return 0.0;
+    }
+    let bps = ((quoted_out - min_out) / quoted_out * Decimal::from(10_000)).floor();
+    bps.to_f64().unwrap_or_default() / 100.0
+}
+
+// Least output a quote settles for at a slippage
+pub fn slippage_min_out(quoted_out: Decimal, slippage_percent: f64) -> Decimal {
+    let bps = Decimal::from((slippage_percent * 100.0).round() as u32);
+    quoted_out * (Decimal::from(10_000) - bps) / Decimal::from(10_000)
+}
+
+// Split a chain's balances into those to sweep and skipped entries for the rest
+pub fn select_candidates<'a>(chain: &str, balances: &'a [Balance], config: &SweepConfig) -> (Vec<&'a Balance>, Vec<SweepEntry>) {
+    let native: Address = NATIVE_TOKEN_ADDRESS.parse().unwrap();
+    let mut selected = vec![];
+    let mut skipped = vec![];
+    for balance in balances.iter() {
+        if balance.token_address == native || balance.balance == U256::ZERO {
+            continue;
+        }
+        let reason = match balance.value() {
+            _ if balance.token_price.is_zero() => Some("no price".to_owned()),
+            Ok(value) if value < config.min_value => Some(format!("worth less than {} USD", config.min_value)),
+            Ok(value) if value > config.max_value => Some(format!("worth more than {} USD", config.max_value)),
+            Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        };
+        match reason {
+            Some(reason) => skipped.push(SweepEntry::new(chain, balance, SweepStatus::Skipped { reason })),
+            None => selected.push(balance),
+        }
+    }
+    (selected, skipped)
+}
+
+// Attribute a batch total to its inputs in proportion to their USD value, evenly when values are unknown
+pub fn split_by_input_value(total: Decimal, in_values: &[Decimal], inputs: usize) -> Vec<Decimal> {
+    let sum: Decimal = in_values.iter().sum();
+    if in_values.len() != inputs || sum.is_zero() {
+        return vec![total / Decimal::from(inputs.max(1)); inputs];
+    }
+    in_values.iter().map(|v| total * v / sum).collect()
+}
+
+#[test]
+fn test_split_by_input_value() {
+    let total = Decimal::new(9, 0);
+    assert_eq!(split_by_input_value(total, &[Decimal::ONE, Decimal::TWO], 2), vec![Decimal::new(3, 0), Decimal::new(6, 0)]);
+    assert_eq!(split_by_input_value(total, &[], 3), vec![Decimal::new(3, 0); 3]);
+}
+
+#[test]
+fn test_select_sweep_candidates() {
+    let balance = |address: &str, raw: u64, price: i64| {
+        let mut b = Balance::new(address.parse().unwrap(), "T".to_owned(), "T".to_owned(), 6, U256::from(raw));
+        b.set_token_price(Decimal::new(price, 2));
+        b
+    };
+    let balances = vec![
+        balance(NATIVE_TOKEN_ADDRESS, 1_000_000, 100),
+        balance("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", 10_000_000, 100),
+        balance("0xdAC17F958D2ee523a2206206994597C13D831ec7", 100_000, 100),
+        balance("0x6B175474E89094C44Da98b954EedeAC495271d0F", 1_000_000_000, 100),
+        balance("0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599", 1_000_000, 0),
+    ];
+    let (selected, skipped) = select_candidates("Ethereum", &balances, &SweepConfig::default());
+
+    assert_eq!(selected.len(), 1);
+    assert_eq!(selected[0].token_address, balances[1].token_address);
+    let reasons: Vec<SweepStatus> = skipped.into_iter().map(|e| e.status).collect();
+    assert_eq!(reasons, vec![
+        SweepStatus::Skipped { reason: "worth less than 0.5 USD".to_owned() },
+        SweepStatus::Skipped { reason: "worth more than 50 USD".to_owned() },
+        SweepStatus::Skipped { reason: "no price".to_owned() },
+    ]);
+}
+
+#[test]
+fn test_sweep_report() {
+    let usdc = {
+        let mut b = Balance::new("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(), "USD Coin".to_owned(), "USDC".to_owned(), 6, U256::from(10_000_000));
+        b.set_token_price(Decimal::ONE);
+        b
+    };
+    let report = SweepReport {
+        wallet: "0xbf17a4730fe4a1ea36cf536b8473cc25ba146f19".to_owned(),
+        timestamp: 0,
+        dry_run: false,
+        entries: vec![
+            SweepEntry::new("Base", &usdc, SweepStatus::Swapped { tx_hash: "0x01".to_owned(), out_value: Decimal::new(99, 1), gas_value: Decimal::new(1, 2) }),
+            SweepEntry::new("Ethereum", &usdc, SweepStatus::Skipped { reason: "gas costs more than the output".to_owned() }),
+            SweepEntry::new("Optimism", &usdc, SweepStatus::Rejected { rejection: SwapRejection::PriceImpact { impact: 7.5, limit: 5.0 } }),
+        ],
+    };
+    assert_eq!(report.swapped().count(), 1);
+    assert_eq!(report.rejected().count(), 1);
+    assert_eq!(report.failed().count(), 0);
+    let out = report.render(&CurrencyConverter::usd());
+    assert!(out.contains("Swapped (1):"));
+    assert!(out.ends_with("Swapped to native: 9.90 USD\n"));
+
+    assert!(out.contains("price impact 7.5% exceeds 5%"));
+
+    let json = serde_json::to_value(&report.entries[0]).unwrap();
+    assert_eq!(json["status"], "swapped");
+    assert_eq!(json["tx_hash"], "0x01");
+    let json = serde_json::to_value(&report.entries[2]).unwrap();
+    assert_eq!(json["status"], "rejected");
+    assert_eq!(json["rejection"]["rule"], "price_impact");
+}
+
+#[test]
+fn test_unwrap_wrapped_native() {
+    let chain_data: Value = serde_json::from_str(&std::fs::read_to_string("data/chains.json").unwrap()).unwrap();
+    let weth = wrapped_native(&chain_data["Base"]).unwrap();
+    assert_eq!(weth, "0x4200000000000000000000000000000000000006".parse::<Address>().unwrap());
+    // CELO's GoldToken is the native balance, it is neither scanned twice nor unwrapped
+    assert_eq!(wrapped_native(&chain_data["Celo"]), None);
+    let balance = |address: Address, symbol: &str| {
+        let mut b = Balance::new(address, symbol.to_owned(), symbol.to_owned(), 18, U256::from(2_000_000_000_000_000u64));
+        b.set_token_price(Decimal::new(2500, 0));
+        b
+    };
+    let balances = vec![balance(weth, "WETH"), balance("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".parse().unwrap(), "USDC")];
+    let (swaps, unwraps) = split_wrapped_native(balances.iter().collect(), Some(weth));
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].token_symbol, "USDC");
+    assert_eq!(unwraps.len(), 1);
+    assert_eq!(unwraps[0].token_address, weth);
+    assert_eq!(split_wrapped_native(balances.iter().collect(), None).0.len(), 2);
+
+    let report = SweepReport {
+        wallet: "0x01".to_owned(),
+        timestamp: 0,
+        dry_run: false,
+        entries: vec![SweepEntry::new("Base", &balances[0], SweepStatus::Unwrapped {
+            tx_hash: "0x02".to_owned(),
+            out_value: Decimal::new(5, 0),
+            gas_value: Decimal::new(1, 3),
+        })],
+    };
+    let out = report.render(&CurrencyConverter::usd());
+    assert!(out.contains("Swapped (0):\nUnwrapped (1):\n"));
+    assert!(out.ends_with("Swapped to native: 5.00 USD\n"));
+    assert_eq!(serde_json::to_value(&report.entries[0]).unwrap()["status"], "unwrapped");
+}
+
+#[test]
+fn test_sweep_plan() {
+    let usdc = {
+        let mut b = Balance::new("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(), "USD Coin".to_owned(), "USDC".to_owned(), 6, U256::from(10_000_000));
+        b.set_token_price(Decimal::ONE);
+        b
+    };
+    let report = SweepReport {
+        wallet: "0xbf17a4730fe4a1ea36cf536b8473cc25ba146f19".to_owned(),
+        timestamp: 0,
+        dry_run: true,
+        entries: vec![
+            SweepEntry::new("Base", &usdc, SweepStatus::Simulated { out_value: Decimal::new(99, 1), gas_value: Decimal::new(4, 1) }),
+            SweepEntry::new("Optimism", &usdc, SweepStatus::Simulated { out_value: Decimal::new(98, 1), gas_value: Decimal::new(3, 1) }),
+        ],
+    };
+    let out = report.render(&CurrencyConverter::usd());
+    assert!(out.starts_with("Sweep plan for"));
+    assert!(out.contains("Planned (2):"));
+    assert!(out.contains("net 9.50 USD"));
+    assert!(out.ends_with("Expected output: 19.70 USD, gas: 0.70 USD, net: 19.00 USD\n"));
+
+    // Reports saved before dry runs existed still load
+    let json = r#"{"wallet":"0x01","timestamp":0,"entries":[]}"#;
+    assert!(!serde_json::from_str::<SweepReport>(json).unwrap().dry_run);
+}
+
+#[test]
+fn test_plan_drift_checks() {
+    let d = |v: i64, scale: u32| Decimal::new(v, scale);
+    assert_eq!(drift_percent(d(100, 0), d(99, 0)), d(1, 0));
+    assert_eq!(check_balance_drift(d(10, 0), d(10, 0)), None);
+    assert_eq!(check_balance_drift(d(10, 0), d(1005, 2)), None);
+    assert_eq!(check_balance_drift(d(10, 0), d(9, 0)), Some("balance 9 is below the planned 10".to_owned()));
+    // A planned amount lowered in review still executes
+    assert_eq!(check_balance_drift(d(5, 0), d(12, 0)), None);
+
+    // The on-chain minimum never goes below the planned one
+    assert_eq!(planned_slippage_percent(d(100, 0), d(99, 0)), 1.0);
+    assert_eq!(planned_slippage_percent(d(1003, 1), d(99, 0)), 1.29);
+    assert!(slippage_min_out(d(1003, 1), 1.29) >= d(99, 0));
+    assert_eq!(planned_slippage_percent(d(98, 0), d(99, 0)), 0.0);
+
+    assert_eq!(check_quote_drift(d(100, 0), d(99, 0), d(101, 0), Decimal::ONE), None);
+    assert_eq!(check_quote_drift(d(100, 0), d(99, 0), d(995, 1), Decimal::ONE), None);
+    assert!(check_quote_drift(d(100, 0), d(99, 0), d(98, 0), Decimal::ONE).unwrap().contains("below the planned minimum 99"));
+    // An edited min_out below the tolerance still leaves the expected output check
+    assert!(check_quote_drift(d(100, 0), d(90, 0), d(95, 0), Decimal::ONE).unwrap().contains("drifted more than 1%"));
+}
+
+#[test]
+fn test_sweep_plan_file() {
+    let entry = |chain: &str, token: &str| PlanEntry {
+        chain: chain.to_owned(),
+        token: token.to_owned(),
+        address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap(),
+        decimals: 6,
+        amount: Decimal::new(10, 0),
+        route: "odos".to_owned(),
+        expected_out: Decimal::new(3, 3),
+        min_out: Decimal::new(297, 5),
+        value: Decimal::new(10, 0),
+        gas_value: Decimal::new(5, 2),
+    };
+    let plan = SweepPlan {
+        wallet: "0xbf17a4730fe4a1ea36cf536b8473cc25ba146f19".to_owned(),
+        timestamp: 2,
+        scan_timestamp: 1,
+        entries: vec![entry("Optimism", "USDC"), entry("Base", "USDC"), entry("Optimism", "DAI")],
+        skipped: vec![],
+    };
+    let chains = plan.chains();
+    assert_eq!(chains.iter().map(|(c, e)| (*c, e.len())).collect::<Vec<_>>(), vec![("Base", 1), ("Optimism", 2)]);
+    assert!(plan.render(&CurrencyConverter::usd()).ends_with("3 swaps, value 30.00 USD, estimated gas 0.15 USD\n"));
+
+    // Amounts stay human readable in the plan file
+    let json = serde_json::to_value(&plan).unwrap();
+    assert_eq!(json["entries"][0]["amount"], "10");
+    assert_eq!(json["entries"][0]["min_out"], "0.00297");
+    let parsed: SweepPlan = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.entries, plan.entries);
+
+    // Edited amounts must fit the token's decimals
+    assert!(plan.validate().is_ok());
+    assert_eq!(PlanEntry { amount: Decimal::new(1_500_000, 6), ..entry("Base", "USDC") }.raw_amount().unwrap(), U256::from(1_500_000));
+    let edited = PlanEntry { amount: Decimal::new(1_2345_678, 7), ..entry("Base", "USDC") };
+    assert!(edited.raw_amount().is_err());
+    assert!(SweepPlan { entries: vec![entry("Base", "DAI"), edited], ..plan.clone() }.validate().is_err());
+    assert!(PlanEntry { amount: Decimal::new(-1, 0), ..entry("Base", "USDC") }.raw_amount().is_err());
+}
@@ -1,17 +1,25 @@
+use alloy::primitives::U256;
 use log::LevelFilter;
 use fern::{Dispatch, colors::{Color, ColoredLevelConfig}};
 use eyre::Result;
+use rust_decimal::{prelude::FromPrimitive, Decimal};
 use crate::constants::const_types::*;
 
 
+// Level colors of the logger, reused by the terminal report
+pub static TRACE_COLOR: Color = Color::Cyan;
+pub static DEBUG_COLOR: Color = Color::Magenta;
+pub static INFO_COLOR: Color = Color::Green;
+pub static WARN_COLOR: Color = Color::Red;
+pub static ERROR_COLOR: Color = Color::BrightRed;
+
 pub fn setup_logger() -> Result<()> {
     let colors = ColoredLevelConfig {
-        trace: Color::Cyan,
-        debug: Color::Magenta,
-        info: Color::Green,
-        warn: Color::Red,
-        error: Color::BrightRed,
-        ..ColoredLevelConfig::new()
+        trace: TRACE_COLOR,
+        debug: DEBUG_COLOR,
+        info: INFO_COLOR,
+        warn: WARN_COLOR,
+        error: ERROR_COLOR,
     };
 
     Dispatch::new()
@@ -29,4 +37,45 @@ pub fn setup_logger() -> Result<()> {
         .apply()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Wrap text in ANSI color codes when enabled
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if enabled {
+        format!("\x1B[{}m{}\x1B[0m", color.to_fg_str(), text)
+    } else {
+        text.to_owned()
+    }
+}
+
+// Convert a raw token amount to a decimal. Amounts beyond 96 bits lose their lowest digits instead of failing.
+pub fn u256_to_decimal(amount: U256, decimals: u8) -> Result<Decimal> {
+    let max = U256::from(u128::MAX >> 32);
+    let mut amount = amount;
+    let mut scale = decimals as u32;
+    while (amount > max || scale > 28) && scale > 0 {
+        amount /= U256::from(10);
+        scale -= 1;
+    }
+    if amount > max {
+        return Err(eyre::eyre!("Amount {} is too large for a decimal", amount));
+    }
+    Ok(Decimal::from_i128_with_scale(amount.to::<u128>() as i128, scale).normalize())
+}
+
+// Prices arrive from APIs as JSON floats
+pub fn f64_to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|d| d.normalize())
+}
+
+#[test]
+fn test_u256_to_decimal() {
+    let amount = U256::from(1_234_567_890_123_456_789_u128);
+    assert_eq!(u256_to_decimal(amount, 18).unwrap().to_string(), "1.234567890123456789");
+    assert_eq!(u256_to_decimal(U256::from(5), 0).unwrap().to_string(), "5");
+
+    // 10^33 wei does not fit 96 bits, the fractional digits are dropped instead
+    let large = U256::from(10).pow(U256::from(33));
+    assert_eq!(u256_to_decimal(large, 18).unwrap().to_string(), "1000000000000000");
+    assert!(u256_to_decimal(U256::MAX, 0).is_err());
+}
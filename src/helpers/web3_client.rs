@@ -1,28 +1,36 @@
 use std::{fs, sync::Arc, time};
 
 use alloy::{
+    consensus::{SignableTransaction, TxEip1559},
     contract::Interface,
     dyn_abi::DynSolValue,
     json_abi::JsonAbi,
     network::{Ethereum, EthereumWallet, TransactionBuilder},
-    primitives::{Address, Bytes, U256},
+    primitives::{Address, Bytes, TxKind, B256, U256, U160},
     providers::{
         fillers::{FillProvider, RecommendedFiller},
         Provider,
         ProviderBuilder,
         RootProvider
     },
-    rpc::types::{TransactionReceipt, TransactionRequest},
-    signers::local::PrivateKeySigner,
+    rpc::types::{Filter, TransactionReceipt, TransactionRequest},
+    signers::{local::PrivateKeySigner, Signer},
     sol,
+    sol_types::{Eip712Domain, SolCall, SolEvent, SolStruct},
     transports::http::Http
 };
 use eyre::Result;
+use futures::{stream, StreamExt};
 use log::warn;
 use reqwest::{Client, Url};
+use rust_decimal::Decimal;
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 
+use crate::constants::const_types::NATIVE_TOKEN_ADDRESS;
 use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::price_oracle::PriceConfidence;
+use crate::helpers::utils::u256_to_decimal;
 
 sol!(
     #[allow(missing_docs)]
@@ -38,6 +46,83 @@ sol!(
     "src/utils/contract_abis/Multicall2.json"
 );
 
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UniswapV2Router,
+    "src/utils/contract_abis/UniswapV2Router.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UniswapV3Quoter,
+    "src/utils/contract_abis/UniswapV3Quoter.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UniswapV3SwapRouter,
+    "src/utils/contract_abis/UniswapV3SwapRouter.json"
+);
+
+sol!(
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    ERC20Permit,
+    "src/utils/contract_abis/ERC20Permit.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    Permit2,
+    "src/utils/contract_abis/Permit2.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    StargatePool,
+    "src/utils/contract_abis/StargatePool.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    WrappedNative,
+    "src/utils/contract_abis/WrappedNative.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    GasPriceOracle,
+    "src/utils/contract_abis/GasPriceOracle.json"
+);
+
+sol! {
+    // EIP-2612 permit message
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+// Same address on every chain
+pub static PERMIT2_ADDRESS: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+// Seconds Permit2 allowances and EIP-2612 permits stay valid
+static PERMIT_VALIDITY: u64 = 30 * 60;
+// Block range of one eth_getLogs request, public RPCs reject larger ones
+static LOG_CHUNK_BLOCKS: u64 = 10_000;
+// eth_getLogs requests in flight at once, and the smaller ranges a failed one is retried in
+static LOG_CONCURRENT_REQUESTS: usize = 4;
+static LOG_RETRY_SPLIT: u64 = 10;
+
 type MyFiller = FillProvider<RecommendedFiller, RootProvider<Http<Client>>, Http<Client>, Ethereum>;
 
 #[derive(Clone)]
@@ -69,6 +154,81 @@ impl Network {
             multicall,
         })
     }
+
+    // Build a network from its entry in chains.json
+    pub fn from_chain_data(chain_name: &str, chain: &Value) -> Result<Self> {
+        let id = match chain["id"].as_u64() {
+            Some(id) => id as u32,
+            None => return Err(eyre::eyre!("Network:from_chain_data Missing chain id for {}", chain_name)),
+        };
+        let rpc_url = chain["rpc"].as_array()
+            .map(|rpcs| rpcs.iter().filter_map(|rpc| rpc.as_str().and_then(|r| Url::parse(r).ok())).collect())
+            .unwrap_or_default();
+        Network::new(
+            id,
+            chain_name.to_owned(),
+            rpc_url,
+            chain["explorer"].as_str().unwrap_or_default().to_owned(),
+            chain["multicall"].as_str().and_then(|m| m.parse::<Address>().ok()).unwrap_or(Address::ZERO),
+        )
+    }
+}
+
+// A transaction built and checked against the chain but not broadcast
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimulatedTx {
+    pub to: Option<Address>,
+    pub gas: u128,
+    pub gas_price: u128,
+    // eth_call result, None when the call depends on an earlier transaction that was not sent
+    pub output: Option<Bytes>,
+}
+
+impl SimulatedTx {
+    // Gas cost in wei
+    pub fn gas_cost(&self) -> U256 {
+        U256::from(self.gas) * U256::from(self.gas_price)
+    }
+}
+
+// How a spender gets the allowance it needs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStrategy {
+    // Approve the amount spent
+    #[default]
+    Exact,
+    // Approve once for good
+    Unlimited,
+    // Approve the Permit2 contract once and give the spender an expiring allowance through it
+    Permit2,
+    // Set the allowance with a signed EIP-2612 permit, exact approval for tokens without permits
+    Permit,
+}
+
+// Native tokens are spent as value and never approved
+pub fn is_native_token(token_address: &Address) -> bool {
+    *token_address == Address::ZERO ||
+        *token_address == NATIVE_TOKEN_ADDRESS.parse::<Address>().unwrap() ||
+        *token_address == "0x0000000000000000000000000000000000001010".parse::<Address>().unwrap()
+}
+
+// Inclusive block ranges of at most size blocks covering from_block to to_block
+pub fn block_ranges(from_block: u64, to_block: u64, size: u64) -> Vec<(u64, u64)> {
+    let mut ranges = vec![];
+    let mut start = from_block;
+    while start <= to_block {
+        let end = start.saturating_add(size.max(1) - 1).min(to_block);
+        ranges.push((start, end));
+        start = end + 1;
+    }
+    ranges
+}
+
+#[derive(Debug)]
+pub enum TxOutcome {
+    Sent(Box<TransactionReceipt>),
+    Simulated(SimulatedTx),
 }
 
 pub struct GasMultiplier {
@@ -85,13 +245,17 @@ impl GasMultiplier {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Balance {
     pub token_address: Address,
     pub balance: U256,
     pub token_name: String,
     pub token_symbol: String,
-    pub token_price: f64,
+    pub token_price: Decimal,
+    #[serde(default)]
+    pub price_source: Option<String>,
+    #[serde(default)]
+    pub price_confidence: Option<PriceConfidence>,
     pub decimals: u8,
 }
 
@@ -109,13 +273,33 @@ impl Balance {
             token_symbol,
             decimals,
             balance,
-            token_price: 0.0,
+            token_price: Decimal::ZERO,
+            price_source: None,
+            price_confidence: None,
         }
     }
 
-    pub fn set_token_price(&mut self, price: f64) {
+    pub fn set_token_price(&mut self, price: Decimal) {
         self.token_price = price;
     }
+
+    // Balance in whole tokens
+    pub fn amount(&self) -> Result<Decimal> {
+        u256_to_decimal(self.balance, self.decimals)
+    }
+
+    // USD value of the balance
+    pub fn value(&self) -> Result<Decimal> {
+        match self.amount()?.checked_mul(self.token_price) {
+            Some(v) => Ok(v),
+            None => Err(eyre::eyre!("Value of {} {} overflows", self.balance, self.token_symbol)),
+        }
+    }
+
+    pub fn set_price_source(&mut self, source: &str, confidence: PriceConfidence) {
+        self.price_source = Some(source.to_owned());
+        self.price_confidence = Some(confidence);
+    }
 }
 
 pub struct Web3Client {
@@ -124,6 +308,8 @@ pub struct Web3Client {
     multicall_interface: Interface,
     erc20_interface: Interface,
     provider: Arc<MyFiller>,
+    // Simulate transactions instead of broadcasting them
+    dry_run: bool,
 }
 
 impl Web3Client {
@@ -157,10 +343,23 @@ impl Web3Client {
                 multicall_interface,
                 erc20_interface,
                 provider,
+                dry_run: false,
             }
         )
     }
 
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
     // Sleep function
     async fn sleep(duration: time::Duration) {
         tokio::time::sleep(duration).await;
@@ -174,20 +373,138 @@ impl Web3Client {
             .on_http(rpc_url));
     }
 
+    pub async fn allowance(&self, token_address: Address, spender: Address) -> Result<U256> {
+        self.allowance_of(self.signer.address(), token_address, spender).await
+    }
+
+    pub async fn allowance_of(&self, owner: Address, token_address: Address, spender: Address) -> Result<U256> {
+        let erc20 = ERC20::new(token_address, self.provider.clone());
+        let ERC20::allowanceReturn { _0 } = erc20.allowance(owner, spender).call().await?;
+        Ok(_0)
+    }
+
+    // Allowances of (token, spender) pairs through multicall, None where the call failed
+    pub async fn allowances_of(&self, owner: Address, pairs: &[(Address, Address)]) -> Result<Vec<Option<U256>>> {
+        let mut calls = vec![];
+        for (token_address, spender) in pairs.iter() {
+            let call_data = Bytes::copy_from_slice(&self.erc20_interface.encode_input("allowance", &[
+                DynSolValue::Address(owner),
+                DynSolValue::Address(*spender),
+            ])?);
+            calls.push(Multicall::Call {
+                target: *token_address,
+                callData: call_data,
+            });
+        }
+        Ok(self.try_aggregate(calls).await?
+            .into_iter()
+            .map(|data| data.and_then(|d| U256::try_from_be_slice(&d[..d.len().min(32)])))
+            .collect())
+    }
+
+    // Allowances the owner gave spenders inside Permit2 for (token, spender) pairs through multicall,
+    // zero once expired. None where the call failed.
+    pub async fn permit2_allowances_of(&self, owner: Address, pairs: &[(Address, Address)]) -> Result<Vec<Option<U256>>> {
+        let permit2: Address = PERMIT2_ADDRESS.parse()?;
+        let calls = pairs.iter().map(|(token_address, spender)| Multicall::Call {
+            target: permit2,
+            callData: Permit2::allowanceCall { user: owner, token: *token_address, spender: *spender }.abi_encode().into(),
+        }).collect();
+        let now = chrono::Utc::now().timestamp() as u64;
+        Ok(self.try_aggregate(calls).await?
+            .into_iter()
+            .map(|data| {
+                let allowed = Permit2::allowanceCall::abi_decode_returns(&data?, true).ok()?;
+                Some(if allowed.expiration <= now { U256::ZERO } else { U256::from(allowed.amount) })
+            })
+            .collect())
+    }
+
+    pub async fn permit2_allowance_of(&self, owner: Address, token_address: Address, spender: Address) -> Result<U256> {
+        let allowed = self.permit2_allowances_of(owner, &[(token_address, spender)]).await?;
+        match allowed.into_iter().next().flatten() {
+            Some(amount) => Ok(amount),
+            None => Err(eyre::eyre!("Web3Client:permit2_allowance_of Could not read the Permit2 allowance of {} for {}", token_address, spender)),
+        }
+    }
+
+    // Zero the spender's Permit2 allowance. An expiration of 0 expires it at once.
+    pub async fn revoke_permit2(&self, token_address: Address, spender: Address) -> Result<TxOutcome> {
+        let permit2_address: Address = PERMIT2_ADDRESS.parse()?;
+        let call_data = Permit2::approveCall { token: token_address, spender, amount: U256::ZERO, expiration: 0 }.abi_encode();
+        let tx = TransactionRequest::default()
+            .with_from(self.signer.address())
+            .with_to(permit2_address)
+            .with_input(call_data);
+        self.send_tx(tx, None).await
+    }
+
+    // (token, spender) pairs of the owner's Approval events between two blocks, oldest first.
+    // A range that still fails in smaller pieces is left out with a warning, only when every
+    // range fails is it an error.
+    pub async fn approval_logs(&self, owner: Address, from_block: u64, to_block: u64) -> Result<Vec<(Address, Address)>> {
+        let chunks = block_ranges(from_block, to_block, LOG_CHUNK_BLOCKS);
+        let chunk_count = chunks.len();
+        let results: Vec<_> = stream::iter(chunks)
+            .map(|(start, end)| self.approval_logs_chunk(owner, start, end))
+            .buffered(LOG_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+
+        let mut pairs = vec![];
+        let mut missing = vec![];
+        for (chunk_pairs, chunk_missing) in results {
+            pairs.extend(chunk_pairs);
+            missing.extend(chunk_missing);
+        }
+        if !missing.is_empty() {
+            let ranges: Vec<String> = missing.iter().map(|(start, end)| format!("{}-{}", start, end)).collect();
+            if pairs.is_empty() && missing.len() >= chunk_count {
+                return Err(eyre::eyre!("Web3Client:approval_logs No Approval logs could be read on {}", self.network.chain_name));
+            }
+            warn!("Approval logs on {} are missing blocks {}", self.network.chain_name, ranges.join(", "));
+        }
+        Ok(pairs)
+    }
+
+    // Logs of one range, retried in smaller ranges when it fails. Returns the ranges still missing.
+    async fn approval_logs_chunk(&self, owner: Address, start: u64, end: u64) -> (Vec<(Address, Address)>, Vec<(u64, u64)>) {
+        if let Ok(pairs) = self.approval_logs_range(owner, start, end).await {
+            return (pairs, vec![]);
+        }
+        let mut pairs = vec![];
+        let mut missing = vec![];
+        let size = ((end - start + 1) / LOG_RETRY_SPLIT).max(1);
+        for (start, end) in block_ranges(start, end, size) {
+            match self.approval_logs_range(owner, start, end).await {
+                Ok(p) => pairs.extend(p),
+                Err(_) => missing.push((start, end)),
+            }
+        }
+        (pairs, missing)
+    }
+
+    async fn approval_logs_range(&self, owner: Address, start: u64, end: u64) -> Result<Vec<(Address, Address)>> {
+        let filter = Filter::new()
+            .from_block(start)
+            .to_block(end)
+            .event_signature(ERC20::Approval::SIGNATURE_HASH)
+            .topic1(owner.into_word());
+        Ok(self.provider.get_logs(&filter).await?
+            .into_iter()
+            .filter_map(|log| log.topics().get(2).map(|spender| (log.address(), Address::from_word(*spender))))
+            .collect())
+    }
+
     pub async fn approve(
         &self,
         token_address: Address,
         to: Address,
         amount: U256,
         _min_allowance: Option<U256>,
-    ) -> Result<Option<TransactionReceipt>> {
-        let erc20 = ERC20::new(token_address, self.provider.clone());
-
+    ) -> Result<Option<TxOutcome>> {
         if let Some(min_allowance) = _min_allowance {
-            let ERC20::allowanceReturn { _0 } = erc20.allowance(self.signer.address(), to).call().await?;
-            // Print allowance data
-
-            if _0 >= min_allowance {
+            if self.allowance(token_address, to).await? >= min_allowance {
                 return Ok(None);
             }
         }
@@ -201,30 +518,211 @@ impl Web3Client {
             .with_to(token_address)
             .with_input(call_data);
 
-        let tx_hash = self.send_tx(tx, None).await?;
+        let outcome = self.send_tx(tx, None).await?;
         
-        Ok(Some(tx_hash))
+        Ok(Some(outcome))
     }
 
+    // Send amount of an ERC-20 token to an address, native balances are moved with plain transfers
+    pub async fn transfer_token(&self, token_address: Address, to: Address, amount: U256) -> Result<TxOutcome> {
+        if is_native_token(&token_address) {
+            return Err(eyre::eyre!("Web3Client:transfer_token {} is not an ERC-20 token", token_address));
+        }
+        let erc20 = ERC20::new(token_address, self.provider.clone());
+        let call_data = erc20.transfer(to, amount).calldata().clone();
+        let tx = TransactionRequest::default()
+            .with_from(self.signer.address())
+            .with_to(token_address)
+            .with_input(call_data);
+        self.send_tx(tx, None).await
+    }
+
+    // Turn amount of a wrapped native token like WETH back into native with its withdraw
+    pub async fn unwrap_native(&self, wrapper: Address, amount: U256) -> Result<TxOutcome> {
+        let wrapped = WrappedNative::new(wrapper, self.provider.clone());
+        let call_data = wrapped.withdraw(amount).calldata().clone();
+        let tx = TransactionRequest::default()
+            .with_from(self.signer.address())
+            .with_to(wrapper)
+            .with_input(call_data);
+        self.send_tx(tx, None).await
+    }
+
+    // Give the spender an allowance of at least amount with the given strategy. Native tokens are
+    // skipped. Once approvals were sent the allowance is read back and has to cover the amount.
+    pub async fn approve_with_strategy(
+        &self,
+        token_address: Address,
+        spender: Address,
+        amount: U256,
+        strategy: ApprovalStrategy,
+    ) -> Result<Vec<TxOutcome>> {
+        if is_native_token(&token_address) {
+            return Ok(vec![]);
+        }
+        if strategy == ApprovalStrategy::Permit2 {
+            return self.approve_permit2(token_address, spender, amount).await;
+        }
+        if self.allowance(token_address, spender).await? >= amount {
+            return Ok(vec![]);
+        }
+
+        let outcome = match strategy {
+            ApprovalStrategy::Unlimited => self.approve(token_address, spender, U256::MAX, None).await?,
+            ApprovalStrategy::Permit => match self.permit(token_address, spender, amount).await? {
+                Some(outcome) => Some(outcome),
+                None => {
+                    warn!("Token {} has no EIP-2612 permit, approving the exact amount", token_address);
+                    self.approve(token_address, spender, amount, None).await?
+                }
+            },
+            _ => self.approve(token_address, spender, amount, None).await?,
+        };
+        let outcomes: Vec<TxOutcome> = outcome.into_iter().collect();
+        if Self::check_sent(&outcomes)? && self.allowance(token_address, spender).await? < amount {
+            return Err(eyre::eyre!("Web3Client:approve Allowance of {} for {} is still below {}", token_address, spender, amount));
+        }
+        Ok(outcomes)
+    }
+
+    // Approve Permit2 for good, then give the spender an expiring Permit2 allowance
+    async fn approve_permit2(&self, token_address: Address, spender: Address, amount: U256) -> Result<Vec<TxOutcome>> {
+        let permit2_address: Address = PERMIT2_ADDRESS.parse()?;
+        let mut outcomes = vec![];
+        if self.allowance(token_address, permit2_address).await? < amount {
+            outcomes.extend(self.approve(token_address, permit2_address, U256::MAX, None).await?);
+        }
+
+        let permit2 = Permit2::new(permit2_address, self.provider.clone());
+        let owner = self.signer.address();
+        let allowed = permit2.allowance(owner, token_address, spender).call().await?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        if U256::from(allowed.amount) < amount || allowed.expiration <= now {
+            let call_data = permit2.approve(
+                token_address,
+                spender,
+                amount.min(U256::from(U160::MAX)),
+                now + PERMIT_VALIDITY,
+            ).calldata().clone();
+            let tx = TransactionRequest::default()
+                .with_from(owner)
+                .with_to(permit2_address)
+                .with_input(call_data);
+            outcomes.push(self.send_tx(tx, None).await?);
+        }
+
+        if Self::check_sent(&outcomes)? {
+            let allowed = permit2.allowance(owner, token_address, spender).call().await?;
+            if self.allowance(token_address, permit2_address).await? < amount || U256::from(allowed.amount) < amount {
+                return Err(eyre::eyre!("Web3Client:approve Permit2 allowance of {} for {} is still below {}", token_address, spender, amount));
+            }
+        }
+        Ok(outcomes)
+    }
+
+    // Sign an EIP-2612 permit and submit it, None when the token doesn't support permits.
+    // Aggregator routers take no permit data, so the signer submits the permit itself.
+    async fn permit(&self, token_address: Address, spender: Address, amount: U256) -> Result<Option<TxOutcome>> {
+        let token = ERC20Permit::new(token_address, self.provider.clone());
+        let owner = self.signer.address();
+        let (separator, nonce, name) = match (
+            token.DOMAIN_SEPARATOR().call().await,
+            token.nonces(owner).call().await,
+            token.name().call().await,
+        ) {
+            (Ok(separator), Ok(nonce), Ok(name)) => (separator._0, nonce._0, name._0),
+            _ => return Ok(None),
+        };
+        let domain = match Self::permit_domain(&name, self.network.id, token_address, separator) {
+            Some(domain) => domain,
+            None => return Ok(None),
+        };
+
+        let deadline = U256::from(chrono::Utc::now().timestamp() as u64 + PERMIT_VALIDITY);
+        let permit = Permit { owner, spender, value: amount, nonce, deadline };
+        let signature = self.signer.sign_hash(&permit.eip712_signing_hash(&domain)).await?;
+        let call_data = token.permit(
+            owner,
+            spender,
+            amount,
+            deadline,
+            27 + signature.v().y_parity_byte(),
+            B256::from(signature.r()),
+            B256::from(signature.s()),
+        ).calldata().clone();
+        let tx = TransactionRequest::default()
+            .with_from(owner)
+            .with_to(token_address)
+            .with_input(call_data);
+        Ok(Some(self.send_tx(tx, None).await?))
+    }
+
+    // Tokens rarely expose their domain version, so it is matched against the on-chain separator
+    fn permit_domain(name: &str, chain_id: u32, token_address: Address, separator: B256) -> Option<Eip712Domain> {
+        ["1", "2"].into_iter()
+            .map(|version| Eip712Domain::new(
+                Some(name.to_owned().into()),
+                Some(version.into()),
+                Some(U256::from(chain_id)),
+                Some(token_address),
+                None,
+            ))
+            .find(|domain| domain.separator() == separator)
+    }
+
+    // Whether any of the transactions was sent, failing when one of them reverted
+    fn check_sent(outcomes: &[TxOutcome]) -> Result<bool> {
+        let mut sent = false;
+        for outcome in outcomes {
+            if let TxOutcome::Sent(receipt) = outcome {
+                if !receipt.status() {
+                    return Err(eyre::eyre!("Web3Client:approve Approval {} reverted", receipt.transaction_hash));
+                }
+                sent = true;
+            }
+        }
+        Ok(sent)
+    }
+
+    // Broadcasts the transaction, or only simulates it in dry-run mode
     pub async fn send_tx(
         &self,
         mut tx_body: TransactionRequest,
         _gas_multipliers: Option<GasMultiplier>,
-    ) -> Result<TransactionReceipt> {
-        if let Some(gas_multipliers) = _gas_multipliers {
+    ) -> Result<TxOutcome> {
+        if let Some(gas_multipliers) = &_gas_multipliers {
             // let gas_limit = self.estimate_tx_gas(&tx_body, Some(gas_multipliers.limit)).await?;
-            let gas_price = self.get_gas_price(Some(gas_multipliers.price)).await?;
-            tx_body = tx_body.max_fee_per_gas(gas_price).max_priority_fee_per_gas(gas_price);
+            let (max_fee, priority_fee) = self.get_fees(Some(gas_multipliers.price)).await?;
+            tx_body = tx_body.max_fee_per_gas(max_fee).max_priority_fee_per_gas(priority_fee);
+        }
+
+        if self.dry_run {
+            let gas_multiplier = _gas_multipliers.map(|m| m.price);
+            return Ok(TxOutcome::Simulated(self.simulate_tx(&tx_body, gas_multiplier).await?));
         }
 
         let wallet = EthereumWallet::from(self.signer.clone());
         let tx_envelope = tx_body.build(&wallet).await?;
 
         let tx_receipt = self.provider.send_tx_envelope(tx_envelope).await?.get_receipt().await?;
-        Ok(tx_receipt)          
+        Ok(TxOutcome::Sent(Box::new(tx_receipt)))
     }
 
-    async fn estimate_tx_gas(
+    // Estimate gas and eth_call the transaction, a revert comes back as an error
+    pub async fn simulate_tx(&self, tx_body: &TransactionRequest, gas_price_multiplier: Option<f32>) -> Result<SimulatedTx> {
+        let tx_body = tx_body.clone().with_from(self.signer.address());
+        let gas = self.provider.estimate_gas(&tx_body).await?;
+        let output = self.provider.call(&tx_body).await?;
+        let gas_price = self.get_gas_price(gas_price_multiplier).await?;
+        Ok(SimulatedTx {
+            to: tx_body.to.and_then(|to| to.to().copied()),
+            gas,
+            gas_price,
+            output: Some(output),
+        })
+    }
+
+    pub async fn estimate_tx_gas(
         &self,
         tx_body: &TransactionRequest,
         _multiplier: Option<f32>,
@@ -234,7 +732,7 @@ impl Web3Client {
         Ok((gas_estimate as f32 * multiplier) as u128)
     }
 
-    async fn get_gas_price(
+    pub async fn get_gas_price(
         &self,
         _multiplier: Option<f32>,
     ) -> Result<u128> {
@@ -243,6 +741,42 @@ impl Web3Client {
         Ok((gas_price as f32 * multiplier) as u128)
     }
 
+    // EIP-1559 max fee and priority fee. The max fee is the gas price with the multiplier, the
+    // tip comes from eth_maxPriorityFeePerGas so only base fee plus tip is paid out of it.
+    // Chains without the method tip the whole max fee, as a legacy gas price would.
+    pub async fn get_fees(&self, multiplier: Option<f32>) -> Result<(u128, u128)> {
+        let max_fee = self.get_gas_price(multiplier).await?;
+        let priority_fee = match self.provider.get_max_priority_fee_per_gas().await {
+            Ok(priority_fee) => priority_fee.min(max_fee),
+            Err(e) => {
+                warn!("No priority fee estimate on {}, tipping the max fee: {:?}", self.network.chain_name, e);
+                max_fee
+            }
+        };
+        Ok((max_fee, priority_fee))
+    }
+
+    // L1 data fee an OP Stack or Scroll rollup charges on top of the gas of a transaction, from
+    // the chain's gas price oracle. The oracle prices the unsigned transaction, so it is encoded
+    // with the fields the transaction will be sent with.
+    pub async fn l1_data_fee(&self, oracle: Address, tx_body: &TransactionRequest) -> Result<U256> {
+        let nonce = self.provider.get_transaction_count(self.signer.address()).await?;
+        let tx = TxEip1559 {
+            chain_id: self.network.id.into(),
+            nonce,
+            gas_limit: tx_body.gas.unwrap_or_default(),
+            max_fee_per_gas: tx_body.max_fee_per_gas.unwrap_or_default(),
+            max_priority_fee_per_gas: tx_body.max_priority_fee_per_gas.unwrap_or_default(),
+            to: tx_body.to.unwrap_or(TxKind::Create),
+            value: tx_body.value.unwrap_or_default(),
+            access_list: Default::default(),
+            input: tx_body.input.input().cloned().unwrap_or_default(),
+        };
+        let oracle = GasPriceOracle::new(oracle, self.provider.clone());
+        let GasPriceOracle::getL1FeeReturn { _0: fee } = oracle.getL1Fee(tx.encoded_for_signing().into()).call().await?;
+        Ok(fee)
+    }
+
     pub async fn get_user_balance(&self, wallet_address: Address, token_address: Option<String>) -> Result<U256> {
         if let Some(token) = token_address {
             let erc20 = ERC20::new(token.parse()?, self.provider.clone());
@@ -253,7 +787,38 @@ impl Web3Client {
         }
     }
 
-    pub async fn call_balance(&mut self, wallet_address: Address, tokens: Vec<TokenData>) -> Result<Vec<Balance>> {
+    pub async fn get_block_number(&self) -> Result<u64> {
+        Ok(self.provider.get_block_number().await?)
+    }
+
+    pub async fn get_amounts_out(&self, router: Address, amount_in: U256, path: Vec<Address>) -> Result<Vec<U256>> {
+        let router = UniswapV2Router::new(router, self.provider.clone());
+        let UniswapV2Router::getAmountsOutReturn { amounts } = router.getAmountsOut(amount_in, path).call().await?;
+        Ok(amounts)
+    }
+
+    // eth_call with calldata encoded by the caller
+    pub async fn call(&self, to: Address, data: Bytes) -> Result<Bytes> {
+        let tx = TransactionRequest::default().with_to(to).with_input(data);
+        Ok(self.provider.call(&tx).await?)
+    }
+
+    // Run raw calls through multicall in batches, failed calls come back as None
+    pub async fn try_aggregate(&self, calls: Vec<Multicall::Call>) -> Result<Vec<Option<Bytes>>> {
+        let multicall = Multicall::new(self.network.multicall, self.provider.clone());
+        let batch_size = 500;
+        let mut results = vec![];
+        for batch in calls.chunks(batch_size) {
+            let Multicall::tryAggregateReturn { returnData } = multicall.tryAggregate(false, batch.to_vec()).call().await?;
+            results.extend(returnData.into_iter().map(|r| if r.success { Some(r.returnData) } else { None }));
+        }
+        Ok(results)
+    }
+
+    // Non-zero balances of the tokens and the block they were read at. Every batch is pinned to
+    // that block, so a scan is one consistent snapshot.
+    pub async fn call_balance(&mut self, wallet_address: Address, tokens: Vec<TokenData>) -> Result<(Vec<Balance>, u64)> {
+        let block_number = self.get_block_number().await?;
         let mut multicall = Multicall::new(self.network.multicall, self.provider.clone());
         let max_retries = 2;
         let mut balances: Vec<Balance> = vec![];
@@ -286,7 +851,7 @@ impl Web3Client {
                 // Aggregate the calls
                 let mut retry_count = 0;
                 while retry_count < max_retries {
-                    let call_result = multicall.tryAggregate(false, calls.clone()).call().await;
+                    let call_result = multicall.tryAggregate(false, calls.clone()).block(block_number.into()).call().await;
                     let Multicall::tryAggregateReturn { returnData } = match call_result {
                         Ok(data) => data,
                         Err(_) => {
@@ -336,7 +901,7 @@ impl Web3Client {
                 Self::sleep(time::Duration::from_millis(200)).await;
             }
         }
-        Ok(balances)
+        Ok((balances, block_number))
     }
 }
 
@@ -394,4 +959,58 @@ async fn test_approve() {
         None,
     ).await.unwrap();
     println!("{:?}", result);
-}
\ No newline at end of file
+}
+#[test]
+fn test_balance_value() {
+    // Results written before prices were decimals store the price as a float
+    let balance: Balance = serde_json::from_str(r#"{
+        "token_address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        "balance": "0x2540be400",
+        "token_name": "USD Coin",
+        "token_symbol": "USDC",
+        "token_price": 0.9998,
+        "decimals": 6
+    }"#).unwrap();
+    assert_eq!(balance.amount().unwrap(), Decimal::new(10_000, 0));
+    assert_eq!(balance.value().unwrap(), Decimal::new(9_998, 0));
+    assert!(balance.price_source.is_none());
+}
+
+#[test]
+fn test_permit_domain() {
+    use alloy::sol_types::eip712_domain;
+
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    let expected = eip712_domain! {
+        name: "USD Coin",
+        version: "2",
+        chain_id: 1,
+        verifying_contract: usdc,
+    };
+    let domain = Web3Client::permit_domain("USD Coin", 1, usdc, expected.separator()).unwrap();
+    assert_eq!(domain.version.as_deref(), Some("2"));
+    assert!(Web3Client::permit_domain("USD Coin", 10, usdc, expected.separator()).is_none());
+}
+
+#[test]
+fn test_is_native_token() {
+    let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".parse().unwrap();
+    assert!(is_native_token(&NATIVE_TOKEN_ADDRESS.parse().unwrap()));
+    assert!(!is_native_token(&usdc));
+}
+
+#[test]
+fn test_approval_strategy_serde() {
+    assert_eq!(serde_json::from_str::<ApprovalStrategy>("\"permit2\"").unwrap(), ApprovalStrategy::Permit2);
+    assert_eq!(serde_json::to_string(&ApprovalStrategy::Unlimited).unwrap(), "\"unlimited\"");
+    assert_eq!(ApprovalStrategy::default(), ApprovalStrategy::Exact);
+}
+
+#[test]
+fn test_block_ranges() {
+    assert_eq!(block_ranges(0, 24_999, 10_000), vec![(0, 9_999), (10_000, 19_999), (20_000, 24_999)]);
+    assert_eq!(block_ranges(5, 5, 10_000), vec![(5, 5)]);
+    assert!(block_ranges(6, 5, 10_000).is_empty());
+    // A failed range is retried in tenths
+    assert_eq!(block_ranges(100, 119, (20 / LOG_RETRY_SPLIT).max(1)).len(), 10);
+}
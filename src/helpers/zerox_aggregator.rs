@@ -0,0 +1,129 @@
+use alloy::primitives::{Address, Bytes, U256};
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::Url;
+
+use crate::helpers::garbage_collector::TokenData;
+use crate::helpers::swap_provider::{self, QuoteMetrics, SwapOptions, SwapProvider, SwapQuote, SwapTx};
+use crate::helpers::web3_client::Network;
+
+static SUPPORTED_CHAIN_IDS: [u32; 11] = [1, 10, 56, 137, 5000, 8453, 42161, 43114, 59144, 81457, 534352];
+
+// 0x Swap API v2 through its AllowanceHolder contract, which takes plain approvals
+pub struct ZeroExAggregator {
+    network: Network,
+    api_key: String,
+    base_url: Url,
+}
+
+impl ZeroExAggregator {
+    pub fn new(network: Network, api_key: String) -> Result<Self> {
+        Ok(ZeroExAggregator {
+            network,
+            api_key,
+            base_url: Url::parse("https://api.0x.org/")?,
+        })
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+}
+
+#[async_trait]
+impl SwapProvider for ZeroExAggregator {
+    fn name(&self) -> &'static str {
+        "0x"
+    }
+
+    fn supports_network(&self) -> bool {
+        SUPPORTED_CHAIN_IDS.contains(&self.network.id)
+    }
+
+    // 0x quotes come with their transaction, so the quote is kept as the route
+    async fn quote(&self, sender: Address, tokens_in: &[(TokenData, U256)], token_out: &TokenData, options: &SwapOptions) -> Result<SwapQuote> {
+        let (token_in, amount_in) = swap_provider::single_input(self.name(), tokens_in)?;
+        let mut query = vec![
+            ("chainId", self.network.id.to_string()),
+            ("sellToken", swap_provider::api_address(&token_in.address).to_string()),
+            ("buyToken", swap_provider::api_address(&token_out.address).to_string()),
+            ("sellAmount", amount_in.to_string()),
+            ("taker", sender.to_string()),
+            ("slippageBps", options.slippage_bps().to_string()),
+        ];
+        if !options.source_blacklist.is_empty() {
+            query.push(("excludedSources", options.source_blacklist.join(",")));
+        }
+        let request = reqwest::Client::new()
+            .get(self.base_url.join("swap/allowance-holder/quote")?)
+            .header("0x-api-key", &self.api_key)
+            .header("0x-version", "v2")
+            .query(&query);
+        let json = swap_provider::send_json(request, "ZeroExAggregator:quote").await?;
+        if json["liquidityAvailable"].as_bool() == Some(false) {
+            return Err(eyre::eyre!("ZeroExAggregator:quote No liquidity for {}", token_in.symbol));
+        }
+
+        let spender = json["allowanceTarget"].as_str()
+            .or(json["issues"]["allowance"]["spender"].as_str())
+            .or(json["transaction"]["to"].as_str());
+        Ok(SwapQuote {
+            provider: self.name(),
+            tokens_in: tokens_in.to_vec(),
+            token_out: token_out.clone(),
+            out_amount: swap_provider::parse_amount(&json["buyAmount"])?,
+            gas: swap_provider::parse_amount(&json["transaction"]["gas"]).map(|g| g.to::<u64>()).unwrap_or_default(),
+            spender: match spender {
+                Some(s) => s.parse()?,
+                None => return Err(eyre::eyre!("ZeroExAggregator:quote Could not get approval target")),
+            },
+            in_values: vec![],
+            metrics: QuoteMetrics::default(),
+            route: json["transaction"].clone(),
+        })
+    }
+
+    async fn build_swap(&self, _sender: Address, quote: &SwapQuote, _options: &SwapOptions, _simulate: bool) -> Result<SwapTx> {
+        let tx = &quote.route;
+        Ok(SwapTx {
+            to: match tx["to"].as_str() {
+                Some(to) => to.parse()?,
+                None => return Err(eyre::eyre!("ZeroExAggregator:build_swap Quote has no transaction")),
+            },
+            data: tx["data"].as_str().unwrap_or_default().parse::<Bytes>()?,
+            value: swap_provider::parse_amount(&tx["value"])?,
+            gas: swap_provider::parse_amount(&tx["gas"]).ok().map(|g| g.to::<u128>()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_zerox_provider() {
+    let allowance_holder = "0x0000000000001fF3684f28c67538d4D072C22734";
+    let url = swap_provider::mock_server(vec![
+        ("/swap/allowance-holder/quote", serde_json::json!({
+            "liquidityAvailable": true,
+            "buyAmount": "410000000000000",
+            "minBuyAmount": "405000000000000",
+            "allowanceTarget": allowance_holder,
+            "issues": { "allowance": { "actual": "0", "spender": allowance_holder } },
+            "transaction": { "to": allowance_holder, "data": "0x2213bc0b", "gas": "164000", "gasPrice": "1000000", "value": "0" },
+        })),
+    ]).await;
+    let blast = swap_provider::test_network(81457, "Blast");
+    let aggregator = ZeroExAggregator::new(blast, "key".to_owned()).unwrap().with_base_url(url);
+    assert!(aggregator.supports_network());
+
+    let usdb = swap_provider::test_token("0x4300000000000000000000000000000000000003", "USDB", 18);
+    let eth = swap_provider::test_token("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE", "ETH", 18);
+    let options = SwapOptions::default();
+    let quote = aggregator.quote(Address::ZERO, &[(usdb, U256::from(1))], &eth, &options).await.unwrap();
+    assert_eq!(quote.out_amount, U256::from(410_000_000_000_000u64));
+    assert_eq!(quote.gas, 164_000);
+    assert_eq!(quote.spender, allowance_holder.parse::<Address>().unwrap());
+    let tx = aggregator.build_swap(Address::ZERO, &quote, &options, true).await.unwrap();
+    assert_eq!(tx.to, quote.spender);
+    assert_eq!(tx.gas, Some(164_000));
+    assert_eq!(tx.value, U256::ZERO);
+}
@@ -1,6 +1,12 @@
+use std::collections::HashMap;
+
 use alloy::{primitives::Address, signers::local::{PrivateKeySigner, LocalSigner}};
 use eyre::Result;
 use garbage_collector_rust::helpers::garbage_collector::GarbageCollector;
+use garbage_collector_rust::helpers::allowances::RevokeFilter;
+use garbage_collector_rust::helpers::consolidation::TokenFilter;
+use garbage_collector_rust::helpers::report::ReportFormat;
+use garbage_collector_rust::helpers::results_store::ResultsStore;
 use log::{error, info, warn};
 use garbage_collector_rust::helpers::utils::setup_logger;
 
@@ -8,11 +14,118 @@ enum Scenario {
     BalanceCheckerPK,
     BalanceCheckerAddressess,
     DisplayNonZeroTokens,
+    SweepDust,
+    SweepPlan,
+    SweepExecute,
+    Allowances,
+    Revoke,
+    Consolidate,
+    Forward,
+    Bridge,
+    #[cfg(feature = "sqlite")]
+    DbImport,
+    #[cfg(feature = "sqlite")]
+    DbTokenBalances,
+    #[cfg(feature = "sqlite")]
+    DbValueHistory,
+}
+
+impl Scenario {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "check-keys" => Ok(Scenario::BalanceCheckerPK),
+            "check-addresses" => Ok(Scenario::BalanceCheckerAddressess),
+            "display" => Ok(Scenario::DisplayNonZeroTokens),
+            "sweep" => Ok(Scenario::SweepDust),
+            "sweep-plan" => Ok(Scenario::SweepPlan),
+            "sweep-execute" => Ok(Scenario::SweepExecute),
+            "allowances" => Ok(Scenario::Allowances),
+            "revoke" => Ok(Scenario::Revoke),
+            "consolidate" => Ok(Scenario::Consolidate),
+            "forward" => Ok(Scenario::Forward),
+            "bridge" => Ok(Scenario::Bridge),
+            #[cfg(feature = "sqlite")]
+            "db-import" => Ok(Scenario::DbImport),
+            #[cfg(feature = "sqlite")]
+            "db-token" => Ok(Scenario::DbTokenBalances),
+            #[cfg(feature = "sqlite")]
+            "db-history" => Ok(Scenario::DbValueHistory),
+            s => Err(eyre::eyre!("Unknown scenario {}", s)),
+        }
+    }
+}
+
+static FLAGS: [&str; 2] = ["dry-run", "all"];
+static OPTIONS: [&str; 13] = [
+    "format", "output", "min-value", "keys", "addresses", "plan", "chain", "token", "spender", "to", "tokens", "to-chain", "wallet",
+];
+
+// Usage: garbage-collector-rust [check-keys|check-addresses|display] [--format text|csv|markdown|html|jsonl] [--output dir] [--min-value usd]
+//        garbage-collector-rust sweep --keys <file with one private key per line> [--dry-run]
+//        garbage-collector-rust sweep-plan --keys <file>, then sweep-execute --keys <file> --plan <plan file> [--dry-run]
+//        garbage-collector-rust allowances [--keys <file>|--addresses <file with one address per line>]
+//        garbage-collector-rust revoke --keys <file> [--chain <name>] [--token <address>] [--spender <address>] [--all] [--dry-run]
+//        garbage-collector-rust consolidate --keys <file> [--to <collector address>] [--dry-run]
+//        garbage-collector-rust forward --keys <file> --tokens <symbols or addresses, comma separated> [--chain <name>] [--to <address>] [--dry-run]
+//        garbage-collector-rust bridge --keys <file> [--to-chain <name>] [--dry-run]
+// With the sqlite feature: garbage-collector-rust [db-import|db-token --token <symbol or address>|db-history --wallet <address>]
+fn parse_args(args: &[String]) -> Result<(Scenario, HashMap<String, String>)> {
+    let mut scenario = Scenario::BalanceCheckerAddressess;
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            // Flags without a value
+            Some(key) if FLAGS.contains(&key) => { options.insert(key.to_owned(), "true".to_owned()); }
+            Some(key) if !OPTIONS.contains(&key) => return Err(eyre::eyre!("Unknown option --{}", key)),
+            Some(key) => match args.next() {
+                Some(value) => { options.insert(key.to_owned(), value.clone()); }
+                None => return Err(eyre::eyre!("Missing value for --{}", key)),
+            },
+            None => scenario = Scenario::parse(arg)?,
+        }
+    }
+    Ok((scenario, options))
+}
+
+// Non-empty lines of a text file, without surrounding whitespace
+fn read_lines(path: &str) -> Result<Vec<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+// Signers from the --keys file, skipping keys that don't parse
+fn read_signers(options: &HashMap<String, String>) -> Result<Vec<PrivateKeySigner>> {
+    let keys_vec = match options.get("keys") {
+        Some(path) => read_lines(path)?,
+        None => return Err(eyre::eyre!("Sweeping needs --keys")),
+    };
+    let mut signers = vec![];
+    for key in keys_vec {
+        match key.parse::<PrivateKeySigner>() {
+            Ok(signer) => signers.push(signer),
+            Err(e) => error!("Error parsing private key: {:?}", e),
+        }
+    }
+    Ok(signers)
+}
+
+fn configure_report(garbage_collector: &mut GarbageCollector, options: &HashMap<String, String>) -> Result<()> {
+    let format = ReportFormat::parse(options.get("format").map(|f| f.as_str()).unwrap_or_default())?;
+    garbage_collector.set_report_format(format, options.get("output").cloned());
+    if let Some(min_value) = options.get("min-value") {
+        garbage_collector.set_dust_threshold(min_value.parse()?);
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let scenario = Scenario::BalanceCheckerAddressess;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (scenario, options) = parse_args(&args)?;
 
     dotenv::dotenv().ok();
     setup_logger().unwrap();
@@ -33,6 +146,7 @@ async fn main() -> Result<()> {
             }
 
             let mut garbage_collector = GarbageCollector::new();
+            configure_report(&mut garbage_collector, &options)?;
             for key in keys_vec {
                 let parsed_signer: PrivateKeySigner = match key.parse() {
                     Ok(signer) => signer,
@@ -62,7 +176,8 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
 
-            let garbage_collector = GarbageCollector::new();
+            let mut garbage_collector = GarbageCollector::new();
+            configure_report(&mut garbage_collector, &options)?;
             for address in addresses_vec {
                 let parsed_address: Address = match address.parse() {
                     Ok(address) => address,
@@ -79,10 +194,205 @@ async fn main() -> Result<()> {
         Scenario::DisplayNonZeroTokens => {
             info!("Display Non Zero Tokens");
 
-            if let Err(e) = GarbageCollector::read_all_non_zero_balances() {
+            let mut garbage_collector = GarbageCollector::new();
+            configure_report(&mut garbage_collector, &options)?;
+            if let Err(e) = garbage_collector.read_all_non_zero_balances().await {
                 error!("Error reading non zero balances: {:?}", e);
             };
         }
+        Scenario::SweepDust => {
+            info!("Sweep Dust To Native");
+
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                if let Err(e) = garbage_collector.sweep_dust().await {
+                    error!("Error sweeping {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        Scenario::SweepPlan => {
+            info!("Plan Dust Sweep");
+
+            let mut garbage_collector = GarbageCollector::new();
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                if let Err(e) = garbage_collector.plan_sweep().await {
+                    error!("Error planning sweep of {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        Scenario::SweepExecute => {
+            info!("Execute Dust Sweep Plan");
+
+            let plan = match options.get("plan") {
+                Some(path) => ResultsStore::load_plan(path)?,
+                None => return Err(eyre::eyre!("sweep-execute needs --plan")),
+            };
+            let wallet: Address = plan.wallet.parse()?;
+            let signer = match read_signers(&options)?.into_iter().find(|s| s.address() == wallet) {
+                Some(signer) => signer,
+                None => return Err(eyre::eyre!("No key in --keys for plan wallet {}", plan.wallet)),
+            };
+
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            garbage_collector.connect_signer(signer);
+            if let Err(e) = garbage_collector.execute_sweep_plan(&plan).await {
+                error!("Error executing sweep plan of {}: {:?}", plan.wallet, e);
+            }
+        }
+        Scenario::Allowances => {
+            info!("Allowance Audit");
+
+            let wallets: Vec<Address> = match options.get("addresses") {
+                Some(path) => read_lines(path)?.iter().map(|a| a.parse()).collect::<Result<_, _>>()?,
+                None => read_signers(&options)?.iter().map(|s| s.address()).collect(),
+            };
+            let garbage_collector = GarbageCollector::new();
+            for wallet in wallets {
+                if let Err(e) = garbage_collector.audit_allowances(wallet).await {
+                    error!("Error auditing allowances of {}: {:?}", wallet, e);
+                }
+            }
+        }
+        Scenario::Revoke => {
+            info!("Revoke Allowances");
+
+            let filter = RevokeFilter {
+                chain: options.get("chain").cloned(),
+                token: options.get("token").map(|t| t.parse()).transpose()?,
+                spender: options.get("spender").map(|s| s.parse()).transpose()?,
+                all: options.contains_key("all"),
+            };
+            if filter.chain.is_none() && filter.token.is_none() && filter.spender.is_none() && !filter.all {
+                return Err(eyre::eyre!("revoke needs --chain, --token, --spender or --all"));
+            }
+
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                let report = match garbage_collector.audit_allowances(signer_address).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        error!("Error auditing allowances of {}: {:?}", signer_address, e);
+                        continue;
+                    }
+                };
+                let selected: Vec<_> = report.entries.into_iter().filter(|e| filter.matches(e)).collect();
+                if let Err(e) = garbage_collector.revoke_allowances(&selected).await {
+                    error!("Error revoking allowances of {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        Scenario::Consolidate => {
+            info!("Consolidate Native Balances");
+
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            if let Some(to) = options.get("to") {
+                garbage_collector.set_collector(to.parse()?);
+            }
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                if let Err(e) = garbage_collector.consolidate_native().await {
+                    error!("Error consolidating {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        Scenario::Forward => {
+            info!("Forward Tokens");
+
+            let filter = TokenFilter {
+                tokens: match options.get("tokens") {
+                    Some(tokens) => tokens.split(',').map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()).collect(),
+                    None => return Err(eyre::eyre!("forward needs --tokens")),
+                },
+                chain: options.get("chain").cloned(),
+            };
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            if let Some(to) = options.get("to") {
+                garbage_collector.set_collector(to.parse()?);
+            }
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                if let Err(e) = garbage_collector.forward_tokens(&filter).await {
+                    error!("Error forwarding tokens of {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        Scenario::Bridge => {
+            info!("Bridge Native Balances");
+
+            let mut garbage_collector = GarbageCollector::new();
+            if options.contains_key("dry-run") {
+                garbage_collector.set_dry_run(true);
+            }
+            if let Some(chain) = options.get("to-chain") {
+                garbage_collector.set_bridge_target(chain.clone());
+            }
+            for signer in read_signers(&options)? {
+                let signer_address = signer.address();
+                garbage_collector.connect_signer(signer);
+                if let Err(e) = garbage_collector.bridge_native().await {
+                    error!("Error bridging {}: {:?}", signer_address, e);
+                }
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        Scenario::DbImport => {
+            info!("Import JSON Results Into Database");
+
+            let garbage_collector = GarbageCollector::new();
+            match garbage_collector.import_results_to_db() {
+                Ok(count) => info!("Imported {} scans", count),
+                Err(e) => error!("Error importing results: {:?}", e),
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        Scenario::DbTokenBalances => {
+            let token = match options.get("token") {
+                Some(t) => t,
+                None => return Err(eyre::eyre!("db-token needs --token")),
+            };
+            info!("Balances Of {} Across Wallets", token);
+
+            let garbage_collector = GarbageCollector::new();
+            if let Err(e) = garbage_collector.print_token_balances(token).await {
+                error!("Error querying token balances: {:?}", e);
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        Scenario::DbValueHistory => {
+            let wallet = match options.get("wallet") {
+                Some(w) => w,
+                None => return Err(eyre::eyre!("db-history needs --wallet")),
+            };
+            info!("Value History Of {}", wallet);
+
+            let garbage_collector = GarbageCollector::new();
+            if let Err(e) = garbage_collector.print_value_history(wallet).await {
+                error!("Error querying value history: {:?}", e);
+            }
+        }
     }
 
     Ok(())